@@ -0,0 +1,174 @@
+use std::fmt::Display;
+
+use super::span::Span;
+
+/// Error codes, grouped by the stage that reports them.
+pub mod codes {
+    // Parsing
+    pub const SYNTAX: &str = "E0001";
+    pub const MULTIPLE_ENTRY_POINTS: &str = "E0002";
+    pub const MISSING_ENTRY_POINT: &str = "E0003";
+
+    // Name resolution and typing
+    pub const UNKNOWN_VARIABLE: &str = "E0100";
+    pub const UNKNOWN_FUNCTION: &str = "E0101";
+    pub const UNKNOWN_TYPE: &str = "E0102";
+    pub const UNKNOWN_FIELD: &str = "E0103";
+    pub const UNKNOWN_VARIANT: &str = "E0104";
+    pub const MISSING_FIELD: &str = "E0105";
+    pub const NOT_A_RECORD: &str = "E0106";
+    pub const NOT_AN_ENUM: &str = "E0107";
+    pub const TYPE_MISMATCH: &str = "E0108";
+    pub const INFINITE_TYPE: &str = "E0109";
+    pub const CANNOT_INFER: &str = "E0110";
+    pub const WRONG_ARGUMENT_COUNT: &str = "E0111";
+    pub const NOT_A_FUNCTION: &str = "E0112";
+    pub const LITERAL_OUT_OF_RANGE: &str = "E0113";
+    pub const NON_EXHAUSTIVE: &str = "E0114";
+    pub const NOT_RENDERABLE: &str = "E0115";
+    pub const DUPLICATE_FIELD: &str = "E0116";
+    pub const INVALID_OPERAND: &str = "E0117";
+    pub const UNKNOWN_TRAIT: &str = "E0118";
+    pub const NOT_IMPLEMENTED: &str = "E0119";
+    pub const MISSING_METHOD: &str = "E0120";
+    pub const DUPLICATE_IMPLEMENTATION: &str = "E0121";
+    pub const INCOMPATIBLE_BUILTIN: &str = "E0122";
+    pub const MISSING_IMPLEMENTATION: &str = "E0123";
+    pub const DUPLICATE_DECLARATION: &str = "E0124";
+
+    // Warnings
+    pub const UNREACHABLE_PATTERN: &str = "W0001";
+
+    // Code generation
+    pub const UNSUPPORTED: &str = "E0200";
+    pub const INTERNAL: &str = "E0201";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelStyle {
+    /// The offending code.
+    Primary,
+    /// Related code, e.g. the other side of a type mismatch.
+    Secondary,
+}
+
+/// A message attached to a region of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub style: LabelStyle,
+    pub span: Span,
+    pub message: String,
+}
+
+/// A problem found while compiling, reported to the user instead of panicking.
+///
+/// At most one label is primary; it is kept first in `labels`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Label {
+    pub fn new(style: LabelStyle, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            style,
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub const fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Sets the primary label, replacing any previous one.
+    pub fn with_primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.retain(|l| l.style != LabelStyle::Primary);
+        self.labels
+            .insert(0, Label::new(LabelStyle::Primary, span, message));
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels
+            .push(Label::new(LabelStyle::Secondary, span, message));
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn primary(&self) -> Option<&Label> {
+        self.labels
+            .first()
+            .filter(|l| l.style == LabelStyle::Primary)
+    }
+
+    pub fn secondary(&self) -> impl Iterator<Item = &Label> {
+        self.labels
+            .iter()
+            .filter(|l| l.style == LabelStyle::Secondary)
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// One-line rendering, for contexts without access to the source text.
+/// The CLI renders diagnostics with annotated snippets instead.
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = self.code {
+            write!(f, "[{code}]")?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(label) = self.primary() {
+            write!(f, " at {}", label.span)?;
+        }
+        Ok(())
+    }
+}
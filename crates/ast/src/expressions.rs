@@ -1,11 +1,19 @@
 use std::fmt::Debug;
 
-use super::{patterns::Pattern, statements::Block, types::Type};
+use super::{patterns::Pattern, span::Span, statements::Block, types::Type};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Expression {
     pub kind: ExpressionKind,
     pub associated_type: Option<AssociatedType>,
+    pub span: Span,
+}
+
+/// Spans are ignored, so hand-built trees compare equal to parsed ones.
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.associated_type == other.associated_type
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,7 +40,29 @@ pub enum ExpressionKind {
         name: String,
         arguments: Vec<Expression>,
     },
+    /// A function called on a value, found in the namespace of its type by
+    /// inference, which turns it into a `FunctionCall`.
+    /// Example: `opt.map(f)`, that is `Option::map(opt, f)`
+    MethodCall {
+        receiver: Box<Expression>,
+        name: String,
+        arguments: Vec<Expression>,
+    },
     Match(Box<Expression>, Vec<MatchArm>),
+    /// A condition and the blocks evaluated when it holds or not. An
+    /// `else if` is an `If` alone in the else block.
+    /// Example: `if a > b { a } else { b }`
+    If(Box<Expression>, Box<Block>, Box<Block>),
+    /// Example: `[1, 2, 3]`
+    List(Vec<Expression>),
+    /// Two or more values. Example: `(1, True)`
+    Tuple(Vec<Expression>),
+    /// An anonymous function: its parameters and body.
+    /// Example: `(x) -> x + offset`
+    Lambda(Vec<String>, Box<Expression>),
+    /// A string with expressions in it.
+    /// Example: `"Hello, {person.name}"`
+    Interpolation(Vec<StringPart>),
     BinaryOp(Box<Expression>, BinaryOp, Box<Expression>),
     UnaryOp(UnaryOp, Box<Expression>),
     Unit,
@@ -58,10 +88,20 @@ impl Expression {
         self.associated_type = Some(ty)
     }
 
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
     pub const fn unit() -> Self {
         Self {
             kind: ExpressionKind::Unit,
             associated_type: Some(AssociatedType::Concrete(Type::Unit)),
+            span: Span::DUMMY,
         }
     }
 
@@ -69,6 +109,7 @@ impl Expression {
         Self {
             kind: ExpressionKind::Identifier(identifier),
             associated_type: None,
+            span: Span::DUMMY,
         }
     }
 
@@ -76,6 +117,7 @@ impl Expression {
         Self {
             kind: ExpressionKind::Literal(literal),
             associated_type: None,
+            span: Span::DUMMY,
         }
     }
 
@@ -83,6 +125,7 @@ impl Expression {
         Self {
             kind: ExpressionKind::NewEnumInstance(enum_name.to_owned(), variant_name, fields),
             associated_type: Some(AssociatedType::Concrete(Type::Custom(enum_name, vec![]))),
+            span: Span::DUMMY,
         }
     }
 
@@ -91,6 +134,7 @@ impl Expression {
         Self {
             kind: ExpressionKind::NewRecordInstance(record_name, fields),
             associated_type: Some(AssociatedType::Concrete(associated_type)),
+            span: Span::DUMMY,
         }
     }
 
@@ -98,6 +142,7 @@ impl Expression {
         Self {
             kind: ExpressionKind::RecordAccess(record_name, field_name),
             associated_type: None,
+            span: Span::DUMMY,
         }
     }
 
@@ -117,6 +162,19 @@ impl Expression {
                 arguments: args,
             },
             associated_type: None,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub fn method_call(receiver: Self, name: String, args: Vec<Self>) -> Self {
+        Self {
+            kind: ExpressionKind::MethodCall {
+                receiver: Box::new(receiver),
+                name,
+                arguments: args,
+            },
+            associated_type: None,
+            span: Span::DUMMY,
         }
     }
 
@@ -124,6 +182,47 @@ impl Expression {
         Self {
             kind: ExpressionKind::Match(Box::new(expr), arms),
             associated_type: None,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub fn if_expr(condition: Self, then: Block, otherwise: Block) -> Self {
+        Self {
+            kind: ExpressionKind::If(Box::new(condition), Box::new(then), Box::new(otherwise)),
+            associated_type: None,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub fn lambda(params: Vec<String>, body: Self) -> Self {
+        Self {
+            kind: ExpressionKind::Lambda(params, Box::new(body)),
+            associated_type: None,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub const fn list(elements: Vec<Self>) -> Self {
+        Self {
+            kind: ExpressionKind::List(elements),
+            associated_type: None,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub const fn tuple(elements: Vec<Self>) -> Self {
+        Self {
+            kind: ExpressionKind::Tuple(elements),
+            associated_type: None,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub const fn interpolation(parts: Vec<StringPart>) -> Self {
+        Self {
+            kind: ExpressionKind::Interpolation(parts),
+            associated_type: None,
+            span: Span::DUMMY,
         }
     }
 
@@ -131,6 +230,7 @@ impl Expression {
         Self {
             kind: ExpressionKind::BinaryOp(Box::new(lhs), op, Box::new(rhs)),
             associated_type: None,
+            span: Span::DUMMY,
         }
     }
 
@@ -138,6 +238,7 @@ impl Expression {
         Self {
             kind: ExpressionKind::UnaryOp(op, Box::new(expr)),
             associated_type: None,
+            span: Span::DUMMY,
         }
     }
 }
@@ -178,6 +279,36 @@ pub enum BinaryOp {
     GreaterEq,
 }
 
+impl BinaryOp {
+    /// Whether the operator compares its operands, giving a `Bool`.
+    pub const fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Self::Eq | Self::NotEq | Self::Less | Self::LessEq | Self::Greater | Self::GreaterEq
+        )
+    }
+
+    /// The operator as written: `+`, `==`...
+    pub const fn symbol(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Xor => "^",
+            Self::Eq => "==",
+            Self::NotEq => "!=",
+            Self::Less => "<",
+            Self::LessEq => "<=",
+            Self::Greater => ">",
+            Self::GreaterEq => ">=",
+        }
+    }
+}
+
 /// TODO: Add more operators
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UnaryOp {
@@ -187,6 +318,24 @@ pub enum UnaryOp {
     Not,
 }
 
+impl UnaryOp {
+    /// The operator as written: `-` or `!`.
+    pub const fn symbol(&self) -> &'static str {
+        match self {
+            Self::Negate => "-",
+            Self::Not => "!",
+        }
+    }
+}
+
+/// A piece of an interpolated string: text, or a `{hole}` whose value is
+/// rendered in its place.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Hole(Expression),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchArm {
     pub pattern: Pattern,
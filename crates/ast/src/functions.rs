@@ -1,7 +1,9 @@
 use super::{
     expressions::Expression,
     patterns::Pattern,
+    span::Span,
     statements::Block,
+    traits::Bound,
     types::{FunctionSignature, Type},
 };
 
@@ -39,7 +41,10 @@ pub struct FunctionDeclaration {
     name: QualifiedName,
     /// Declared type variables, e.g. `<A, B>` in `map<A, B>([A], f: (A) -> B) -> [B];`
     type_params: Vec<String>,
+    /// Traits the type parameters must implement: `<A: Show>`
+    bounds: Vec<Bound>,
     signature: FunctionSignature,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +52,7 @@ pub struct FunctionImplementation {
     name: QualifiedName,
     arguments: Vec<Pattern>,
     body: FunctionBody,
+    span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -64,7 +70,9 @@ impl FunctionDeclaration {
         Self {
             name,
             type_params,
+            bounds: vec![],
             signature,
+            span: Span::DUMMY,
         }
     }
 
@@ -72,10 +80,81 @@ impl FunctionDeclaration {
         Self {
             name: QualifiedName::unqualified("main"),
             type_params: vec![],
+            bounds: vec![],
             signature: FunctionSignature::new(vec![], ty.to_owned()),
+            span: Span::DUMMY,
         }
     }
 
+    /// Functions provided by the compiler rather than the program.
+    pub fn builtins() -> Vec<Self> {
+        let string = |name: &str, arguments, return_type| {
+            Self::new(
+                QualifiedName::new(Some("String".to_owned()), name),
+                vec![],
+                FunctionSignature::new(arguments, return_type),
+            )
+        };
+        let list = |name: &str, type_params: &[&str], arguments, return_type| {
+            Self::new(
+                QualifiedName::new(Some("List".to_owned()), name),
+                type_params
+                    .iter()
+                    .map(|param| (*param).to_owned())
+                    .collect(),
+                FunctionSignature::new(arguments, return_type),
+            )
+        };
+        let param = |name: &str| Type::Custom(name.to_owned(), vec![]);
+        let function =
+            |arguments, return_type| Type::Function(FunctionSignature::new(arguments, return_type));
+        let (a, b) = (param("A"), param("B"));
+        let int = |name: &str, return_type| {
+            Self::new(
+                QualifiedName::new(Some("Int".to_owned()), name),
+                vec!["A".to_owned()],
+                FunctionSignature::new(vec![a.clone(), a.clone()], return_type),
+            )
+        };
+        // `Int::checked_*` give `None` when the operation overflows, or
+        // divides by zero; `Int::wrapping_*` wrap around.
+        let checked = Type::Custom("Option".to_owned(), vec!["A".to_owned()]);
+        vec![
+            int("checked_add", checked.clone()),
+            int("checked_sub", checked.clone()),
+            int("checked_mul", checked.clone()),
+            int("checked_div", checked),
+            int("wrapping_add", a.clone()),
+            int("wrapping_sub", a.clone()),
+            int("wrapping_mul", a.clone()),
+            string("concat", vec![Type::String, Type::String], Type::String),
+            string("length", vec![Type::String], Type::U64),
+            list("length", &["A"], vec![Type::list(a.clone())], Type::U64),
+            list(
+                "map",
+                &["A", "B"],
+                vec![Type::list(a.clone()), function(vec![a.clone()], b.clone())],
+                Type::list(b.clone()),
+            ),
+            list(
+                "filter",
+                &["A"],
+                vec![Type::list(a.clone()), function(vec![a.clone()], Type::Bool)],
+                Type::list(a.clone()),
+            ),
+            list(
+                "fold",
+                &["A", "B"],
+                vec![
+                    Type::list(a.clone()),
+                    b.clone(),
+                    function(vec![b.clone(), a], b.clone()),
+                ],
+                b,
+            ),
+        ]
+    }
+
     pub const fn qualified_name(&self) -> &QualifiedName {
         &self.name
     }
@@ -88,9 +167,27 @@ impl FunctionDeclaration {
         &self.type_params
     }
 
+    pub fn bounds(&self) -> &[Bound] {
+        &self.bounds
+    }
+
+    pub fn with_bounds(mut self, bounds: Vec<Bound>) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
     pub const fn signature(&self) -> &FunctionSignature {
         &self.signature
     }
+
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
 impl FunctionImplementation {
@@ -99,6 +196,7 @@ impl FunctionImplementation {
             name,
             arguments,
             body,
+            span: Span::DUMMY,
         }
     }
 
@@ -108,6 +206,7 @@ impl FunctionImplementation {
             name: QualifiedName::unqualified("main"),
             arguments: vec![],
             body,
+            span: Span::DUMMY,
         }
     }
 
@@ -126,4 +225,17 @@ impl FunctionImplementation {
     pub const fn body(&self) -> &FunctionBody {
         &self.body
     }
-}
\ No newline at end of file
+
+    pub const fn body_mut(&mut self) -> &mut FunctionBody {
+        &mut self.body
+    }
+
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
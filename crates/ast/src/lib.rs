@@ -1,7 +1,10 @@
+pub mod diagnostics;
 pub mod expressions;
 pub mod functions;
 pub mod patterns;
 pub mod program;
+pub mod span;
 pub mod statements;
 pub mod top_level;
+pub mod traits;
 pub mod types;
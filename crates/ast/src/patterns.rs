@@ -1,4 +1,4 @@
-use super::expressions::Literal;
+use super::{expressions::Literal, span::Span};
 
 /// A pattern, as used in match arms and function implementation parameters.
 ///
@@ -6,8 +6,21 @@ use super::expressions::Literal;
 /// subset. (`1 + 2 -> x` is not a valid pattern.)
 ///
 /// Grammar reference (`grammar_optimized.pest`): `Pattern`
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+/// Spans are ignored, so hand-built patterns compare equal to parsed ones.
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Pattern {
+pub enum PatternKind {
     /// `_` — matches anything, binds nothing
     Wildcard,
     /// `x` — matches anything, binds it to a name
@@ -18,8 +31,86 @@ pub enum Pattern {
     EnumInstance {
         enum_name: String,
         variant_name: String,
-        args: Vec<Self>,
+        args: Vec<Pattern>,
+    },
+    /// `[]`
+    EmptyList,
+    /// A list of at least one element: its first, and the list of the
+    /// others. `[a, b]` is `a` followed by `[b]`, itself `b` followed by
+    /// `[]`, and `[a, ..rest]` is `a` followed by `rest`.
+    Cons {
+        head: Box<Pattern>,
+        tail: Box<Pattern>,
     },
-    // TODO: StructPat (`Client { name, age }`) and TuplePat (`(a, b)`) once
-    // records/tuples are settled on the expression side.
+    /// `(0, x)`
+    Tuple(Vec<Pattern>),
+    /// `Person { name, age: 30, .. }`. A field without a pattern, like
+    /// `name`, binds it to its name. `rest` is whether there's a `..`,
+    /// ignoring the fields left out.
+    Record {
+        type_name: String,
+        fields: Vec<(String, Pattern)>,
+        rest: bool,
+    },
+}
+
+impl Pattern {
+    pub const fn new(kind: PatternKind) -> Self {
+        Self {
+            kind,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub const fn wildcard() -> Self {
+        Self::new(PatternKind::Wildcard)
+    }
+
+    pub const fn identifier(name: String) -> Self {
+        Self::new(PatternKind::Identifier(name))
+    }
+
+    pub const fn literal(literal: Literal) -> Self {
+        Self::new(PatternKind::Literal(literal))
+    }
+
+    pub const fn enum_instance(enum_name: String, variant_name: String, args: Vec<Self>) -> Self {
+        Self::new(PatternKind::EnumInstance {
+            enum_name,
+            variant_name,
+            args,
+        })
+    }
+
+    pub const fn tuple(elements: Vec<Self>) -> Self {
+        Self::new(PatternKind::Tuple(elements))
+    }
+
+    pub const fn record(type_name: String, fields: Vec<(String, Self)>, rest: bool) -> Self {
+        Self::new(PatternKind::Record {
+            type_name,
+            fields,
+            rest,
+        })
+    }
+
+    /// `[elements]`, or `[elements, ..rest]` when there's a `rest`.
+    pub fn list(elements: Vec<Self>, rest: Option<Self>) -> Self {
+        let end = rest.unwrap_or_else(|| Self::new(PatternKind::EmptyList));
+        elements.into_iter().rev().fold(end, |tail, head| {
+            Self::new(PatternKind::Cons {
+                head: Box::new(head),
+                tail: Box::new(tail),
+            })
+        })
+    }
+
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
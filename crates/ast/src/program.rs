@@ -1,7 +1,10 @@
 use super::{
+    diagnostics::{Diagnostic, codes},
     functions::{FunctionDeclaration, FunctionImplementation},
+    span::Span,
     statements::Block,
     top_level::TopLevel,
+    traits::{TraitDeclaration, TraitImplementation},
     types::CustomType,
 };
 
@@ -11,36 +14,62 @@ pub struct Program {
     pub function_declarations: Vec<FunctionDeclaration>,
     pub function_implementations: Vec<FunctionImplementation>,
     pub type_definitions: Vec<CustomType>,
+    pub traits: Vec<TraitDeclaration>,
+    /// Type inference turns the methods of implementations into functions,
+    /// leaving this empty.
+    pub trait_implementations: Vec<TraitImplementation>,
     pub entry_point: Block,
 }
 
 impl Program {
-    pub fn from_top_levels(top_levels: Vec<TopLevel>) -> Self {
+    /// # Errors
+    /// When there isn't exactly one `main` block.
+    pub fn from_top_levels(top_levels: Vec<TopLevel>) -> Result<Self, Vec<Diagnostic>> {
         let mut function_declarations: Vec<FunctionDeclaration> = vec![];
         let mut function_implementations: Vec<FunctionImplementation> = vec![];
         let mut type_definitions: Vec<CustomType> = vec![];
-        let mut entry_point: Option<Block> = None;
+        let mut traits: Vec<TraitDeclaration> = vec![];
+        let mut trait_implementations: Vec<TraitImplementation> = vec![];
+        let mut entry_point: Option<(Block, Span)> = None;
+        let mut diagnostics = vec![];
 
         for top_level in top_levels {
             match top_level {
                 TopLevel::FunctionDefinition(fd) => function_declarations.push(fd),
                 TopLevel::FunctionImplementation(fi) => function_implementations.push(fi),
                 TopLevel::CustomType(ct) => type_definitions.push(ct),
-                TopLevel::EntryPoint(ep) => {
-                    if entry_point.is_some() {
-                        panic!("Multiple entry points found");
-                    }
-                    entry_point = Some(ep);
-                }
+                TopLevel::Trait(td) => traits.push(td),
+                TopLevel::TraitImplementation(ti) => trait_implementations.push(ti),
+                TopLevel::EntryPoint(ep, span) => match &entry_point {
+                    Some((_, first)) => diagnostics.push(
+                        Diagnostic::error("multiple entry points found")
+                            .with_code(codes::MULTIPLE_ENTRY_POINTS)
+                            .with_primary(span, "second `main` block")
+                            .with_secondary(*first, "first `main` block"),
+                    ),
+                    None => entry_point = Some((ep, span)),
+                },
             }
         }
-        let entry_point = entry_point.expect("No entry point found");
+        let Some((entry_point, _)) = entry_point else {
+            diagnostics.push(
+                Diagnostic::error("no entry point found")
+                    .with_code(codes::MISSING_ENTRY_POINT)
+                    .with_note("add a `main { ... }` block"),
+            );
+            return Err(diagnostics);
+        };
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
 
-        Self {
+        Ok(Self {
             function_declarations,
             function_implementations,
             type_definitions,
+            traits,
+            trait_implementations,
             entry_point,
-        }
+        })
     }
 }
@@ -0,0 +1,84 @@
+use std::{fmt::Display, ops::Range};
+
+/// Identifies a source file. Spans only store the id; the text and name of the
+/// file live wherever the driver keeps them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(pub u32);
+
+/// A byte-offset range into a source file.
+/// Example: in `x = 1 + 2;`, the expression `1 + 2` spans `4..9`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Placeholder for nodes that don't come from source, e.g. the synthesized
+    /// `main` declaration.
+    pub const DUMMY: Self = Self::new(FileId(0), 0, 0);
+
+    pub const fn new(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    pub const fn from_range(file: FileId, range: Range<usize>) -> Self {
+        Self::new(file, range.start, range.end)
+    }
+
+    pub const fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub const fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Whether `other` lies within `self`.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.file.0 == other.file.0 && self.start <= other.start && other.end <= self.end
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    /// Example: the span of `a + b` is `a.span.to(b.span)`
+    pub fn to(self, other: Self) -> Self {
+        Self::new(
+            self.file,
+            self.start.min(other.start),
+            self.end.max(other.end),
+        )
+    }
+}
+
+/// Zero-based line and column, in characters, of byte `offset` in `text`.
+/// Offsets past the end of the text are those of its end.
+pub fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let line = before.matches('\n').count();
+    (line, before[line_start..].chars().count())
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_col() {
+        let text = "main {\n    1 / 0\n}";
+        assert_eq!(line_col(text, 0), (0, 0));
+        assert_eq!(line_col(text, 13), (1, 6));
+        assert_eq!(line_col(text, 100), (2, 1));
+    }
+}
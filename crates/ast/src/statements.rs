@@ -1,14 +1,63 @@
-use super::expressions::Expression;
+use super::{
+    expressions::{Expression, ExpressionKind},
+    span::Span,
+    types::Type,
+};
 
 /// Statements are the building blocks of a block.
 /// Example: `my_value = 1 + 2 * 3;`
 /// Example: `return 1 + 2 * 3;`
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+/// Spans are ignored, so hand-built statements compare equal to parsed ones.
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement {
-    Assignment(String, Expression),
+pub enum StatementKind {
+    /// `name = value;`, or `name: Type = value;` with a type annotation.
+    Assignment(String, Option<Type>, Expression),
     Return(Expression),
 }
 
+impl Statement {
+    pub const fn new(kind: StatementKind) -> Self {
+        Self {
+            kind,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub const fn assignment(var_name: String, expr: Expression) -> Self {
+        Self::new(StatementKind::Assignment(var_name, None, expr))
+    }
+
+    /// `var_name: ty = expr;`
+    pub const fn typed_assignment(var_name: String, ty: Type, expr: Expression) -> Self {
+        Self::new(StatementKind::Assignment(var_name, Some(ty), expr))
+    }
+
+    pub const fn return_(expr: Expression) -> Self {
+        Self::new(StatementKind::Return(expr))
+    }
+
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
 /// Blocks contain statements and return an expression.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Block {
@@ -34,4 +83,17 @@ impl Block {
     pub fn statements(&self) -> &[Statement] {
         &self.statements
     }
+
+    /// Whether the block ends in a `return` statement, with nothing after it:
+    /// it never gives a value of its own.
+    pub fn ends_in_return(&self) -> bool {
+        matches!(
+            self.statements.last(),
+            Some(Statement {
+                kind: StatementKind::Return(_),
+                ..
+            })
+        ) && self.return_expr.kind == ExpressionKind::Unit
+            && self.return_expr.span() == Span::DUMMY
+    }
 }
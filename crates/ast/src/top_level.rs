@@ -1,6 +1,8 @@
 use super::{
     functions::{FunctionDeclaration, FunctionImplementation},
+    span::Span,
     statements::Block,
+    traits::{TraitDeclaration, TraitImplementation},
     types::CustomType,
 };
 
@@ -9,5 +11,8 @@ pub enum TopLevel {
     FunctionDefinition(FunctionDeclaration),
     FunctionImplementation(FunctionImplementation),
     CustomType(CustomType),
-    EntryPoint(Block),
+    Trait(TraitDeclaration),
+    TraitImplementation(TraitImplementation),
+    /// The `main` block, and the span of the whole `main { ... }` item
+    EntryPoint(Block, Span),
 }
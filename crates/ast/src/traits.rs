@@ -0,0 +1,120 @@
+use super::{
+    functions::{FunctionDeclaration, FunctionImplementation, QualifiedName},
+    span::Span,
+};
+
+/// The type implementing a trait, in the signatures of its methods.
+pub const SELF: &str = "Self";
+
+/// A set of methods types can implement.
+///
+/// Grammar reference: `Trait = "trait" TypeIdentifier "{" FunctionDeclaration* "}"`
+#[derive(Debug, Clone)]
+pub struct TraitDeclaration {
+    name: String,
+    methods: Vec<FunctionDeclaration>,
+    span: Span,
+}
+
+/// The methods of a trait for a type.
+///
+/// Grammar reference: `Impl = "impl" TypeIdentifier "for" TypeIdentifier "{" FunctionImplementation* "}"`
+#[derive(Debug, Clone)]
+pub struct TraitImplementation {
+    trait_name: String,
+    type_name: String,
+    methods: Vec<FunctionImplementation>,
+    span: Span,
+}
+
+/// A type parameter that must implement a trait: `A: Show`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bound {
+    pub param: String,
+    pub trait_name: String,
+}
+
+impl TraitDeclaration {
+    pub fn new(name: &str, methods: Vec<FunctionDeclaration>) -> Self {
+        Self {
+            name: name.to_owned(),
+            methods,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn methods(&self) -> &[FunctionDeclaration] {
+        &self.methods
+    }
+
+    /// The declaration of `method`, and its position among the methods.
+    pub fn method(&self, method: &str) -> Option<(usize, &FunctionDeclaration)> {
+        self.methods
+            .iter()
+            .enumerate()
+            .find(|(_, fd)| fd.name() == method)
+    }
+
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl TraitImplementation {
+    pub fn new(trait_name: &str, type_name: &str, methods: Vec<FunctionImplementation>) -> Self {
+        Self {
+            trait_name: trait_name.to_owned(),
+            type_name: type_name.to_owned(),
+            methods,
+            span: Span::DUMMY,
+        }
+    }
+
+    pub fn trait_name(&self) -> &str {
+        &self.trait_name
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    pub fn methods(&self) -> &[FunctionImplementation] {
+        &self.methods
+    }
+
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl Bound {
+    pub fn new(param: &str, trait_name: &str) -> Self {
+        Self {
+            param: param.to_owned(),
+            trait_name: trait_name.to_owned(),
+        }
+    }
+}
+
+/// The function implementing `method` of `trait_name` for `type_name`:
+/// `Show::show<U8>`.
+pub fn instance_method(trait_name: &str, type_name: &str, method: &str) -> QualifiedName {
+    QualifiedName::new(
+        Some(trait_name.to_owned()),
+        &format!("{method}<{type_name}>"),
+    )
+}
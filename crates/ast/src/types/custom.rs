@@ -1,4 +1,5 @@
 use super::Type;
+use crate::span::Span;
 
 #[derive(Debug, Clone, PartialEq)]
 /// Data type defined by the user
@@ -6,6 +7,7 @@ pub struct CustomType {
     name: String,
     content: Option<CustomTypeContent>,
     generics: Vec<String>,
+    span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -34,13 +36,39 @@ impl CustomType {
             name: name.to_owned(),
             content,
             generics,
+            span: Span::DUMMY,
         }
     }
 
+    /// Types provided by the compiler rather than the program, which may
+    /// define its own instead.
+    pub fn builtins() -> Vec<Self> {
+        let a = Type::Custom("A".to_owned(), vec![]);
+        let option = CustomTypeContent::Enum(vec![
+            (0, Variant::new("None", vec![])),
+            (1, Variant::new("Some", vec![a])),
+        ]);
+        vec![Self::new("Option", Some(option), vec!["A".to_owned()])]
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Type parameters: `A` in `type Option<A> { ... }`
+    pub fn generics(&self) -> &[String] {
+        &self.generics
+    }
+
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    pub const fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
     pub const fn content(&self) -> Option<&CustomTypeContent> {
         self.content.as_ref()
     }
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use super::Type;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,3 +24,11 @@ impl FunctionSignature {
         &self.return_type
     }
 }
+
+/// Example: `(U8, U8) -> U8`
+impl Display for FunctionSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arguments: Vec<_> = self.arguments.iter().map(ToString::to_string).collect();
+        write!(f, "({}) -> {}", arguments.join(", "), self.return_type)
+    }
+}
@@ -2,9 +2,10 @@ pub mod compound;
 pub mod custom;
 pub mod function;
 
+pub use compound::CompoundType;
 pub use custom::CustomType;
 pub use function::FunctionSignature;
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
@@ -25,11 +26,95 @@ pub enum Type {
     /// Type name, generic parameters
     Custom(String, Vec<String>),
     Function(FunctionSignature),
+    Compound(CompoundType),
 
     // Special types
     Unit,
 }
 
+impl Type {
+    /// `[element]`
+    pub fn list(element: Self) -> Self {
+        Self::Compound(CompoundType::List(Box::new(element)))
+    }
+
+    /// `(elements)`
+    pub const fn tuple(elements: Vec<Self>) -> Self {
+        Self::Compound(CompoundType::Tuple(elements))
+    }
+
+    /// `self` with the type named `name` replaced by `ty`.
+    pub fn replace(&self, name: &str, ty: &Self) -> Self {
+        match self {
+            Self::Custom(custom, generics) if custom == name && generics.is_empty() => ty.clone(),
+            Self::Custom(custom, generics) => Self::Custom(
+                custom.clone(),
+                generics
+                    .iter()
+                    .map(|generic| match generic == name {
+                        true => ty.to_string(),
+                        false => generic.clone(),
+                    })
+                    .collect(),
+            ),
+            Self::Function(signature) => Self::Function(FunctionSignature::new(
+                signature
+                    .arguments()
+                    .iter()
+                    .map(|arg| arg.replace(name, ty))
+                    .collect(),
+                signature.return_type().replace(name, ty),
+            )),
+            Self::Compound(CompoundType::List(element)) => Self::list(element.replace(name, ty)),
+            Self::Compound(CompoundType::Tuple(elements)) => Self::tuple(
+                elements
+                    .iter()
+                    .map(|element| element.replace(name, ty))
+                    .collect(),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    pub const fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Self::I8
+                | Self::I16
+                | Self::I32
+                | Self::I64
+                | Self::U8
+                | Self::U16
+                | Self::U32
+                | Self::U64
+        )
+    }
+
+    pub const fn is_signed(&self) -> bool {
+        matches!(self, Self::I8 | Self::I16 | Self::I32 | Self::I64)
+    }
+
+    pub const fn is_float(&self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    /// The largest value of an integer type.
+    pub const fn max_integer(&self) -> Option<u128> {
+        let max = match self {
+            Self::I8 => i8::MAX as u128,
+            Self::I16 => i16::MAX as u128,
+            Self::I32 => i32::MAX as u128,
+            Self::I64 => i64::MAX as u128,
+            Self::U8 => u8::MAX as u128,
+            Self::U16 => u16::MAX as u128,
+            Self::U32 => u32::MAX as u128,
+            Self::U64 => u64::MAX as u128,
+            _ => return None,
+        };
+        Some(max)
+    }
+}
+
 impl FromStr for Type {
     type Err = String;
 
@@ -48,8 +133,58 @@ impl FromStr for Type {
             "Bool" => Self::Bool,
             "String" => Self::String,
             "Unit" => Self::Unit,
+            // Generic arguments are kept as text: `Option<[U8]>`.
+            _ if s.starts_with('[') && s.ends_with(']') => Self::list(s[1..s.len() - 1].parse()?),
+            _ if s.starts_with('(') && s.ends_with(')') => Self::tuple(
+                split_top_level(&s[1..s.len() - 1])
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?,
+            ),
             _ => Err("Invalid type")?,
         };
         Ok(t)
     }
 }
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::I8 => write!(f, "I8"),
+            Self::I16 => write!(f, "I16"),
+            Self::I32 => write!(f, "I32"),
+            Self::I64 => write!(f, "I64"),
+            Self::U8 => write!(f, "U8"),
+            Self::U16 => write!(f, "U16"),
+            Self::U32 => write!(f, "U32"),
+            Self::U64 => write!(f, "U64"),
+            Self::F32 => write!(f, "F32"),
+            Self::F64 => write!(f, "F64"),
+            Self::Bool => write!(f, "Bool"),
+            Self::String => write!(f, "String"),
+            Self::Custom(name, generics) if generics.is_empty() => write!(f, "{name}"),
+            Self::Custom(name, generics) => write!(f, "{name}<{}>", generics.join(", ")),
+            Self::Function(signature) => write!(f, "{signature}"),
+            Self::Compound(CompoundType::List(element)) => write!(f, "[{element}]"),
+            Self::Compound(CompoundType::Tuple(elements)) => {
+                let elements: Vec<_> = elements.iter().map(ToString::to_string).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+            Self::Unit => write!(f, "Unit"),
+        }
+    }
+}
+
+/// `s` split at the commas that aren't nested in brackets: the elements of a
+/// tuple type.
+fn split_top_level(s: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    s.split(move |c| {
+        match c {
+            '(' | '[' | '<' => depth += 1,
+            ')' | ']' | '>' => depth -= 1,
+            _ => {}
+        }
+        c == ',' && depth == 0
+    })
+    .map(str::trim)
+}
@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use elk_core::{
+    diagnostics::{Diagnostic, LabelStyle},
+    span::line_col,
+};
+
+/// A source file, indexed by line so byte offsets can be turned into
+/// line/column positions.
+pub struct SourceFile<'a> {
+    name: &'a str,
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceFile<'a> {
+    pub fn new(name: &'a str, text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            name,
+            text,
+            line_starts,
+        }
+    }
+
+    /// Zero-based line index and zero-based column (in characters) of `offset`.
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        line_col(self.text, offset)
+    }
+
+    fn line(&self, line: usize) -> &'a str {
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        self.text[start..end].trim_end_matches('\r')
+    }
+}
+
+/// Renders a diagnostic rustc-style: a header, the location, and the source
+/// lines it refers to with the labelled spans underlined.
+///
+/// ```text
+/// error[E0108]: mismatched types
+///  --> samples/sample.elk:3:13
+///   |
+/// 3 |     z = x + True;
+///   |             ^^^^ expected `U8`, found `Bool`
+///   |         - this is `U8`
+/// ```
+pub fn render(diagnostic: &Diagnostic, file: &SourceFile) -> String {
+    let mut out = String::new();
+    let _ = write!(out, "{}", diagnostic.severity);
+    if let Some(code) = diagnostic.code {
+        let _ = write!(out, "[{code}]");
+    }
+    let _ = writeln!(out, ": {}", diagnostic.message);
+
+    let labels = &diagnostic.labels;
+    let last_line = labels
+        .iter()
+        .map(|l| file.line_col(l.span.start).0 + 1)
+        .max()
+        .unwrap_or(1);
+    let gutter = " ".repeat(last_line.to_string().len());
+
+    match labels.first() {
+        Some(label) => {
+            let (line, col) = file.line_col(label.span.start);
+            let _ = writeln!(out, "{gutter}--> {}:{}:{}", file.name, line + 1, col + 1);
+        }
+        None => {
+            let _ = writeln!(out, "{gutter}--> {}", file.name);
+        }
+    }
+
+    if !labels.is_empty() {
+        let _ = writeln!(out, "{gutter} |");
+        let mut lines: Vec<usize> = labels
+            .iter()
+            .map(|l| file.line_col(l.span.start).0)
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        for line in lines {
+            let number = (line + 1).to_string();
+            let _ = writeln!(
+                out,
+                "{number:>width$} | {}",
+                file.line(line),
+                width = gutter.len()
+            );
+            for label in labels {
+                let (start_line, start_col) = file.line_col(label.span.start);
+                if start_line != line {
+                    continue;
+                }
+                // Spans running over several lines are underlined up to the
+                // end of their first line.
+                let (end_line, end_col) = file.line_col(label.span.end);
+                let end_col = if end_line == line {
+                    end_col
+                } else {
+                    file.line(line).chars().count()
+                };
+                let width = end_col.saturating_sub(start_col).max(1);
+                let marker = match label.style {
+                    LabelStyle::Primary => "^",
+                    LabelStyle::Secondary => "-",
+                };
+                let _ = write!(
+                    out,
+                    "{gutter} | {}{}",
+                    " ".repeat(start_col),
+                    marker.repeat(width)
+                );
+                if label.message.is_empty() {
+                    let _ = writeln!(out);
+                } else {
+                    let _ = writeln!(out, " {}", label.message);
+                }
+            }
+        }
+    }
+
+    for note in &diagnostic.notes {
+        let _ = writeln!(out, "{gutter} = note: {}", note.replace('\n', " "));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use elk_core::{
+        diagnostics::Diagnostic,
+        span::{FileId, Span},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_line_col() {
+        let file = SourceFile::new("test.elk", "main {\n  x = 1;\n}");
+        assert_eq!(file.line_col(0), (0, 0));
+        assert_eq!(file.line_col(9), (1, 2));
+        assert_eq!(file.line(1), "  x = 1;");
+    }
+
+    #[test]
+    fn test_render_primary_and_secondary_labels() {
+        let source = "main {\n    z = x + True;\n    z\n}";
+        let file = SourceFile::new("test.elk", source);
+        let diagnostic = Diagnostic::error("mismatched types")
+            .with_code("E0108")
+            .with_primary(Span::new(FileId(0), 19, 23), "expected `U8`, found `Bool`")
+            .with_secondary(Span::new(FileId(0), 15, 16), "this is `U8`");
+        let expected = "\
+error[E0108]: mismatched types
+ --> test.elk:2:13
+  |
+2 |     z = x + True;
+  |             ^^^^ expected `U8`, found `Bool`
+  |         - this is `U8`
+";
+        assert_eq!(render(&diagnostic, &file), expected);
+    }
+
+    #[test]
+    fn test_render_without_labels() {
+        let file = SourceFile::new("test.elk", "");
+        let diagnostic = Diagnostic::error("no entry point found").with_note("add a `main` block");
+        let expected = "\
+error: no entry point found
+ --> test.elk
+  = note: add a `main` block
+";
+        assert_eq!(render(&diagnostic, &file), expected);
+    }
+}
@@ -1,6 +1,10 @@
 use anyhow::Result;
 use clap::Parser;
-use core::compile_to_object;
+use elk_core::{compile_to_object, span::FileId, Options};
+
+use crate::diagnostics::{render, SourceFile};
+
+mod diagnostics;
 
 #[derive(Parser)]
 struct Args {
@@ -10,6 +14,10 @@ struct Args {
     /// Output file path
     #[arg(short, long, default_value = "temp.o")]
     output_path: String,
+
+    /// Abort on arithmetic overflow and division by zero
+    #[arg(long)]
+    checked: bool,
 }
 
 fn main() -> Result<()> {
@@ -18,7 +26,27 @@ fn main() -> Result<()> {
     println!("Compiling {path}...");
     let src = std::fs::read_to_string(&path)?;
 
-    let compiled = compile_to_object(&mut src.as_str());
+    let file = SourceFile::new(&path, &src);
+    let options = Options {
+        checked_arithmetic: args.checked,
+    };
+    let compiled = match compile_to_object(&src, FileId::default(), options) {
+        Ok(compiled) => {
+            for warning in &compiled.warnings {
+                eprintln!("{}", render(warning, &file));
+            }
+            compiled.object
+        }
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", render(diagnostic, &file));
+            }
+            let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+            let plural = if errors == 1 { "" } else { "s" };
+            eprintln!("error: could not compile `{path}` due to {errors} previous error{plural}");
+            std::process::exit(1);
+        }
+    };
 
     std::fs::write(&args.output_path, compiled)?;
     // std::process::Command::new("gcc")
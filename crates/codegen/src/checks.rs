@@ -0,0 +1,282 @@
+use ast::{
+    diagnostics::Diagnostic,
+    expressions::{BinaryOp, Expression},
+    span::{Span, line_col},
+    types::Type,
+};
+use cranelift::{
+    codegen::ir::{MemFlagsData, TrapCode},
+    prelude::{FunctionBuilder, InstBuilder, IntCC, Value, types},
+};
+use cranelift_module::Module;
+
+use crate::{
+    Codegen, internal_error,
+    layout::{DISCRIMINANT_OFFSET, EnumLayout},
+    unsupported, untyped,
+};
+
+// With `Options::checked_arithmetic`, an operation that overflows or
+// divides by zero aborts the program with the location of the expression,
+// instead of wrapping around or faulting; so does negating the smallest
+// signed integer. `^` is bitwise, so it can't overflow. Either way,
+// `Int::checked_*` give an `Option` to handle these cases, and
+// `Int::wrapping_*` wrap around.
+
+/// `elk_abort(message, length)`: writes the message to the standard error
+/// and exits.
+const ABORT: &str = "elk_abort";
+
+/// The exit status of a program aborted by a failed check.
+const ABORT_STATUS: i64 = 101;
+
+/// The trap code of `exit` returning, which it doesn't.
+const EXITED: u8 = 3;
+
+const STDERR: i64 = 2;
+
+impl Codegen {
+    /// Defines the routine aborting the program on a failed check.
+    pub fn define_abort(&mut self) -> Result<(), Diagnostic> {
+        let ptr = self.module.target_config().pointer_type();
+        let write = self.import_function("write", &[types::I32, ptr, ptr], ptr)?;
+        let exit = self.import_function("exit", &[types::I32], types::I32)?;
+        self.define_runtime_function(ABORT, &[ptr, ptr], types::I8, |codegen, builder, params| {
+            let [message, length] = *params else {
+                unreachable!("`{ABORT}` takes a message and its length")
+            };
+            let stderr = builder.ins().iconst(types::I32, STDERR);
+            let write = codegen.module.declare_func_in_func(write, builder.func);
+            builder.ins().call(write, &[stderr, message, length]);
+            let status = builder.ins().iconst(types::I32, ABORT_STATUS);
+            let exit = codegen.module.declare_func_in_func(exit, builder.func);
+            builder.ins().call(exit, &[status]);
+            builder.ins().trap(TrapCode::unwrap_user(EXITED));
+            Ok(())
+        })
+    }
+
+    /// Aborts the program if `failed`, saying `message` happened at `span`.
+    fn gen_check(
+        &mut self,
+        failed: Value,
+        message: &str,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<(), Diagnostic> {
+        let abort = builder.create_block();
+        let ok = builder.create_block();
+        builder.set_cold_block(abort);
+        builder.ins().brif(failed, abort, &[], ok, &[]);
+
+        builder.switch_to_block(abort);
+        builder.seal_block(abort);
+        let (line, column) = line_col(&self.source, span.start);
+        let message = format!("error at {}:{}: {message}\n", line + 1, column + 1);
+        let data = self.gen_c_string(message.as_bytes())?;
+        let data = self.module.declare_data_in_func(data, builder.func);
+        let ptr = self.module.target_config().pointer_type();
+        let data = builder.ins().symbol_value(ptr, data);
+        let length = builder.ins().iconst(ptr, message.len() as i64);
+        self.gen_runtime_call(ABORT, &[data, length], span, builder)?;
+        builder.ins().trap(TrapCode::unwrap_user(EXITED));
+
+        builder.switch_to_block(ok);
+        builder.seal_block(ok);
+        Ok(())
+    }
+
+    /// `op` on integers `lhs` and `rhs`, aborting when it overflows or
+    /// divides by zero. Gives `None` for operators that can't fail.
+    pub fn gen_checked_op(
+        &mut self,
+        op: &BinaryOp,
+        lhs: Value,
+        rhs: Value,
+        signed: bool,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Option<Value>, Diagnostic> {
+        let value = match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+                let (value, overflow) = gen_overflowing_op(op, lhs, rhs, signed, builder);
+                let message = match op {
+                    BinaryOp::Add => "attempt to add with overflow",
+                    BinaryOp::Sub => "attempt to subtract with overflow",
+                    _ => "attempt to multiply with overflow",
+                };
+                self.gen_check(overflow, message, span, builder)?;
+                value
+            }
+            BinaryOp::Div | BinaryOp::Mod => {
+                let division = *op == BinaryOp::Div;
+                let zero = builder.ins().icmp_imm_s(IntCC::Equal, rhs, 0);
+                let message = match division {
+                    true => "attempt to divide by zero",
+                    false => "attempt to calculate the remainder with a divisor of zero",
+                };
+                self.gen_check(zero, message, span, builder)?;
+                if signed {
+                    let overflow = gen_division_overflows(lhs, rhs, builder);
+                    let message = match division {
+                        true => "attempt to divide with overflow",
+                        false => "attempt to calculate the remainder with overflow",
+                    };
+                    self.gen_check(overflow, message, span, builder)?;
+                }
+                match (division, signed) {
+                    (true, true) => builder.ins().sdiv(lhs, rhs),
+                    (true, false) => builder.ins().udiv(lhs, rhs),
+                    (false, true) => builder.ins().srem(lhs, rhs),
+                    (false, false) => builder.ins().urem(lhs, rhs),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+
+    /// `-operand` on a signed integer, aborting when it overflows: the
+    /// smallest value has no opposite.
+    pub fn gen_checked_negate(
+        &mut self,
+        operand: Value,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let bits = builder.func.dfg.value_type(operand).bits();
+        let is_min = builder
+            .ins()
+            .icmp_imm_s(IntCC::Equal, operand, i64::MIN >> (64 - bits));
+        self.gen_check(is_min, "attempt to negate with overflow", span, builder)?;
+        Ok(builder.ins().ineg(operand))
+    }
+
+    /// A call to `Int::name`, a built-in function, on `arguments`, whose
+    /// values are `args`. `ty` is the type of the call.
+    pub fn gen_int_builtin(
+        &mut self,
+        name: &str,
+        arguments: &[Expression],
+        args: &[Value],
+        ty: Option<&Type>,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let operand_type = arguments
+            .first()
+            .and_then(Expression::get_type)
+            .ok_or_else(|| untyped(span))?;
+        let signed = operand_type.is_signed();
+        let [lhs, rhs] = *args else {
+            return Err(unsupported(&format!("`Int::{name}`"), span));
+        };
+        let op = match name.rsplit_once('_') {
+            Some((_, "add")) => BinaryOp::Add,
+            Some((_, "sub")) => BinaryOp::Sub,
+            Some((_, "mul")) => BinaryOp::Mul,
+            Some(("checked", "div")) => BinaryOp::Div,
+            _ => return Err(unsupported(&format!("`Int::{name}`"), span)),
+        };
+        if name.starts_with("wrapping_") {
+            let (value, _) = gen_overflowing_op(&op, lhs, rhs, signed, builder);
+            return Ok(value);
+        }
+        let (value, failed) = match op {
+            BinaryOp::Div => {
+                let operand = builder.func.dfg.value_type(rhs);
+                let zero = builder.ins().icmp_imm_s(IntCC::Equal, rhs, 0);
+                let failed = match signed {
+                    true => {
+                        let overflow = gen_division_overflows(lhs, rhs, builder);
+                        builder.ins().bor(zero, overflow)
+                    }
+                    false => zero,
+                };
+                // Divide by 1 instead, for a result that isn't used.
+                let one = builder.ins().iconst(operand, 1);
+                let divisor = builder.ins().select(failed, one, rhs);
+                let value = match signed {
+                    true => builder.ins().sdiv(lhs, divisor),
+                    false => builder.ins().udiv(lhs, divisor),
+                };
+                (value, failed)
+            }
+            _ => gen_overflowing_op(&op, lhs, rhs, signed, builder),
+        };
+        let ty = ty.ok_or_else(|| untyped(span))?;
+        self.gen_option(value, failed, ty, span, builder)
+    }
+
+    /// An `Option` of type `ty`: `None` if `none`, or else `Some(value)`.
+    fn gen_option(
+        &mut self,
+        value: Value,
+        none: Value,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let Type::Custom(name, _) = ty else {
+            return Err(internal_error(format!("`{ty}` is not an `Option`"), span));
+        };
+        let variants = self
+            .find_type(name, span)?
+            .get_enum_variants()
+            .ok_or_else(|| internal_error(format!("`{name}` is not an enum"), span))?
+            .clone();
+        let discriminant = |variant: &str| {
+            variants
+                .iter()
+                .find(|(_, v)| v.name() == variant)
+                .map(|(discriminant, _)| i64::from(*discriminant))
+                .ok_or_else(|| internal_error(format!("no variant `{name}::{variant}`"), span))
+        };
+        let (none_discriminant, some_discriminant) = (discriminant("None")?, discriminant("Some")?);
+        let layout = EnumLayout::new(&variants);
+        let option = self.gen_alloc(layout.layout, span, builder)?;
+        let none_discriminant = builder.ins().iconst(types::I8, none_discriminant);
+        let some_discriminant = builder.ins().iconst(types::I8, some_discriminant);
+        let discriminant = builder
+            .ins()
+            .select(none, none_discriminant, some_discriminant);
+        let flags = MemFlagsData::trusted();
+        builder
+            .ins()
+            .store(flags, discriminant, option, DISCRIMINANT_OFFSET);
+        let offset = layout
+            .field_offset("Some", 0)
+            .ok_or_else(|| internal_error(format!("no field 0 in `{name}::Some`"), span))?;
+        builder.ins().store(flags, value, option, offset);
+        Ok(option)
+    }
+}
+
+/// `op`, an addition, subtraction or multiplication, wrapping around, and
+/// whether it overflowed.
+fn gen_overflowing_op(
+    op: &BinaryOp,
+    lhs: Value,
+    rhs: Value,
+    signed: bool,
+    builder: &mut FunctionBuilder,
+) -> (Value, Value) {
+    match (op, signed) {
+        (BinaryOp::Add, true) => builder.ins().sadd_overflow(lhs, rhs),
+        (BinaryOp::Add, false) => builder.ins().uadd_overflow(lhs, rhs),
+        (BinaryOp::Sub, true) => builder.ins().ssub_overflow(lhs, rhs),
+        (BinaryOp::Sub, false) => builder.ins().usub_overflow(lhs, rhs),
+        (_, true) => builder.ins().smul_overflow(lhs, rhs),
+        (_, false) => builder.ins().umul_overflow(lhs, rhs),
+    }
+}
+
+/// Whether the signed division of `lhs` by `rhs` overflows: the smallest
+/// value divided by -1.
+fn gen_division_overflows(lhs: Value, rhs: Value, builder: &mut FunctionBuilder) -> Value {
+    let bits = builder.func.dfg.value_type(lhs).bits();
+    let min = i64::MIN >> (64 - bits);
+    let is_min = builder.ins().icmp_imm_s(IntCC::Equal, lhs, min);
+    let minus_one = builder.ins().icmp_imm_s(IntCC::Equal, rhs, -1);
+    builder.ins().band(is_min, minus_one)
+}
@@ -0,0 +1,478 @@
+use std::collections::BTreeSet;
+
+use ast::{
+    diagnostics::{Diagnostic, codes},
+    expressions::{Expression, ExpressionKind, MatchBody, StringPart},
+    functions::FunctionBody,
+    span::Span,
+    statements::{Block, StatementKind},
+    types::{FunctionSignature, Type},
+};
+use cranelift::{
+    codegen::{
+        Context,
+        ir::{Function, MemFlagsData, UserFuncName},
+        verify_function,
+    },
+    prelude::{
+        AbiParam, FunctionBuilder, FunctionBuilderContext, InstBuilder, Signature, Value,
+        isa::CallConv, settings::FlagsOrIsa, types,
+    },
+};
+use cranelift_module::{DataDescription, DataId, FuncId, FuncOrDataId, Linkage, Module};
+
+use crate::{
+    Codegen, Generable,
+    escape::Escapes,
+    internal_error,
+    layout::Layout,
+    symbol,
+    tuples::{returned_in_registers, tuple_elements, tuple_layout},
+    unsupported,
+};
+
+// A function value is a pointer to a closure: the address of its code,
+// followed by the values of the variables it captured. The code takes the
+// closure as its first parameter, then the arguments. Every argument and
+// the result are passed in a pointer-sized slot whatever their type, so
+// that a generic function like `map<A, B>` can call any function value.
+const CLOSURE_CODE: i32 = 0;
+const SLOT: types::Type = types::I64;
+
+impl Codegen {
+    /// A lambda of type `ty`. Its body is compiled into a function of its
+    /// own, and the variables it uses are copied into its closure.
+    pub fn gen_lambda(
+        &mut self,
+        params: &[String],
+        body: &Expression,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let Type::Function(signature) = ty else {
+            return Err(internal_error(format!("lambda of type `{ty}`"), span));
+        };
+        let captures: Vec<(String, Type)> = free_variables(params, body)
+            .into_iter()
+            .filter_map(|name| {
+                let (_, ty) = self.get_variable(&name)?;
+                Some((name, ty.clone()))
+            })
+            .collect();
+        let values: Vec<Value> = captures
+            .iter()
+            .map(|(name, _)| {
+                let (var, _) = self.find_variable(name, span)?;
+                Ok(builder.use_var(*var))
+            })
+            .collect::<Result<_, Diagnostic>>()?;
+        let code = self.define_lambda(params, body, signature, &captures, span)?;
+
+        let (layout, offsets) = closure_layout(captures.iter().map(|(_, ty)| ty));
+        let closure = self.gen_alloc(layout, span, builder)?;
+        let code = self.module.declare_func_in_func(code, builder.func);
+        let ptr = self.module.target_config().pointer_type();
+        let code = builder.ins().func_addr(ptr, code);
+        let flags = MemFlagsData::trusted();
+        builder.ins().store(flags, code, closure, CLOSURE_CODE);
+        for (value, offset) in values.into_iter().zip(offsets) {
+            builder.ins().store(flags, value, closure, offset as i32);
+        }
+        Ok(closure)
+    }
+
+    /// Compiles the code of a lambda, which finds `captures` in its closure.
+    fn define_lambda(
+        &mut self,
+        params: &[String],
+        body: &Expression,
+        signature: &FunctionSignature,
+        captures: &[(String, Type)],
+        span: Span,
+    ) -> Result<FuncId, Diagnostic> {
+        let id = self
+            .module
+            .declare_anonymous_function(&closure_signature(signature))
+            .map_err(|e| internal_error(format!("cannot declare lambda: {e}"), span))?;
+        // Only the captured variables are in scope in the body, which has
+        // values escaping of its own.
+        let outer_scopes = self.scopes.split_off(1);
+        let body_escapes = Escapes::of(&[&FunctionBody::SingleLine(body.clone())], true);
+        let outer_escapes = std::mem::replace(&mut self.escapes, body_escapes);
+        let outer_dictionaries = std::mem::take(&mut self.dictionaries);
+        let defined = self.define_closure_code(id, signature, span, |codegen, builder, args| {
+            let closure = args[0];
+            let (_, offsets) = closure_layout(captures.iter().map(|(_, ty)| ty));
+            for ((name, ty), offset) in captures.iter().zip(offsets) {
+                let value = builder.ins().load(
+                    ty.to_cranelift(),
+                    MemFlagsData::trusted(),
+                    closure,
+                    offset as i32,
+                );
+                let var = codegen.declare_variable(name, ty.clone(), builder);
+                builder.def_var(var, value);
+            }
+            for ((name, ty), value) in params.iter().zip(signature.arguments()).zip(&args[1..]) {
+                let var = codegen.declare_variable(name, ty.clone(), builder);
+                builder.def_var(var, *value);
+            }
+            codegen.gen_expression(body, builder)
+        });
+        self.scopes.extend(outer_scopes);
+        self.escapes = outer_escapes;
+        self.dictionaries = outer_dictionaries;
+        defined.map(|()| id)
+    }
+
+    /// The closure of `function_name`, to use it as a value. It captures
+    /// nothing, so it's built once, in the data of the program.
+    pub fn gen_function_value(
+        &mut self,
+        function_name: &str,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let closure = self.function_closure(function_name, span)?;
+        let closure = self.module.declare_data_in_func(closure, builder.func);
+        let ptr = self.module.target_config().pointer_type();
+        Ok(builder.ins().symbol_value(ptr, closure))
+    }
+
+    /// The closure of `function_name`, defining it the first time.
+    pub fn function_closure(
+        &mut self,
+        function_name: &str,
+        span: Span,
+    ) -> Result<DataId, Diagnostic> {
+        if self.bounds.contains_key(function_name) {
+            return Err(unsupported("a function with trait bounds as a value", span));
+        }
+        let closure_name = format!("{}$closure", symbol(function_name));
+        match self.module.get_name(&closure_name) {
+            Some(FuncOrDataId::Data(closure)) => Ok(closure),
+            _ => self.define_function_closure(function_name, &closure_name, span),
+        }
+    }
+
+    /// Defines the closure of `function_name`, whose code calls it.
+    fn define_function_closure(
+        &mut self,
+        function_name: &str,
+        closure_name: &str,
+        span: Span,
+    ) -> Result<DataId, Diagnostic> {
+        let (function, signature) = self
+            .get_function(function_name)
+            .ok_or_else(|| {
+                Diagnostic::error(format!("cannot find variable `{function_name}`"))
+                    .with_code(codes::UNKNOWN_VARIABLE)
+                    .with_primary(span, "not found in this scope")
+            })?
+            .clone();
+        let error = |e| internal_error(format!("cannot define `{closure_name}`: {e}"), span);
+        let code = self
+            .module
+            .declare_anonymous_function(&closure_signature(&signature))
+            .map_err(error)?;
+        self.define_closure_code(code, &signature, span, |codegen, builder, args| {
+            codegen.gen_closure_result(function, signature.return_type(), &args[1..], span, builder)
+        })?;
+
+        let closure = self
+            .module
+            .declare_data(closure_name, Linkage::Local, false, false)
+            .map_err(error)?;
+        let mut description = DataDescription::new();
+        description.define(vec![0; CLOSURE.size as usize].into());
+        description.set_align(u64::from(CLOSURE.align));
+        let code = self.module.declare_func_in_data(code, &mut description);
+        description.write_function_addr(CLOSURE_CODE as u32, code);
+        self.module
+            .define_data(closure, &description)
+            .map_err(error)?;
+        Ok(closure)
+    }
+
+    /// `function`, of type `signature`, applied to `args`, fewer than it
+    /// takes: a closure holding them, taking the rest of the arguments.
+    pub fn gen_partial_application(
+        &mut self,
+        function: FuncId,
+        signature: &FunctionSignature,
+        args: &[Value],
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let (given, rest) = signature.arguments().split_at(args.len());
+        let rest = FunctionSignature::new(rest.to_vec(), signature.return_type().clone());
+        let code = self
+            .module
+            .declare_anonymous_function(&closure_signature(&rest))
+            .map_err(|e| internal_error(format!("cannot declare closure: {e}"), span))?;
+        let (layout, offsets) = closure_layout(given.iter());
+        self.define_closure_code(code, &rest, span, |codegen, builder, slots| {
+            let closure = slots[0];
+            let mut args: Vec<Value> = given
+                .iter()
+                .zip(&offsets)
+                .map(|(ty, offset)| {
+                    let flags = MemFlagsData::trusted();
+                    builder
+                        .ins()
+                        .load(ty.to_cranelift(), flags, closure, *offset as i32)
+                })
+                .collect();
+            args.extend(&slots[1..]);
+            codegen.gen_closure_result(function, signature.return_type(), &args, span, builder)
+        })?;
+
+        let closure = self.gen_alloc(layout, span, builder)?;
+        let code = self.module.declare_func_in_func(code, builder.func);
+        let ptr = self.module.target_config().pointer_type();
+        let code = builder.ins().func_addr(ptr, code);
+        let flags = MemFlagsData::trusted();
+        builder.ins().store(flags, code, closure, CLOSURE_CODE);
+        for ((arg, ty), offset) in args.iter().zip(given).zip(offsets) {
+            let arg = gen_coerce(*arg, ty.to_cranelift(), builder);
+            builder.ins().store(flags, arg, closure, offset as i32);
+        }
+        Ok(closure)
+    }
+
+    /// Calls `function`, returning `return_type`, with `args` from the code
+    /// of a closure. Its result outlives the call, so tuples returned in
+    /// registers are stored on the heap.
+    fn gen_closure_result(
+        &mut self,
+        function: FuncId,
+        return_type: &Type,
+        args: &[Value],
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let function = self.module.declare_func_in_func(function, builder.func);
+        let call = builder.ins().call(function, args);
+        let results = builder.inst_results(call).to_vec();
+        if !returned_in_registers(return_type) {
+            return Ok(results[0]);
+        }
+        let (layout, _) = tuple_layout(tuple_elements(return_type, span)?);
+        let tuple = self.gen_heap_alloc(layout, span, builder)?;
+        self.gen_store_elements(tuple, &results, return_type, span, builder)
+    }
+
+    /// Defines `id`, the code of a closure for functions of type
+    /// `signature`. `build` emits the body, given the closure and the
+    /// arguments, and gives the result.
+    fn define_closure_code(
+        &mut self,
+        id: FuncId,
+        signature: &FunctionSignature,
+        span: Span,
+        build: impl FnOnce(&mut Self, &mut FunctionBuilder, &[Value]) -> Result<Value, Diagnostic>,
+    ) -> Result<(), Diagnostic> {
+        let mut func =
+            Function::with_name_signature(UserFuncName::user(0, 0), closure_signature(signature));
+        let mut func_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut func_ctx);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let slots = builder.block_params(entry).to_vec();
+        let mut args = vec![slots[0]];
+        for (slot, ty) in slots[1..].iter().zip(signature.arguments()) {
+            args.push(from_slot(*slot, ty.to_cranelift(), &mut builder));
+        }
+        let exit = builder.create_block();
+        builder.append_block_param(exit, signature.return_type().to_cranelift());
+        let outer_exit = self.exit.replace(exit);
+        let result = self.with_scope(|codegen| build(codegen, &mut builder, &args));
+        self.exit = outer_exit;
+        builder.ins().jump(exit, &[result?.into()]);
+        builder.switch_to_block(exit);
+        let result = to_slot(builder.block_params(exit)[0], &mut builder);
+        builder.ins().return_(&[result]);
+        builder.seal_all_blocks();
+        builder.finalize(self.module.isa().frontend_config());
+
+        verify_function(
+            &func,
+            FlagsOrIsa {
+                flags: &self.flags,
+                isa: None,
+            },
+        )
+        .map_err(|e| internal_error(format!("invalid IR for closure: {e}"), span))?;
+        let mut ctx = Context::for_function(func);
+        self.module
+            .define_function(id, &mut ctx)
+            .map_err(|e| internal_error(format!("cannot define closure: {e}"), span))
+    }
+
+    /// Calls the function value `closure`, of type `signature`, with `args`.
+    pub fn gen_closure_call(
+        &self,
+        closure: Value,
+        signature: &FunctionSignature,
+        args: &[Value],
+        builder: &mut FunctionBuilder,
+    ) -> Value {
+        let ptr = self.module.target_config().pointer_type();
+        let code = builder
+            .ins()
+            .load(ptr, MemFlagsData::trusted(), closure, CLOSURE_CODE);
+        let mut slots = vec![closure];
+        slots.extend(args.iter().map(|arg| to_slot(*arg, builder)));
+        let sig = builder.import_signature(closure_signature(signature));
+        let call = builder.ins().call_indirect(sig, code, &slots);
+        let result = builder.inst_results(call)[0];
+        from_slot(result, signature.return_type().to_cranelift(), builder)
+    }
+}
+
+/// The code of closures of functions of type `signature`: the closure, then
+/// one slot per argument, and a slot for the result.
+fn closure_signature(signature: &FunctionSignature) -> Signature {
+    let mut params = vec![AbiParam::new(SLOT)];
+    params.extend(signature.arguments().iter().map(|_| AbiParam::new(SLOT)));
+    Signature {
+        params,
+        returns: vec![AbiParam::new(SLOT)],
+        call_conv: CallConv::SystemV,
+    }
+}
+
+/// A closure without captured variables: the address of its code.
+const CLOSURE: Layout = Layout { size: 8, align: 8 };
+
+/// The captured values follow the address of the code.
+fn closure_layout<'a>(captures: impl Iterator<Item = &'a Type>) -> (Layout, Vec<u32>) {
+    CLOSURE.extend(captures.map(Layout::of))
+}
+
+/// `value` widened to a slot. Floats keep their bits.
+fn to_slot(value: Value, builder: &mut FunctionBuilder) -> Value {
+    let ty = builder.func.dfg.value_type(value);
+    let bits = match ty.is_float() {
+        true => builder
+            .ins()
+            .bitcast(ty.as_int(), MemFlagsData::new(), value),
+        false => value,
+    };
+    match ty.as_int() == SLOT {
+        true => bits,
+        false => builder.ins().uextend(SLOT, bits),
+    }
+}
+
+/// The value of type `ty` in `slot`.
+fn from_slot(slot: Value, ty: types::Type, builder: &mut FunctionBuilder) -> Value {
+    let bits = match ty.as_int() == SLOT {
+        true => slot,
+        false => builder.ins().ireduce(ty.as_int(), slot),
+    };
+    match ty.is_float() {
+        true => builder.ins().bitcast(ty, MemFlagsData::new(), bits),
+        false => bits,
+    }
+}
+
+/// `value` as a value of cranelift type `ty`, through a slot: arguments of
+/// generic functions are passed in slots, and their results come back in
+/// them.
+pub fn gen_coerce(value: Value, ty: types::Type, builder: &mut FunctionBuilder) -> Value {
+    if builder.func.dfg.value_type(value) == ty {
+        return value;
+    }
+    let slot = to_slot(value, builder);
+    from_slot(slot, ty, builder)
+}
+
+/// The names `body` uses that aren't `params`: the variables a lambda may
+/// capture, along with the functions it calls, which aren't variables.
+pub fn free_variables(params: &[String], body: &Expression) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    expression_names(body, &mut names);
+    for param in params {
+        names.remove(param);
+    }
+    names
+}
+
+fn expression_names(expr: &Expression, names: &mut BTreeSet<String>) {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) | ExpressionKind::RecordAccess(name, _) => {
+            names.insert(name.clone());
+        }
+        ExpressionKind::FunctionCall {
+            namespace,
+            name,
+            arguments,
+        } => {
+            if namespace.is_none() {
+                names.insert(name.clone());
+            }
+            arguments
+                .iter()
+                .for_each(|arg| expression_names(arg, names));
+        }
+        ExpressionKind::MethodCall {
+            receiver,
+            arguments,
+            ..
+        } => {
+            expression_names(receiver, names);
+            arguments
+                .iter()
+                .for_each(|arg| expression_names(arg, names));
+        }
+        ExpressionKind::NewEnumInstance(_, _, args)
+        | ExpressionKind::List(args)
+        | ExpressionKind::Tuple(args) => args.iter().for_each(|arg| expression_names(arg, names)),
+        ExpressionKind::NewRecordInstance(_, fields) => fields
+            .iter()
+            .for_each(|(_, value)| expression_names(value, names)),
+        ExpressionKind::Match(scrutinee, arms) => {
+            expression_names(scrutinee, names);
+            for arm in arms {
+                match &arm.body {
+                    MatchBody::Expr(body) => expression_names(body, names),
+                    MatchBody::Block(block) => block_names(block, names),
+                }
+            }
+        }
+        ExpressionKind::If(condition, then, otherwise) => {
+            expression_names(condition, names);
+            block_names(then, names);
+            block_names(otherwise, names);
+        }
+        ExpressionKind::BinaryOp(lhs, _, rhs) => {
+            expression_names(lhs, names);
+            expression_names(rhs, names);
+        }
+        ExpressionKind::UnaryOp(_, operand) => expression_names(operand, names),
+        ExpressionKind::Interpolation(parts) => {
+            for part in parts {
+                if let StringPart::Hole(hole) = part {
+                    expression_names(hole, names);
+                }
+            }
+        }
+        ExpressionKind::Lambda(params, body) => names.extend(free_variables(params, body)),
+        ExpressionKind::Literal(_) | ExpressionKind::Unit => {}
+    }
+}
+
+fn block_names(block: &Block, names: &mut BTreeSet<String>) {
+    for statement in &block.statements {
+        match &statement.kind {
+            StatementKind::Assignment(_, _, value) | StatementKind::Return(value) => {
+                expression_names(value, names);
+            }
+        }
+    }
+    expression_names(&block.return_expr, names);
+}
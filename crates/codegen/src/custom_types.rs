@@ -1,17 +1,29 @@
 use ast::{
-    expressions::{AssociatedType, Expression},
-    types::{CustomType, Type},
+    diagnostics::{Diagnostic, codes},
+    expressions::Expression,
+    span::Span,
+    types::{CustomType, Type, custom::CustomTypeContent},
+};
+use cranelift::{
+    codegen::ir::MemFlagsData,
+    prelude::{FunctionBuilder, InstBuilder, StackSlotData, StackSlotKind, Value, types},
 };
-use cranelift::prelude::{FunctionBuilder, InstBuilder, Value, types};
 use cranelift_module::Module;
 
-use crate::{Codegen, Generable};
+use crate::{
+    Codegen, Generable, internal_error,
+    layout::{DISCRIMINANT_OFFSET, EnumLayout, Layout, RecordLayout},
+};
 
 impl Generable for CustomType {
     type Output = types::Type;
 
     fn size(&self) -> u32 {
-        todo!()
+        match self.content() {
+            Some(CustomTypeContent::Enum(variants)) => EnumLayout::new(variants).layout.size,
+            Some(CustomTypeContent::Record(fields)) => RecordLayout::new(fields).layout.size,
+            None => 0,
+        }
     }
 
     fn to_cranelift(&self) -> Self::Output {
@@ -24,87 +36,172 @@ impl Codegen {
         &mut self,
         record_name: &str,
         fields: &[(String, Expression)],
+        span: Span,
         builder: &mut FunctionBuilder,
-    ) -> Value {
-        let ty = self.get_type(record_name).expect("Type not found");
-        let size = ty.size();
-
-        let data = cranelift::prelude::StackSlotData::new(
-            cranelift::prelude::StackSlotKind::ExplicitSlot,
-            size,
-            0,
-        );
-        let ss = builder.create_sized_stack_slot(data);
-        let mut offset = 0;
-        fields.iter().for_each(|(_, expr)| {
-            let off = offset;
-            if let AssociatedType::Concrete(ty) = expr.associated_type().unwrap() {
-                offset += ty.size();
-            } else {
-                panic!("Type not inferred");
-            }
-            let v = self.gen_expression(expr, builder);
+    ) -> Result<Value, Diagnostic> {
+        let layout = self.record_layout(record_name, span)?;
+        let record = self.gen_alloc(layout.layout, span, builder)?;
+        for (name, expr) in fields {
+            let offset = layout
+                .field_offset(name)
+                .ok_or_else(|| unknown_field(record_name, name, expr.span()))?;
+            let v = self.gen_expression(expr, builder)?;
             builder
                 .ins()
-                .stack_store(cranelift::prelude::types::I32, v, ss, off as i32);
-        });
-
-        let ty = self.module.target_config().pointer_type();
-        builder.ins().stack_addr(ty, ss, 0)
+                .store(MemFlagsData::trusted(), v, record, offset);
+        }
+        Ok(record)
     }
 
+    /// Loads field `field_name`, of type `ty`, of the record in `var_name`.
     pub fn gen_record_access(
         &self,
         var_name: &str,
         field_name: &str,
+        ty: &Type,
+        span: Span,
         builder: &mut FunctionBuilder,
-    ) -> Value {
-        let (var, ty) = self.get_variable(var_name).expect("Type not found");
-        let Type::Custom(type_name, _) = ty else {
-            panic!("Type is not defined");
+    ) -> Result<Value, Diagnostic> {
+        let (var, record_type) = self.find_variable(var_name, span)?;
+        let Type::Custom(type_name, _) = record_type else {
+            return Err(not_a_record(var_name, span));
         };
-        let fields = self
-            .get_type(type_name)
-            .and_then(|t| t.get_record_fields())
-            .expect("Type is not a record");
-        let mut offset = 0;
-        let (field, offset) = fields
-            .iter()
-            .map(|f| {
-                let off = offset;
-                offset += f.ty().size();
-                (f, off)
-            })
-            .find(|(f, _)| f.name() == field_name)
-            .unwrap();
         let ptr = builder.use_var(*var);
-        builder
+        self.gen_record_field(ptr, type_name, field_name, ty, span, builder)
+    }
+
+    /// Loads field `field_name`, of type `ty`, of the `type_name` record at
+    /// `ptr`.
+    pub fn gen_record_field(
+        &self,
+        ptr: Value,
+        type_name: &str,
+        field_name: &str,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let offset = self
+            .record_layout(type_name, span)?
+            .field_offset(field_name)
+            .ok_or_else(|| unknown_field(type_name, field_name, span))?;
+        Ok(builder
             .ins()
-            .load(field.ty().to_cranelift(), todo!(), ptr, offset as i32)
+            .load(ty.to_cranelift(), MemFlagsData::trusted(), ptr, offset))
+    }
+
+    fn record_layout(&self, record_name: &str, span: Span) -> Result<RecordLayout, Diagnostic> {
+        self.find_type(record_name, span)?
+            .get_record_fields()
+            .map(|fields| RecordLayout::new(fields))
+            .ok_or_else(|| not_a_record(record_name, span))
     }
 
     pub fn gen_new_enum_instance(
-        &self,
+        &mut self,
         enum_name: &str,
         variant_name: &str,
+        args: &[Expression],
+        span: Span,
         builder: &mut FunctionBuilder,
-    ) -> Value {
-        let ty = self.get_type(enum_name).expect("Enum not found");
-        let (discriminant, _) = ty
-            .get_enum_variants()
-            .expect("Type is not an enum")
+    ) -> Result<Value, Diagnostic> {
+        let ty = self.find_type(enum_name, span)?.clone();
+        let variants = ty.get_enum_variants().ok_or_else(|| {
+            Diagnostic::error(format!("`{enum_name}` is not an enum"))
+                .with_code(codes::NOT_AN_ENUM)
+                .with_primary(span, "has no variants")
+        })?;
+        let (discriminant, _) = variants
             .iter()
             .find(|(_, v)| v.name() == variant_name)
-            .expect("Variant not found on enum");
-        let data = cranelift::prelude::StackSlotData::new(
-            cranelift::prelude::StackSlotKind::ExplicitSlot,
-            ty.size(),
-            0,
+            .ok_or_else(|| {
+                Diagnostic::error(format!("no variant `{variant_name}` on enum `{enum_name}`"))
+                    .with_code(codes::UNKNOWN_VARIANT)
+                    .with_primary(span, "unknown variant")
+            })?;
+        let layout = EnumLayout::new(variants);
+        let value = self.gen_alloc(layout.layout, span, builder)?;
+        let discriminant = builder.ins().iconst(types::I8, i64::from(*discriminant));
+        builder.ins().store(
+            MemFlagsData::trusted(),
+            discriminant,
+            value,
+            DISCRIMINANT_OFFSET,
+        );
+        for (i, arg) in args.iter().enumerate() {
+            let offset = layout.field_offset(variant_name, i).ok_or_else(|| {
+                internal_error(
+                    format!("no field {i} in `{enum_name}::{variant_name}`"),
+                    arg.span(),
+                )
+            })?;
+            let v = self.gen_expression(arg, builder)?;
+            builder
+                .ins()
+                .store(MemFlagsData::trusted(), v, value, offset);
+        }
+        Ok(value)
+    }
+
+    /// Memory for the value of `layout` built at `span`: in the stack frame
+    /// of the function, unless it escapes it.
+    pub fn gen_alloc(
+        &mut self,
+        layout: Layout,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        if self.escapes.contains(span) {
+            return self.gen_heap_alloc(layout, span, builder);
+        }
+        let data = StackSlotData::new(
+            StackSlotKind::ExplicitSlot,
+            layout.size,
+            layout.align_shift(),
         );
         let ss = builder.create_sized_stack_slot(data);
-        let discriminant = builder.ins().iconst(types::I8, *discriminant as i64);
-        builder.ins().stack_store(discriminant, ss, 0);
-        let ty = self.module.target_config().pointer_type();
-        builder.ins().stack_addr(ty, ss, 0)
+        let ptr = self.module.target_config().pointer_type();
+        Ok(builder.ins().stack_addr(ptr, ss, 0))
     }
+
+    /// Loads field `index`, of type `ty`, of the payload of the
+    /// `enum_name::variant_name` value at `ptr`.
+    pub fn gen_payload_field(
+        &self,
+        ptr: Value,
+        (enum_name, variant_name): (&str, &str),
+        index: usize,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let offset = self
+            .find_type(enum_name, span)?
+            .get_enum_variants()
+            .map(|variants| EnumLayout::new(variants))
+            .and_then(|layout| layout.field_offset(variant_name, index))
+            .ok_or_else(|| {
+                internal_error(
+                    format!("no field {index} in `{enum_name}::{variant_name}`"),
+                    span,
+                )
+            })?;
+        Ok(builder
+            .ins()
+            .load(ty.to_cranelift(), MemFlagsData::trusted(), ptr, offset))
+    }
+}
+
+fn unknown_field(record_name: &str, field_name: &str, span: Span) -> Diagnostic {
+    Diagnostic::error(format!(
+        "record `{record_name}` has no field `{field_name}`"
+    ))
+    .with_code(codes::UNKNOWN_FIELD)
+    .with_primary(span, "unknown field")
+}
+
+fn not_a_record(name: &str, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("`{name}` is not a record"))
+        .with_code(codes::NOT_A_RECORD)
+        .with_primary(span, "field access on a non-record value")
 }
@@ -0,0 +1,870 @@
+use std::collections::BTreeMap;
+
+use ast::{
+    diagnostics::Diagnostic,
+    expressions::Literal,
+    patterns::{Pattern, PatternKind},
+    span::Span,
+    types::Type,
+};
+use cranelift::{
+    codegen::ir::{Block, MemFlagsData, TrapCode},
+    frontend::Switch,
+    prelude::{FloatCC, FunctionBuilder, InstBuilder, Value, types},
+};
+
+use crate::{
+    Codegen, Generable, internal_error, layout::DISCRIMINANT_OFFSET, lists::element_type,
+    tuples::tuple_elements,
+};
+
+/// Where a matched value is: the n-th argument of the function (or the
+/// scrutinee of a `match`), a field of an enum variant's payload, the first
+/// element or the rest of a non-empty list, an element of a tuple, or a
+/// field of a record.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Occurrence {
+    Root(usize),
+    Field {
+        parent: Box<Occurrence>,
+        enum_name: String,
+        variant_name: String,
+        index: usize,
+    },
+    Head(Box<Occurrence>),
+    Tail(Box<Occurrence>),
+    Element(Box<Occurrence>, usize),
+    RecordField {
+        parent: Box<Occurrence>,
+        type_name: String,
+        field: String,
+    },
+}
+
+/// What a refutable pattern tests a value against.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constructor {
+    Literal(Literal),
+    Variant {
+        enum_name: String,
+        variant_name: String,
+        arity: usize,
+    },
+    EmptyList,
+    Cons,
+    /// Every tuple: testing it only gives access to its elements.
+    Tuple(usize),
+    /// Every record of a type, likewise.
+    Record(String),
+}
+
+/// The tests deciding which clause of a function (or arm of a `match`)
+/// matches its arguments. Each value is tested at most once on any path,
+/// and the first clause that matches in source order wins.
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    /// No clause matches.
+    Fail,
+    /// The clause with this index matches.
+    Match(usize),
+    /// Tests the value at `occurrence` against each constructor in turn.
+    /// `default` is taken when none applies, and is missing when the
+    /// constructors cover every possible value.
+    Switch {
+        occurrence: Occurrence,
+        cases: Vec<(Constructor, Decision)>,
+        default: Option<Box<Decision>>,
+    },
+}
+
+/// The patterns of a clause still to be tested, with where their values are.
+#[derive(Clone)]
+struct Row<'a> {
+    columns: Vec<(Occurrence, &'a Pattern)>,
+    clause: usize,
+}
+
+impl<'a> Row<'a> {
+    fn pattern_at(&self, occurrence: &Occurrence) -> Option<&'a Pattern> {
+        self.columns
+            .iter()
+            .find(|(o, _)| o == occurrence)
+            .map(|(_, pattern)| *pattern)
+    }
+}
+
+impl Decision {
+    /// Compiles clauses, given as the patterns of their parameters.
+    /// `variant_count` gives the number of variants of an enum, to know when
+    /// a `Switch` needs no default.
+    pub fn compile(clauses: &[&[Pattern]], variant_count: &impl Fn(&str) -> usize) -> Self {
+        let rows = clauses
+            .iter()
+            .enumerate()
+            .map(|(clause, patterns)| Row {
+                columns: patterns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, pattern)| (Occurrence::Root(i), pattern))
+                    .collect(),
+                clause,
+            })
+            .collect();
+        compile_rows(rows, variant_count)
+    }
+}
+
+fn compile_rows(mut rows: Vec<Row>, variant_count: &impl Fn(&str) -> usize) -> Decision {
+    // Wildcards and bindings always match: only the other patterns are tests.
+    for row in &mut rows {
+        row.columns
+            .retain(|(_, pattern)| constructor(pattern).is_some());
+    }
+    let Some(first) = rows.first() else {
+        return Decision::Fail;
+    };
+    let Some((occurrence, _)) = first.columns.first() else {
+        return Decision::Match(first.clause);
+    };
+    let occurrence = occurrence.clone();
+
+    let mut constructors: Vec<Constructor> = vec![];
+    for row in &rows {
+        if let Some(c) = row.pattern_at(&occurrence).and_then(constructor)
+            && !constructors.contains(&c)
+        {
+            constructors.push(c);
+        }
+    }
+    let complete = match constructors.first() {
+        Some(Constructor::Variant { enum_name, .. }) => {
+            constructors.len() == variant_count(enum_name)
+        }
+        Some(
+            Constructor::Literal(Literal::Bool(_)) | Constructor::EmptyList | Constructor::Cons,
+        ) => constructors.len() == 2,
+        Some(Constructor::Tuple(_) | Constructor::Record(_)) => true,
+        _ => false,
+    };
+
+    let default = (!complete).then(|| {
+        let rows = rows
+            .iter()
+            .filter(|row| row.pattern_at(&occurrence).is_none())
+            .cloned()
+            .collect();
+        Box::new(compile_rows(rows, variant_count))
+    });
+    let cases = constructors
+        .into_iter()
+        .map(|c| {
+            let rows = specialize(&rows, &occurrence, &c);
+            (c, compile_rows(rows, variant_count))
+        })
+        .collect();
+    Decision::Switch {
+        occurrence,
+        cases,
+        default,
+    }
+}
+
+/// The rows that still apply once the value at `occurrence` is known to be
+/// built with `c`, with the patterns of its payload to be tested next.
+fn specialize<'a>(rows: &[Row<'a>], occurrence: &Occurrence, c: &Constructor) -> Vec<Row<'a>> {
+    rows.iter()
+        .filter_map(|row| {
+            let Some(pattern) = row.pattern_at(occurrence) else {
+                return Some(row.clone());
+            };
+            if constructor(pattern).as_ref() != Some(c) {
+                return None;
+            }
+            let mut columns: Vec<_> = row
+                .columns
+                .iter()
+                .filter(|(o, _)| o != occurrence)
+                .cloned()
+                .collect();
+            // Payloads are tested before the rest of the row, so the tree
+            // goes depth first, like the patterns read.
+            match &pattern.kind {
+                PatternKind::EnumInstance {
+                    enum_name,
+                    variant_name,
+                    args,
+                } => {
+                    let fields = args.iter().enumerate().map(|(index, arg)| {
+                        let field = Occurrence::Field {
+                            parent: Box::new(occurrence.clone()),
+                            enum_name: enum_name.clone(),
+                            variant_name: variant_name.clone(),
+                            index,
+                        };
+                        (field, arg)
+                    });
+                    columns.splice(0..0, fields);
+                }
+                PatternKind::Cons { head, tail } => {
+                    let parent = Box::new(occurrence.clone());
+                    let fields = [
+                        (Occurrence::Head(parent.clone()), &**head),
+                        (Occurrence::Tail(parent), &**tail),
+                    ];
+                    columns.splice(0..0, fields);
+                }
+                PatternKind::Tuple(elements) => {
+                    let fields = elements.iter().enumerate().map(|(index, element)| {
+                        (
+                            Occurrence::Element(Box::new(occurrence.clone()), index),
+                            element,
+                        )
+                    });
+                    columns.splice(0..0, fields);
+                }
+                PatternKind::Record {
+                    type_name, fields, ..
+                } => {
+                    let fields = fields.iter().map(|(field, pattern)| {
+                        let field = Occurrence::RecordField {
+                            parent: Box::new(occurrence.clone()),
+                            type_name: type_name.clone(),
+                            field: field.clone(),
+                        };
+                        (field, pattern)
+                    });
+                    columns.splice(0..0, fields);
+                }
+                _ => {}
+            }
+            Some(Row {
+                columns,
+                clause: row.clause,
+            })
+        })
+        .collect()
+}
+
+fn constructor(pattern: &Pattern) -> Option<Constructor> {
+    match &pattern.kind {
+        PatternKind::Wildcard | PatternKind::Identifier(_) => None,
+        PatternKind::Literal(lit) => Some(Constructor::Literal(lit.clone())),
+        PatternKind::EnumInstance {
+            enum_name,
+            variant_name,
+            args,
+        } => Some(Constructor::Variant {
+            enum_name: enum_name.clone(),
+            variant_name: variant_name.clone(),
+            arity: args.len(),
+        }),
+        PatternKind::EmptyList => Some(Constructor::EmptyList),
+        PatternKind::Cons { .. } => Some(Constructor::Cons),
+        PatternKind::Tuple(elements) => Some(Constructor::Tuple(elements.len())),
+        PatternKind::Record { type_name, .. } => Some(Constructor::Record(type_name.clone())),
+    }
+}
+
+/// The variables a clause's patterns bind, and where their values are.
+pub fn bindings(patterns: &[Pattern]) -> Vec<(String, Occurrence)> {
+    fn visit(pattern: &Pattern, occurrence: Occurrence, out: &mut Vec<(String, Occurrence)>) {
+        match &pattern.kind {
+            PatternKind::Identifier(name) => out.push((name.clone(), occurrence)),
+            PatternKind::EnumInstance {
+                enum_name,
+                variant_name,
+                args,
+            } => {
+                for (index, arg) in args.iter().enumerate() {
+                    let field = Occurrence::Field {
+                        parent: Box::new(occurrence.clone()),
+                        enum_name: enum_name.clone(),
+                        variant_name: variant_name.clone(),
+                        index,
+                    };
+                    visit(arg, field, out);
+                }
+            }
+            PatternKind::Cons { head, tail } => {
+                visit(head, Occurrence::Head(Box::new(occurrence.clone())), out);
+                visit(tail, Occurrence::Tail(Box::new(occurrence)), out);
+            }
+            PatternKind::Tuple(elements) => {
+                for (index, element) in elements.iter().enumerate() {
+                    let field = Occurrence::Element(Box::new(occurrence.clone()), index);
+                    visit(element, field, out);
+                }
+            }
+            PatternKind::Record {
+                type_name, fields, ..
+            } => {
+                for (field, pattern) in fields {
+                    let field = Occurrence::RecordField {
+                        parent: Box::new(occurrence.clone()),
+                        type_name: type_name.clone(),
+                        field: field.clone(),
+                    };
+                    visit(pattern, field, out);
+                }
+            }
+            PatternKind::Wildcard | PatternKind::Literal(_) | PatternKind::EmptyList => {}
+        }
+    }
+
+    let mut out = vec![];
+    for (i, pattern) in patterns.iter().enumerate() {
+        visit(pattern, Occurrence::Root(i), &mut out);
+    }
+    out
+}
+
+/// The values already known on the current path through a decision tree.
+pub type Occurrences = BTreeMap<Occurrence, (Value, Type)>;
+
+/// A block to jump to when a clause matches, taking the values of the
+/// variables it binds as parameters.
+pub struct ClauseBlock {
+    pub block: Block,
+    pub bindings: Vec<(String, Occurrence)>,
+}
+
+impl Codegen {
+    /// Generates a pattern match of `roots` against the patterns of each
+    /// clause, and the body of the first clause that matches with
+    /// `gen_body`, given the clause's index. Variables bound by the patterns
+    /// are in scope in the body. Gives the value of the body.
+    pub fn gen_pattern_match(
+        &mut self,
+        roots: Vec<(Value, Type)>,
+        clauses: &[&[Pattern]],
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+        mut gen_body: impl FnMut(&mut Self, usize, &mut FunctionBuilder) -> Result<Value, Diagnostic>,
+    ) -> Result<Value, Diagnostic> {
+        let decision = Decision::compile(clauses, &|enum_name| {
+            self.get_type(enum_name)
+                .and_then(|ty| ty.get_enum_variants())
+                .map_or(0, Vec::len)
+        });
+        let root_types: Vec<Type> = roots.iter().map(|(_, ty)| ty.clone()).collect();
+
+        let join_block = builder.create_block();
+        builder.append_block_param(join_block, ty.to_cranelift());
+        let mut clause_blocks = vec![];
+        for patterns in clauses {
+            let block = builder.create_block();
+            let bindings = bindings(patterns);
+            for (_, occurrence) in &bindings {
+                let ty = self.occurrence_type(occurrence, &root_types, span)?;
+                builder.append_block_param(block, ty.to_cranelift());
+            }
+            clause_blocks.push(ClauseBlock { block, bindings });
+        }
+
+        let values = roots
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (Occurrence::Root(i), value))
+            .collect();
+        self.gen_decision(&decision, values, &clause_blocks, span, builder)?;
+
+        for (clause, ClauseBlock { block, bindings }) in clause_blocks.iter().enumerate() {
+            builder.switch_to_block(*block);
+            builder.seal_block(*block);
+            let val = self.with_scope(|codegen| {
+                for (i, (var_name, occurrence)) in bindings.iter().enumerate() {
+                    let ty = codegen.occurrence_type(occurrence, &root_types, span)?;
+                    let var = codegen.declare_variable(var_name, ty, builder);
+                    let value = builder.block_params(*block)[i];
+                    builder.def_var(var, value);
+                }
+                gen_body(codegen, clause, builder)
+            })?;
+            builder.ins().jump(join_block, &[val.into()]);
+        }
+
+        builder.switch_to_block(join_block);
+        builder.seal_block(join_block);
+        Ok(builder.block_params(join_block)[0])
+    }
+
+    /// Generates the tests of `decision`, ending each path with a jump to the
+    /// block of the clause that matches, or a trap when none does.
+    fn gen_decision(
+        &mut self,
+        decision: &Decision,
+        mut values: Occurrences,
+        clauses: &[ClauseBlock],
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<(), Diagnostic> {
+        match decision {
+            Decision::Fail => {
+                builder.ins().trap(TrapCode::unwrap_user(NO_MATCH));
+            }
+            Decision::Match(clause) => {
+                let ClauseBlock { block, bindings } = &clauses[*clause];
+                let args = bindings
+                    .iter()
+                    .map(|(_, occurrence)| {
+                        let (value, _) =
+                            self.occurrence_value(occurrence, &mut values, span, builder)?;
+                        Ok(value.into())
+                    })
+                    .collect::<Result<Vec<_>, Diagnostic>>()?;
+                builder.ins().jump(*block, &args);
+            }
+            Decision::Switch {
+                occurrence,
+                cases,
+                default,
+            } => {
+                let (value, _) = self.occurrence_value(occurrence, &mut values, span, builder)?;
+                let blocks: Vec<Block> = cases.iter().map(|_| builder.create_block()).collect();
+                let otherwise = builder.create_block();
+                self.gen_switch(value, cases, &blocks, otherwise, span, builder)?;
+
+                for ((_, decision), block) in cases.iter().zip(blocks) {
+                    builder.switch_to_block(block);
+                    builder.seal_block(block);
+                    self.gen_decision(decision, values.clone(), clauses, span, builder)?;
+                }
+                builder.switch_to_block(otherwise);
+                builder.seal_block(otherwise);
+                match default {
+                    Some(decision) => {
+                        self.gen_decision(decision, values, clauses, span, builder)?
+                    }
+                    // Every value is covered by a case.
+                    None => {
+                        builder.ins().trap(TrapCode::unwrap_user(NO_MATCH));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Branches to the block of the case whose constructor `value` is built
+    /// with, or to `otherwise`.
+    fn gen_switch(
+        &mut self,
+        value: Value,
+        cases: &[(Constructor, Decision)],
+        blocks: &[Block],
+        otherwise: Block,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<(), Diagnostic> {
+        if let Some((Constructor::Tuple(_) | Constructor::Record(_), _)) = cases.first() {
+            builder.ins().jump(blocks[0], &[]);
+            return Ok(());
+        }
+        if let Some((Constructor::EmptyList | Constructor::Cons, _)) = cases.first() {
+            let block_of = |c: Constructor| {
+                cases
+                    .iter()
+                    .zip(blocks)
+                    .find(|((constructor, _), _)| *constructor == c)
+                    .map_or(otherwise, |(_, block)| *block)
+            };
+            let len = self.gen_list_length(value, builder);
+            let (empty, cons) = (
+                block_of(Constructor::EmptyList),
+                block_of(Constructor::Cons),
+            );
+            builder.ins().brif(len, cons, &[], empty, &[]);
+            return Ok(());
+        }
+        let mut switch = Switch::new();
+        let key = match cases.first() {
+            Some((Constructor::Variant { .. }, _)) => self.gen_load_discriminant(value, builder),
+            _ => value,
+        };
+        for ((constructor, _), &block) in cases.iter().zip(blocks) {
+            match constructor {
+                Constructor::Literal(Literal::Integer(n)) => switch.set_entry(*n, block),
+                Constructor::Literal(Literal::Bool(b)) => switch.set_entry(u128::from(*b), block),
+                Constructor::Literal(Literal::Float(f)) => {
+                    // Floats can't index a jump table: compare them one by one.
+                    let ty = builder.func.dfg.value_type(value);
+                    let f = if ty == types::F32 {
+                        builder.ins().f32const(*f as f32)
+                    } else {
+                        builder.ins().f64const(*f)
+                    };
+                    let equal = builder.ins().fcmp(FloatCC::Equal, value, f);
+                    let next = builder.create_block();
+                    builder.ins().brif(equal, block, &[], next, &[]);
+                    builder.switch_to_block(next);
+                    builder.seal_block(next);
+                }
+                Constructor::Literal(Literal::String(s)) => {
+                    // Strings are compared one by one too.
+                    let s = self.gen_string_literal(s, span, builder)?;
+                    let equal = self.gen_string_eq(value, s, span, builder)?;
+                    let next = builder.create_block();
+                    builder.ins().brif(equal, block, &[], next, &[]);
+                    builder.switch_to_block(next);
+                    builder.seal_block(next);
+                }
+                Constructor::Variant {
+                    enum_name,
+                    variant_name,
+                    ..
+                } => {
+                    let discriminant = self.discriminant(enum_name, variant_name, span)?;
+                    switch.set_entry(u128::from(discriminant), block);
+                }
+                Constructor::EmptyList
+                | Constructor::Cons
+                | Constructor::Tuple(_)
+                | Constructor::Record(_) => {
+                    return Err(internal_error("irrefutable case among others", span));
+                }
+            }
+        }
+        // Floats and strings were compared above, and a float can't be the
+        // key of a `Switch`.
+        let compared = matches!(
+            cases.first(),
+            Some((
+                Constructor::Literal(Literal::Float(_) | Literal::String(_)),
+                _
+            ))
+        );
+        if compared {
+            builder.ins().jump(otherwise, &[]);
+        } else {
+            switch.emit(builder, key, otherwise);
+        }
+        Ok(())
+    }
+
+    /// The value at `occurrence`, loading it from its parent if it isn't
+    /// known yet on this path.
+    fn occurrence_value(
+        &mut self,
+        occurrence: &Occurrence,
+        values: &mut Occurrences,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<(Value, Type), Diagnostic> {
+        if let Some(value) = values.get(occurrence) {
+            return Ok(value.clone());
+        }
+        let value = match occurrence {
+            Occurrence::Root(_) => {
+                return Err(internal_error("pattern match on a missing value", span));
+            }
+            Occurrence::Field {
+                parent,
+                enum_name,
+                variant_name,
+                index,
+            } => {
+                let (ptr, parent_type) = self.occurrence_value(parent, values, span, builder)?;
+                let ty = self.field_type(&parent_type, enum_name, variant_name, *index, span)?;
+                let field = self.gen_payload_field(
+                    ptr,
+                    (enum_name, variant_name),
+                    *index,
+                    &ty,
+                    span,
+                    builder,
+                )?;
+                (field, ty)
+            }
+            Occurrence::Head(parent) => {
+                let (list, list_type) = self.occurrence_value(parent, values, span, builder)?;
+                let head = self.gen_list_head(list, &list_type, span, builder)?;
+                (head, element_type(&list_type, span)?.clone())
+            }
+            Occurrence::Tail(parent) => {
+                let (list, list_type) = self.occurrence_value(parent, values, span, builder)?;
+                let tail = self.gen_list_tail(list, &list_type, span, builder)?;
+                (tail, list_type)
+            }
+            Occurrence::Element(parent, index) => {
+                let (tuple, tuple_type) = self.occurrence_value(parent, values, span, builder)?;
+                let element = self.gen_tuple_element(tuple, &tuple_type, *index, span, builder)?;
+                (element, tuple_elements(&tuple_type, span)?[*index].clone())
+            }
+            Occurrence::RecordField {
+                parent,
+                type_name,
+                field,
+            } => {
+                let (ptr, parent_type) = self.occurrence_value(parent, values, span, builder)?;
+                let ty = self.record_field_type(&parent_type, type_name, field, span)?;
+                let value = self.gen_record_field(ptr, type_name, field, &ty, span, builder)?;
+                (value, ty)
+            }
+        };
+        values.insert(occurrence.clone(), value.clone());
+        Ok(value)
+    }
+
+    /// The type of the value at `occurrence`, given those of the roots.
+    pub fn occurrence_type(
+        &self,
+        occurrence: &Occurrence,
+        roots: &[Type],
+        span: Span,
+    ) -> Result<Type, Diagnostic> {
+        match occurrence {
+            Occurrence::Root(i) => Ok(roots[*i].clone()),
+            Occurrence::Field {
+                parent,
+                enum_name,
+                variant_name,
+                index,
+            } => {
+                let parent_type = self.occurrence_type(parent, roots, span)?;
+                self.field_type(&parent_type, enum_name, variant_name, *index, span)
+            }
+            Occurrence::Head(parent) => {
+                let list_type = self.occurrence_type(parent, roots, span)?;
+                element_type(&list_type, span).cloned()
+            }
+            Occurrence::Tail(parent) => self.occurrence_type(parent, roots, span),
+            Occurrence::Element(parent, index) => {
+                let tuple_type = self.occurrence_type(parent, roots, span)?;
+                Ok(tuple_elements(&tuple_type, span)?[*index].clone())
+            }
+            Occurrence::RecordField {
+                parent,
+                type_name,
+                field,
+            } => {
+                let parent_type = self.occurrence_type(parent, roots, span)?;
+                self.record_field_type(&parent_type, type_name, field, span)
+            }
+        }
+    }
+
+    /// The type of field `index` of `enum_name::variant_name`, in a value of
+    /// type `parent`: type parameters of the enum stand for the arguments of
+    /// `parent`.
+    fn field_type(
+        &self,
+        parent: &Type,
+        enum_name: &str,
+        variant_name: &str,
+        index: usize,
+        span: Span,
+    ) -> Result<Type, Diagnostic> {
+        let enum_type = self.find_type(enum_name, span)?;
+        let field = enum_type
+            .get_enum_variants()
+            .and_then(|variants| variants.iter().find(|(_, v)| v.name() == variant_name))
+            .and_then(|(_, variant)| variant.types().get(index))
+            .ok_or_else(|| {
+                internal_error(
+                    format!("no field {index} in `{enum_name}::{variant_name}`"),
+                    span,
+                )
+            })?;
+        let Type::Custom(_, args) = parent else {
+            return Ok(field.clone());
+        };
+        Ok(substitute(field, enum_type.generics(), args))
+    }
+
+    /// The type of field `field` of a `type_name` record, in a value of type
+    /// `parent`.
+    fn record_field_type(
+        &self,
+        parent: &Type,
+        type_name: &str,
+        field: &str,
+        span: Span,
+    ) -> Result<Type, Diagnostic> {
+        let record_type = self.find_type(type_name, span)?;
+        let declared = record_type
+            .get_record_fields()
+            .and_then(|fields| fields.iter().find(|f| f.name() == field))
+            .ok_or_else(|| internal_error(format!("no field `{field}` in `{type_name}`"), span))?;
+        let Type::Custom(_, args) = parent else {
+            return Ok(declared.ty().clone());
+        };
+        Ok(substitute(declared.ty(), record_type.generics(), args))
+    }
+
+    /// The discriminant identifying variant `variant_name` of `enum_name`.
+    fn discriminant(
+        &self,
+        enum_name: &str,
+        variant_name: &str,
+        span: Span,
+    ) -> Result<u8, Diagnostic> {
+        self.find_type(enum_name, span)?
+            .get_enum_variants()
+            .and_then(|variants| variants.iter().find(|(_, v)| v.name() == variant_name))
+            .map(|(discriminant, _)| *discriminant)
+            .ok_or_else(|| {
+                internal_error(format!("no variant `{enum_name}::{variant_name}`"), span)
+            })
+    }
+
+    /// Enum values point to their discriminant.
+    fn gen_load_discriminant(&self, value: Value, builder: &mut FunctionBuilder) -> Value {
+        builder.ins().load(
+            types::I8,
+            MemFlagsData::trusted(),
+            value,
+            DISCRIMINANT_OFFSET,
+        )
+    }
+}
+
+/// `ty` with the type parameters `generics` replaced by `args`.
+fn substitute(ty: &Type, generics: &[String], args: &[String]) -> Type {
+    let replace = |name: &String| {
+        generics
+            .iter()
+            .position(|generic| generic == name)
+            .and_then(|i| args.get(i))
+            .unwrap_or(name)
+            .clone()
+    };
+    match ty {
+        Type::Custom(name, params) if params.is_empty() => {
+            let name = replace(name);
+            name.parse().unwrap_or(Type::Custom(name, vec![]))
+        }
+        Type::Custom(name, params) => {
+            Type::Custom(name.clone(), params.iter().map(replace).collect())
+        }
+        _ => ty.clone(),
+    }
+}
+
+/// The trap code of a pattern match where no clause applies.
+const NO_MATCH: u8 = 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(n: u128) -> Pattern {
+        Pattern::literal(Literal::Integer(n))
+    }
+
+    fn var(name: &str) -> Pattern {
+        Pattern::identifier(name.to_owned())
+    }
+
+    fn variant(name: &str, args: Vec<Pattern>) -> Pattern {
+        Pattern::enum_instance("Option".to_owned(), name.to_owned(), args)
+    }
+
+    fn case(n: u128, decision: Decision) -> (Constructor, Decision) {
+        (Constructor::Literal(Literal::Integer(n)), decision)
+    }
+
+    #[test]
+    fn test_clauses_are_tried_in_order() {
+        // sum(a, 0) = a; sum(0, b) = b; sum(a, b) = a + b;
+        let clauses = [
+            vec![var("a"), int(0)],
+            vec![int(0), var("b")],
+            vec![var("a"), var("b")],
+        ];
+        let clauses: Vec<&[Pattern]> = clauses.iter().map(Vec::as_slice).collect();
+        assert_eq!(
+            Decision::compile(&clauses, &|_| 0),
+            Decision::Switch {
+                occurrence: Occurrence::Root(1),
+                cases: vec![case(0, Decision::Match(0))],
+                default: Some(Box::new(Decision::Switch {
+                    occurrence: Occurrence::Root(0),
+                    cases: vec![case(0, Decision::Match(1))],
+                    default: Some(Box::new(Decision::Match(2))),
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn test_payloads_are_tested_after_their_variant() {
+        let clauses = [
+            vec![variant("Some", vec![int(0)])],
+            vec![variant("Some", vec![var("x")])],
+            vec![variant("None", vec![])],
+        ];
+        let clauses: Vec<&[Pattern]> = clauses.iter().map(Vec::as_slice).collect();
+        let some = Constructor::Variant {
+            enum_name: "Option".to_owned(),
+            variant_name: "Some".to_owned(),
+            arity: 1,
+        };
+        let none = Constructor::Variant {
+            enum_name: "Option".to_owned(),
+            variant_name: "None".to_owned(),
+            arity: 0,
+        };
+        let payload = Occurrence::Field {
+            parent: Box::new(Occurrence::Root(0)),
+            enum_name: "Option".to_owned(),
+            variant_name: "Some".to_owned(),
+            index: 0,
+        };
+        // Both variants are covered: no default.
+        assert_eq!(
+            Decision::compile(&clauses, &|_| 2),
+            Decision::Switch {
+                occurrence: Occurrence::Root(0),
+                cases: vec![
+                    (
+                        some,
+                        Decision::Switch {
+                            occurrence: payload,
+                            cases: vec![case(0, Decision::Match(0))],
+                            default: Some(Box::new(Decision::Match(1))),
+                        }
+                    ),
+                    (none, Decision::Match(2)),
+                ],
+                default: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tuples_need_no_default() {
+        let clauses = [vec![Pattern::tuple(vec![int(0), var("x")])], vec![var("t")]];
+        let clauses: Vec<&[Pattern]> = clauses.iter().map(Vec::as_slice).collect();
+        let element = Occurrence::Element(Box::new(Occurrence::Root(0)), 0);
+        assert_eq!(
+            Decision::compile(&clauses, &|_| 0),
+            Decision::Switch {
+                occurrence: Occurrence::Root(0),
+                cases: vec![(
+                    Constructor::Tuple(2),
+                    Decision::Switch {
+                        occurrence: element,
+                        cases: vec![case(0, Decision::Match(0))],
+                        default: Some(Box::new(Decision::Match(1))),
+                    }
+                )],
+                default: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unmatched_values_fail() {
+        let clauses = [vec![int(1)]];
+        let clauses: Vec<&[Pattern]> = clauses.iter().map(Vec::as_slice).collect();
+        assert_eq!(
+            Decision::compile(&clauses, &|_| 0),
+            Decision::Switch {
+                occurrence: Occurrence::Root(0),
+                cases: vec![case(1, Decision::Match(0))],
+                default: Some(Box::new(Decision::Fail)),
+            }
+        );
+    }
+}
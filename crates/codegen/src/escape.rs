@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+
+use ast::{
+    expressions::{Expression, ExpressionKind, MatchBody, StringPart},
+    functions::FunctionBody,
+    patterns::{Pattern, PatternKind},
+    span::Span,
+    statements::{Block, StatementKind},
+};
+
+use crate::closures::free_variables;
+
+/// The values built in a function which may outlive it, by
+/// span: those returned, stored in another value, or passed to a function,
+/// which could do either. They can't live in the function's stack frame.
+#[derive(Debug, Default)]
+pub struct Escapes {
+    values: HashSet<Span>,
+    /// Variables holding a value that escapes.
+    variables: HashSet<String>,
+    /// Whether the value the function returns escapes.
+    returned: bool,
+}
+
+impl Escapes {
+    /// The escaping values of the clauses of a function, with `bodies`.
+    /// The value it returns escapes if `returned`, unless it's copied out of
+    /// the function in registers.
+    pub fn of(bodies: &[&FunctionBody], returned: bool) -> Self {
+        let mut escapes = Self {
+            returned,
+            ..Self::default()
+        };
+        // A variable found to escape makes the value it was assigned escape,
+        // which may be another variable.
+        loop {
+            let variables = escapes.variables.len();
+            for body in bodies {
+                match body {
+                    FunctionBody::SingleLine(expr) => escapes.visit_expr(expr, returned),
+                    FunctionBody::MultiLine(block) => escapes.visit_block(block, returned),
+                }
+            }
+            if escapes.variables.len() == variables {
+                return escapes;
+            }
+        }
+    }
+
+    pub fn contains(&self, span: Span) -> bool {
+        self.values.contains(&span)
+    }
+
+    fn visit_block(&mut self, block: &Block, escapes: bool) {
+        for statement in &block.statements {
+            match &statement.kind {
+                StatementKind::Assignment(name, _, value) => {
+                    let escapes = self.variables.contains(name);
+                    self.visit_expr(value, escapes);
+                }
+                StatementKind::Return(value) => self.visit_expr(value, self.returned),
+            }
+        }
+        self.visit_expr(&block.return_expr, escapes);
+    }
+
+    /// Visits `expr`, whose value escapes if `escapes`.
+    fn visit_expr(&mut self, expr: &Expression, escapes: bool) {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => {
+                if escapes {
+                    self.variables.insert(name.clone());
+                }
+            }
+            ExpressionKind::NewEnumInstance(_, _, args) => {
+                if escapes {
+                    self.values.insert(expr.span());
+                }
+                args.iter().for_each(|arg| self.visit_expr(arg, true));
+            }
+            ExpressionKind::List(elements) | ExpressionKind::Tuple(elements) => {
+                if escapes {
+                    self.values.insert(expr.span());
+                }
+                elements
+                    .iter()
+                    .for_each(|element| self.visit_expr(element, true));
+            }
+            ExpressionKind::NewRecordInstance(_, fields) => {
+                if escapes {
+                    self.values.insert(expr.span());
+                }
+                fields
+                    .iter()
+                    .for_each(|(_, value)| self.visit_expr(value, true));
+            }
+            ExpressionKind::FunctionCall {
+                namespace,
+                arguments,
+                ..
+            } => {
+                // A tuple returned in registers is stored back in memory at
+                // the call. Built-ins may call functions in a loop, so what
+                // they return can't share a slot.
+                if escapes || namespace.is_some() {
+                    self.values.insert(expr.span());
+                }
+                arguments.iter().for_each(|arg| self.visit_expr(arg, true));
+            }
+            ExpressionKind::MethodCall {
+                receiver,
+                arguments,
+                ..
+            } => {
+                self.values.insert(expr.span());
+                self.visit_expr(receiver, true);
+                arguments.iter().for_each(|arg| self.visit_expr(arg, true));
+            }
+            ExpressionKind::Match(scrutinee, arms) => {
+                // Arms binding the whole scrutinee to a variable, or the
+                // rest of a list, which shares its elements, may let it
+                // escape through it.
+                let bound_escapes = arms.iter().any(|arm| {
+                    aliases(&arm.pattern).is_some_and(|name| self.variables.contains(name))
+                });
+                self.visit_expr(scrutinee, bound_escapes);
+                for arm in arms {
+                    match &arm.body {
+                        MatchBody::Expr(body) => self.visit_expr(body, escapes),
+                        MatchBody::Block(block) => self.visit_block(block, escapes),
+                    }
+                }
+            }
+            ExpressionKind::If(condition, then, otherwise) => {
+                self.visit_expr(condition, false);
+                self.visit_block(then, escapes);
+                self.visit_block(otherwise, escapes);
+            }
+            ExpressionKind::BinaryOp(lhs, _, rhs) => {
+                self.visit_expr(lhs, false);
+                self.visit_expr(rhs, false);
+            }
+            ExpressionKind::UnaryOp(_, operand) => self.visit_expr(operand, false),
+            // Holes are copied into the new string.
+            ExpressionKind::Interpolation(parts) => {
+                for part in parts {
+                    if let StringPart::Hole(hole) = part {
+                        self.visit_expr(hole, false);
+                    }
+                }
+            }
+            // The body of a lambda is compiled on its own, but the values
+            // it captures are stored in its closure.
+            ExpressionKind::Lambda(params, body) => {
+                if escapes {
+                    self.values.insert(expr.span());
+                }
+                self.variables.extend(free_variables(params, body));
+            }
+            // Fields of a record were stored in it, so escape already.
+            ExpressionKind::RecordAccess(..)
+            | ExpressionKind::Literal(_)
+            | ExpressionKind::Unit => {}
+        }
+    }
+}
+
+/// The variable `pattern` binds to the value it matches, or to the rest of
+/// it if it's a list.
+fn aliases(pattern: &Pattern) -> Option<&str> {
+    match &pattern.kind {
+        PatternKind::Identifier(name) => Some(name),
+        PatternKind::Cons { tail, .. } => aliases(tail),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::{expressions::MatchArm, span::FileId, statements::Statement};
+
+    use super::*;
+
+    fn record(at: usize) -> Expression {
+        Expression::new_record_instance("Point".to_owned(), vec![]).with_span(span(at))
+    }
+
+    fn span(at: usize) -> Span {
+        Span::new(FileId::default(), at, at + 1)
+    }
+
+    fn var(name: &str) -> Expression {
+        Expression::identifier(name.to_owned())
+    }
+
+    #[test]
+    fn test_returned_through_variables() {
+        let block = Block::new(
+            vec![
+                Statement::assignment("p".to_owned(), record(0)),
+                Statement::assignment("q".to_owned(), var("p")),
+                Statement::assignment("local".to_owned(), record(1)),
+            ],
+            var("q"),
+        );
+        let escapes = Escapes::of(&[&FunctionBody::MultiLine(block)], true);
+        assert!(escapes.contains(span(0)));
+        assert!(!escapes.contains(span(1)));
+    }
+
+    #[test]
+    fn test_stored_or_passed_values_escape() {
+        let stored =
+            Expression::new_enum_instance("Option".to_owned(), "Some".to_owned(), vec![record(0)]);
+        let passed = Expression::function_call("f".to_owned(), vec![record(1)]);
+        let matched = Expression::match_expr(
+            record(2),
+            vec![MatchArm {
+                pattern: Pattern::wildcard(),
+                body: MatchBody::Expr(passed),
+            }],
+        );
+        let block = Block::new(vec![Statement::assignment("x".to_owned(), stored)], matched);
+        let escapes = Escapes::of(&[&FunctionBody::MultiLine(block)], true);
+        assert!(escapes.contains(span(0)));
+        assert!(escapes.contains(span(1)));
+        assert!(!escapes.contains(span(2)));
+    }
+
+    #[test]
+    fn test_returned_in_registers() {
+        let pair = Expression::tuple(vec![record(0), var("x")]).with_span(span(1));
+        let escapes = Escapes::of(&[&FunctionBody::SingleLine(pair)], false);
+        assert!(escapes.contains(span(0)));
+        assert!(!escapes.contains(span(1)));
+    }
+
+    #[test]
+    fn test_captured_values_escape() {
+        let lambda = Expression::lambda(vec!["x".to_owned()], var("p")).with_span(span(1));
+        let block = Block::new(
+            vec![
+                Statement::assignment("p".to_owned(), record(0)),
+                Statement::assignment("f".to_owned(), lambda),
+            ],
+            Expression::unit(),
+        );
+        let escapes = Escapes::of(&[&FunctionBody::MultiLine(block)], true);
+        assert!(escapes.contains(span(0)));
+        assert!(!escapes.contains(span(1)));
+    }
+}
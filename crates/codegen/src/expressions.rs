@@ -1,41 +1,156 @@
-use core::panic;
-
-use cranelift::prelude::{FunctionBuilder, InstBuilder, IntCC, Value, types};
+use cranelift::prelude::{FloatCC, FunctionBuilder, InstBuilder, IntCC, Value, types};
 
 use ast::{
-    expressions::{BinaryOp, Expression, ExpressionKind, Literal},
+    diagnostics::Diagnostic,
+    expressions::{BinaryOp, Expression, ExpressionKind, Literal, MatchArm, MatchBody, UnaryOp},
+    patterns::Pattern,
+    span::Span,
+    statements::Block,
     types::Type,
 };
 
-use super::{Codegen, Generable};
+use super::{Codegen, Generable, internal_error, unsupported, untyped};
 
 impl Codegen {
-    pub fn gen_expression(&mut self, expr: &Expression, builder: &mut FunctionBuilder) -> Value {
+    pub fn gen_expression(
+        &mut self,
+        expr: &Expression,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let span = expr.span();
         match &expr.kind {
+            ExpressionKind::Literal(Literal::String(s)) => {
+                self.gen_string_literal(s, span, builder)
+            }
             ExpressionKind::Literal(literal) => {
-                let ty = expr.get_type().unwrap();
-                gen_literal(literal, ty, builder)
+                let ty = expr.get_type().ok_or_else(|| untyped(span))?;
+                gen_literal(literal, ty, span, builder)
             }
-            ExpressionKind::Identifier(var_name) => {
-                let (var, _) = self.get_variable(var_name).unwrap();
-                builder.use_var(*var)
+            ExpressionKind::Identifier(var_name) => match self.get_variable(var_name) {
+                Some((var, _)) => Ok(builder.use_var(*var)),
+                None => self.gen_function_value(var_name, span, builder),
+            },
+            ExpressionKind::BinaryOp(lhs, op, rhs) => {
+                self.gen_binary_op(lhs, rhs, op, span, builder)
             }
-            ExpressionKind::BinaryOp(lhs, op, rhs) => self.gen_binary_op(lhs, rhs, op, builder),
             ExpressionKind::FunctionCall {
-                name, arguments, ..
-            } => self.gen_function_call(name, arguments, builder),
-            ExpressionKind::Unit => builder.ins().iconst(types::I32, 0),
+                namespace,
+                name,
+                arguments,
+            } => self.gen_function_call(
+                namespace.as_deref(),
+                name,
+                arguments,
+                expr.get_type(),
+                span,
+                builder,
+            ),
+            ExpressionKind::MethodCall { name, .. } => Err(internal_error(
+                format!("method call `.{name}` wasn't resolved by inference"),
+                span,
+            )),
+            // After a `return`, a block has the type its context expects,
+            // and a value of that type, which is never used.
+            ExpressionKind::Unit => Ok(match expr.get_type().map(Type::to_cranelift) {
+                Some(types::F32) => builder.ins().f32const(0.0),
+                Some(types::F64) => builder.ins().f64const(0.0),
+                Some(ty) => builder.ins().iconst(ty, 0),
+                None => builder.ins().iconst(types::I32, 0),
+            }),
             ExpressionKind::NewRecordInstance(record_name, fields) => {
-                self.gen_new_record_instance(record_name, fields, builder)
+                self.gen_new_record_instance(record_name, fields, span, builder)
             }
             ExpressionKind::RecordAccess(var_name, field_name) => {
-                self.gen_record_access(var_name, field_name, builder)
+                let ty = expr.get_type().ok_or_else(|| untyped(span))?;
+                self.gen_record_access(var_name, field_name, ty, span, builder)
+            }
+            ExpressionKind::NewEnumInstance(enum_name, variant_name, args) => {
+                self.gen_new_enum_instance(enum_name, variant_name, args, span, builder)
+            }
+            ExpressionKind::Match(scrutinee, arms) => {
+                let ty = expr.get_type().ok_or_else(|| untyped(span))?;
+                self.gen_match(scrutinee, arms, ty, span, builder)
+            }
+            ExpressionKind::If(condition, then, otherwise) => {
+                let ty = expr.get_type().ok_or_else(|| untyped(span))?;
+                self.gen_if(condition, then, otherwise, ty, builder)
             }
-            ExpressionKind::NewEnumInstance(enum_name, variant_name, _) => {
-                self.gen_new_enum_instance(enum_name, variant_name, builder)
+            ExpressionKind::List(elements) => {
+                let ty = expr.get_type().ok_or_else(|| untyped(span))?;
+                self.gen_list(elements, ty, span, builder)
             }
-            _ => todo!(),
+            ExpressionKind::Tuple(elements) => {
+                let ty = expr.get_type().ok_or_else(|| untyped(span))?;
+                self.gen_tuple(elements, ty, span, builder)
+            }
+            ExpressionKind::Interpolation(parts) => self.gen_interpolation(parts, span, builder),
+            ExpressionKind::Lambda(params, body) => {
+                let ty = expr.get_type().ok_or_else(|| untyped(span))?;
+                self.gen_lambda(params, body, ty, span, builder)
+            }
+            ExpressionKind::UnaryOp(op, operand) => self.gen_unary_op(op, operand, span, builder),
+        }
+    }
+
+    fn gen_match(
+        &mut self,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let scrutinee_type = scrutinee
+            .get_type()
+            .ok_or_else(|| untyped(scrutinee.span()))?
+            .to_owned();
+        let value = self.gen_expression(scrutinee, builder)?;
+        let patterns: Vec<&[Pattern]> = arms
+            .iter()
+            .map(|arm| std::slice::from_ref(&arm.pattern))
+            .collect();
+        self.gen_pattern_match(
+            vec![(value, scrutinee_type)],
+            &patterns,
+            ty,
+            span,
+            builder,
+            |codegen, arm, builder| match &arms[arm].body {
+                MatchBody::Expr(body) => codegen.gen_expression(body, builder),
+                MatchBody::Block(block) => codegen.gen_block(block, builder),
+            },
+        )
+    }
+
+    /// Branches on `condition`, the value of the branch taken being passed
+    /// to the block after both.
+    fn gen_if(
+        &mut self,
+        condition: &Expression,
+        then: &Block,
+        otherwise: &Block,
+        ty: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let condition = self.gen_expression(condition, builder)?;
+        let then_block = builder.create_block();
+        let else_block = builder.create_block();
+        let join_block = builder.create_block();
+        builder.append_block_param(join_block, ty.to_cranelift());
+        builder
+            .ins()
+            .brif(condition, then_block, &[], else_block, &[]);
+
+        for (block, body) in [(then_block, then), (else_block, otherwise)] {
+            builder.switch_to_block(block);
+            builder.seal_block(block);
+            let val = self.with_scope(|codegen| codegen.gen_block(body, builder))?;
+            builder.ins().jump(join_block, &[val.into()]);
         }
+
+        builder.switch_to_block(join_block);
+        builder.seal_block(join_block);
+        Ok(builder.block_params(join_block)[0])
     }
 
     fn gen_binary_op(
@@ -43,33 +158,189 @@ impl Codegen {
         lhs: &Expression,
         rhs: &Expression,
         op: &BinaryOp,
+        span: Span,
         builder: &mut FunctionBuilder,
-    ) -> Value {
-        let lhs = self.gen_expression(lhs, builder);
-        let rhs = self.gen_expression(rhs, builder);
-        match op {
+    ) -> Result<Value, Diagnostic> {
+        if matches!(op, BinaryOp::And | BinaryOp::Or) {
+            return self.gen_short_circuit(lhs, rhs, op, builder);
+        }
+        let operand_type = lhs.get_type().ok_or_else(|| untyped(lhs.span()))?;
+        let is_string = *operand_type == Type::String;
+        let lhs = self.gen_expression(lhs, builder)?;
+        let rhs = self.gen_expression(rhs, builder)?;
+        if is_string {
+            return match op {
+                BinaryOp::Eq => self.gen_string_eq(lhs, rhs, span, builder),
+                BinaryOp::NotEq => {
+                    let equal = self.gen_string_eq(lhs, rhs, span, builder)?;
+                    Ok(builder.ins().bxor_imm_u(equal, 1))
+                }
+                _ => Err(unsupported(&format!("operator `{op:?}` on strings"), span)),
+            };
+        }
+        if self.options.checked_arithmetic
+            && operand_type.is_integer()
+            && let Some(val) =
+                self.gen_checked_op(op, lhs, rhs, operand_type.is_signed(), span, builder)?
+        {
+            return Ok(val);
+        }
+        let val = match op {
+            _ if operand_type.is_float() => return gen_float_op(lhs, rhs, op, span, builder),
             BinaryOp::Add => builder.ins().iadd(lhs, rhs),
             BinaryOp::Sub => builder.ins().isub(lhs, rhs),
             BinaryOp::Mul => builder.ins().imul(lhs, rhs),
-            BinaryOp::And => builder.ins().band(lhs, rhs),
-            BinaryOp::Or => builder.ins().bor(lhs, rhs),
+            BinaryOp::Div if operand_type.is_signed() => builder.ins().sdiv(lhs, rhs),
+            BinaryOp::Div => builder.ins().udiv(lhs, rhs),
+            BinaryOp::Mod if operand_type.is_signed() => builder.ins().srem(lhs, rhs),
+            BinaryOp::Mod => builder.ins().urem(lhs, rhs),
             BinaryOp::Xor => builder.ins().bxor(lhs, rhs),
-            BinaryOp::Eq => builder.ins().icmp(IntCC::Equal, lhs, rhs),
-            BinaryOp::NotEq => builder.ins().icmp(IntCC::NotEqual, lhs, rhs),
-            _ => todo!(),
-        }
+            _ => {
+                let cc = int_cc(op, operand_type.is_signed(), span)?;
+                builder.ins().icmp(cc, lhs, rhs)
+            }
+        };
+        Ok(val)
     }
+
+    /// `lhs && rhs` and `lhs || rhs` only evaluate `rhs` when `lhs` doesn't
+    /// decide the result on its own.
+    fn gen_short_circuit(
+        &mut self,
+        lhs: &Expression,
+        rhs: &Expression,
+        op: &BinaryOp,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let lhs = self.gen_expression(lhs, builder)?;
+        let rhs_block = builder.create_block();
+        let join_block = builder.create_block();
+        builder.append_block_param(join_block, Type::Bool.to_cranelift());
+        let decided = [lhs.into()];
+        match op {
+            BinaryOp::And => builder
+                .ins()
+                .brif(lhs, rhs_block, &[], join_block, &decided),
+            _ => builder
+                .ins()
+                .brif(lhs, join_block, &decided, rhs_block, &[]),
+        };
+
+        builder.switch_to_block(rhs_block);
+        builder.seal_block(rhs_block);
+        let rhs = self.gen_expression(rhs, builder)?;
+        builder.ins().jump(join_block, &[rhs.into()]);
+
+        builder.switch_to_block(join_block);
+        builder.seal_block(join_block);
+        Ok(builder.block_params(join_block)[0])
+    }
+
+    fn gen_unary_op(
+        &mut self,
+        op: &UnaryOp,
+        operand: &Expression,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let operand_type = operand.get_type().ok_or_else(|| untyped(operand.span()))?;
+        let is_float = operand_type.is_float();
+        let is_bool = *operand_type == Type::Bool;
+        // Inference checked that a negative literal fits in its type.
+        let is_literal = matches!(operand.kind, ExpressionKind::Literal(_));
+        let operand = self.gen_expression(operand, builder)?;
+        Ok(match op {
+            UnaryOp::Negate if is_float => builder.ins().fneg(operand),
+            UnaryOp::Negate if self.options.checked_arithmetic && !is_literal => {
+                self.gen_checked_negate(operand, span, builder)?
+            }
+            UnaryOp::Negate => builder.ins().ineg(operand),
+            UnaryOp::Not if is_bool => builder.ins().bxor_imm_u(operand, 1),
+            UnaryOp::Not => builder.ins().bnot(operand),
+        })
+    }
+}
+
+fn gen_float_op(
+    lhs: Value,
+    rhs: Value,
+    op: &BinaryOp,
+    span: Span,
+    builder: &mut FunctionBuilder,
+) -> Result<Value, Diagnostic> {
+    let cc = match op {
+        BinaryOp::Add => return Ok(builder.ins().fadd(lhs, rhs)),
+        BinaryOp::Sub => return Ok(builder.ins().fsub(lhs, rhs)),
+        BinaryOp::Mul => return Ok(builder.ins().fmul(lhs, rhs)),
+        BinaryOp::Div => return Ok(builder.ins().fdiv(lhs, rhs)),
+        // Cranelift has no float remainder: `lhs - trunc(lhs / rhs) * rhs`
+        // has the sign of `lhs`, like C's `fmod`.
+        BinaryOp::Mod => {
+            let quotient = builder.ins().fdiv(lhs, rhs);
+            let quotient = builder.ins().trunc(quotient);
+            let multiple = builder.ins().fmul(quotient, rhs);
+            return Ok(builder.ins().fsub(lhs, multiple));
+        }
+        BinaryOp::Eq => FloatCC::Equal,
+        BinaryOp::NotEq => FloatCC::NotEqual,
+        BinaryOp::Less => FloatCC::LessThan,
+        BinaryOp::LessEq => FloatCC::LessThanOrEqual,
+        BinaryOp::Greater => FloatCC::GreaterThan,
+        BinaryOp::GreaterEq => FloatCC::GreaterThanOrEqual,
+        BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => {
+            return Err(unsupported(&format!("operator `{op:?}` on floats"), span));
+        }
+    };
+    Ok(builder.ins().fcmp(cc, lhs, rhs))
 }
 
-fn gen_literal(lit: &Literal, ty: &Type, builder: &mut FunctionBuilder) -> Value {
-    match lit {
-        Literal::Integer(v) => builder.ins().iconst(ty.to_cranelift(), *v as i64), // u128 → i64 narrowing at codegen; type checker ensures it fits
+/// The condition of comparison `op` on integers, or `Bool`s, which are
+/// unsigned.
+fn int_cc(op: &BinaryOp, signed: bool, span: Span) -> Result<IntCC, Diagnostic> {
+    Ok(match (op, signed) {
+        (BinaryOp::Eq, _) => IntCC::Equal,
+        (BinaryOp::NotEq, _) => IntCC::NotEqual,
+        (BinaryOp::Less, true) => IntCC::SignedLessThan,
+        (BinaryOp::Less, false) => IntCC::UnsignedLessThan,
+        (BinaryOp::LessEq, true) => IntCC::SignedLessThanOrEqual,
+        (BinaryOp::LessEq, false) => IntCC::UnsignedLessThanOrEqual,
+        (BinaryOp::Greater, true) => IntCC::SignedGreaterThan,
+        (BinaryOp::Greater, false) => IntCC::UnsignedGreaterThan,
+        (BinaryOp::GreaterEq, true) => IntCC::SignedGreaterThanOrEqual,
+        (BinaryOp::GreaterEq, false) => IntCC::UnsignedGreaterThanOrEqual,
+        _ => {
+            return Err(internal_error(
+                format!("`{op:?}` is not a comparison"),
+                span,
+            ));
+        }
+    })
+}
+
+fn gen_literal(
+    lit: &Literal,
+    ty: &Type,
+    span: Span,
+    builder: &mut FunctionBuilder,
+) -> Result<Value, Diagnostic> {
+    let val = match lit {
+        // Inference checked that the value fits in `ty`; `as` keeps the bits
+        // of `U64` values above `I64::MAX`.
+        Literal::Integer(v) => builder.ins().iconst(ty.to_cranelift(), *v as i64),
         Literal::Float(v) => match ty {
             Type::F32 => builder.ins().f32const(*v as f32),
             Type::F64 => builder.ins().f64const(*v),
-            _ => panic!("this is never supposed to happen!"),
+            _ => {
+                return Err(internal_error(
+                    format!("float literal typed as `{ty}`"),
+                    span,
+                ));
+            }
         },
         Literal::Bool(v) => builder.ins().iconst(types::I8, if *v { 1 } else { 0 }),
-        Literal::String(_) => todo!(),
-    }
+        Literal::String(_) => {
+            return Err(internal_error("string literal as a scalar", span));
+        }
+    };
+    Ok(val)
 }
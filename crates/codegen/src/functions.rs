@@ -1,7 +1,10 @@
 use ast::{
+    diagnostics::{Diagnostic, codes},
     expressions::Expression,
-    functions::{FunctionBody, FunctionDeclaration, FunctionImplementation},
-    types::FunctionSignature,
+    functions::{FunctionBody, FunctionDeclaration, FunctionImplementation, QualifiedName},
+    patterns::Pattern,
+    span::Span,
+    types::{FunctionSignature, Type},
 };
 use cranelift::{
     codegen::{
@@ -16,7 +19,15 @@ use cranelift::{
 };
 use cranelift_module::Module;
 
-use crate::{Codegen, Generable};
+use crate::{
+    Codegen, Generable,
+    closures::gen_coerce,
+    escape::Escapes,
+    internal_error,
+    traits::Method,
+    tuples::{return_params, returned_in_registers},
+    unsupported,
+};
 
 impl Generable for FunctionSignature {
     type Output = Signature;
@@ -31,33 +42,50 @@ impl Generable for FunctionSignature {
             .iter()
             .map(|arg| AbiParam::new(arg.to_cranelift()))
             .collect();
-        let returns = AbiParam::new(self.return_type().to_cranelift());
         Signature {
             params,
-            returns: vec![returns],
+            returns: return_params(self.return_type()),
             call_conv: CallConv::SystemV,
         }
     }
 }
 
 impl Codegen {
-    pub fn gen_function_declaration(&mut self, function_declaration: &FunctionDeclaration) {
+    pub fn gen_function_declaration(
+        &mut self,
+        function_declaration: &FunctionDeclaration,
+    ) -> Result<(), Diagnostic> {
         let sig = function_declaration.signature();
+        let name = function_declaration.qualified_name().qualified();
+        self.declare_bounds(&name, function_declaration.bounds());
 
-        self.declare_function(function_declaration.name(), sig.to_owned());
+        self.declare_function(&name, sig.to_owned(), function_declaration.span())
     }
 
+    /// Compiles the clauses of a function into a single function, which
+    /// tries them in order.
     pub fn gen_function_implementation(
         &mut self,
-        function_implementation: &FunctionImplementation,
-    ) {
+        clauses: &[&FunctionImplementation],
+    ) -> Result<(), Diagnostic> {
+        let span = clauses[0].span();
+        let name = &clauses[0].qualified_name().qualified();
         let (fid, sig) = self
-            .get_function(function_implementation.name())
-            .unwrap()
+            .get_function(name)
+            .ok_or_else(|| {
+                Diagnostic::error(format!("implementation of undeclared function `{name}`"))
+                    .with_code(codes::UNKNOWN_FUNCTION)
+                    .with_primary(span, "no matching declaration")
+                    .with_note(format!("declare its signature, e.g. `{name}(U8) -> U8;`"))
+            })?
             .clone();
+        let bodies: Vec<_> = clauses.iter().map(|fi| fi.body()).collect();
+        self.escapes = Escapes::of(&bodies, !returned_in_registers(sig.return_type()));
         self.with_scope(|codegen| {
-            let mut func =
-                Function::with_name_signature(UserFuncName::user(0, 0), sig.to_cranelift());
+            let mut func = Function::with_name_signature(
+                UserFuncName::user(0, 0),
+                codegen.function_signature(name, &sig),
+            );
 
             let mut func_ctx = FunctionBuilderContext::new();
             let mut builder = FunctionBuilder::new(&mut func, &mut func_ctx);
@@ -67,31 +95,48 @@ impl Codegen {
             builder.switch_to_block(entry_block);
             builder.seal_block(entry_block);
 
-            function_implementation
+            let roots = sig
                 .arguments()
                 .iter()
-                .zip(sig.arguments())
                 .enumerate()
-                .for_each(|(i, (pattern, ty))| {
-                    // TODO: full pattern support in params; only simple bindings for now
-                    let ast::patterns::Pattern::Identifier(var_name) = pattern else {
-                        todo!("pattern parameter in codegen")
-                    };
-                    let var = codegen.declare_variable(var_name, ty.to_owned());
-                    builder.declare_var(ty.to_cranelift());
-                    let tmp = builder.block_params(entry_block)[i];
-                    builder.def_var(var, tmp);
-                });
-
-            let val = match function_implementation.body() {
-                FunctionBody::SingleLine(expression) => {
-                    codegen.gen_expression(expression, &mut builder)
-                }
-                FunctionBody::MultiLine(block) => codegen.gen_block(block, &mut builder),
-            };
-
-            builder.ins().return_(&[val]);
-            builder.finalize();
+                .map(|(i, ty)| (builder.block_params(entry_block)[i], ty.to_owned()))
+                .collect();
+            // The dictionaries follow the arguments.
+            let dictionaries = builder.block_params(entry_block)[sig.arguments().len()..].to_vec();
+            codegen.dictionaries = codegen
+                .bounds
+                .get(name)
+                .into_iter()
+                .flatten()
+                .cloned()
+                .zip(dictionaries)
+                .collect();
+            let exit = builder.create_block();
+            builder.append_block_param(exit, sig.return_type().to_cranelift());
+            codegen.exit = Some(exit);
+            let patterns: Vec<&[Pattern]> = clauses.iter().map(|fi| fi.arguments()).collect();
+            let val = codegen.gen_pattern_match(
+                roots,
+                &patterns,
+                sig.return_type(),
+                span,
+                &mut builder,
+                |codegen, clause, builder| match clauses[clause].body() {
+                    FunctionBody::SingleLine(expression) => {
+                        codegen.gen_expression(expression, builder)
+                    }
+                    FunctionBody::MultiLine(block) => codegen.gen_block(block, builder),
+                },
+            )?;
+            builder.ins().jump(exit, &[val.into()]);
+            builder.switch_to_block(exit);
+            let val = builder.block_params(exit)[0];
+            codegen.dictionaries.clear();
+            codegen.exit = None;
+            let vals = codegen.gen_return_values(val, sig.return_type(), span, &mut builder)?;
+            builder.ins().return_(&vals);
+            builder.seal_all_blocks();
+            builder.finalize(codegen.module.isa().frontend_config());
 
             verify_function(
                 &func,
@@ -100,29 +145,101 @@ impl Codegen {
                     isa: None,
                 },
             )
-            .unwrap();
-
-            eprintln!("== IR ==\n{:?}", func);
+            .map_err(|e| internal_error(format!("invalid IR for `{name}`: {e}"), span))?;
 
             let mut ctx = Context::for_function(func);
-            codegen.module.define_function(fid, &mut ctx).unwrap();
-        });
+            codegen
+                .module
+                .define_function(fid, &mut ctx)
+                .map_err(|e| internal_error(format!("cannot define `{name}`: {e}"), span))
+        })
     }
 
     pub fn gen_function_call(
         &mut self,
+        namespace: Option<&str>,
         function_name: &str,
-        args: &[Expression],
+        arguments: &[Expression],
+        ty: Option<&Type>,
+        span: Span,
         builder: &mut FunctionBuilder,
-    ) -> Value {
-        let args: Vec<_> = args
+    ) -> Result<Value, Diagnostic> {
+        let args = arguments
             .iter()
             .map(|e| self.gen_expression(e, builder))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Functions of the program come first: `List::sum` may be declared
+        // next to the built-in `List::map`.
+        let qualified = QualifiedName::new(namespace.map(str::to_owned), function_name).qualified();
+        let function_name = match namespace {
+            Some(_) if self.get_function(&qualified).is_some() => qualified,
+            Some("String") => return self.gen_string_builtin(function_name, &args, span, builder),
+            Some("Int") => {
+                return self.gen_int_builtin(function_name, arguments, &args, ty, span, builder);
+            }
+            Some("List") => {
+                return self.gen_list_builtin(function_name, arguments, &args, span, builder);
+            }
+            // A variable holding a function shadows a function of the same name.
+            None => {
+                if let Some((var, Type::Function(signature))) = self.get_variable(function_name) {
+                    let (var, signature) = (*var, signature.clone());
+                    let callee = builder.use_var(var);
+                    return Ok(self.gen_closure_call(callee, &signature, &args, builder));
+                }
+                qualified
+            }
+            Some(trait_name) => match self.get_trait(trait_name) {
+                Some(td) => match self.resolve_method(td, function_name, arguments, ty, span)? {
+                    Method::Direct(name) => name,
+                    Method::Dispatched {
+                        dictionary,
+                        index,
+                        signature,
+                    } => {
+                        return Ok(
+                            self.gen_dispatched_call(dictionary, index, &signature, &args, builder)
+                        );
+                    }
+                },
+                None => qualified,
+            },
+        };
+        let function_name = function_name.as_str();
+        let (func_id, signature) = self.get_function(function_name).ok_or_else(|| {
+            Diagnostic::error(format!("cannot find function `{function_name}`"))
+                .with_code(codes::UNKNOWN_FUNCTION)
+                .with_primary(span, "not declared")
+        })?;
+        let (func_id, signature) = (*func_id, signature.clone());
+        if args.len() < signature.arguments().len() {
+            if self.bounds.contains_key(function_name) {
+                return Err(unsupported(
+                    "partially applying a function with trait bounds",
+                    span,
+                ));
+            }
+            return self.gen_partial_application(func_id, &signature, &args, span, builder);
+        }
+        let fref = self.module.declare_func_in_func(func_id, builder.func);
+        // Values of a type parameter are passed and returned in slots.
+        let mut args: Vec<_> = args
+            .into_iter()
+            .zip(signature.arguments())
+            .map(|(arg, param)| gen_coerce(arg, param.to_cranelift(), builder))
             .collect();
-        let (func_id, _) = self.get_function(function_name).unwrap();
-        let fref = self.module.declare_func_in_func(*func_id, builder.func);
+        let dictionaries =
+            self.gen_dictionaries(function_name, &signature, arguments, ty, span, builder)?;
+        args.extend(dictionaries);
 
         let i = builder.ins().call(fref, &args);
-        builder.inst_results(i)[0]
+        let results = builder.inst_results(i).to_vec();
+        let return_type = signature.return_type();
+        match (results.as_slice(), ty) {
+            ([result], Some(ty)) if !returned_in_registers(return_type) => {
+                Ok(gen_coerce(*result, ty.to_cranelift(), builder))
+            }
+            _ => self.gen_call_result(&results, return_type, span, builder),
+        }
     }
 }
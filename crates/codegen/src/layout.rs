@@ -0,0 +1,165 @@
+use ast::types::{
+    Type,
+    custom::{Field, Variant},
+};
+
+use crate::Generable;
+
+/// Size and alignment in bytes of a value stored in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub size: u32,
+    pub align: u32,
+}
+
+impl Layout {
+    pub const EMPTY: Self = Self { size: 0, align: 1 };
+
+    /// Scalars are aligned to their size. Records, enums, strings and lists
+    /// are stored as a pointer to their contents, and so are values of a type
+    /// parameter, which may stand for any type: a pointer-sized slot fits any
+    /// scalar.
+    pub fn of(ty: &Type) -> Self {
+        let size = ty.size();
+        Self { size, align: size }
+    }
+
+    /// `align` as the log2 cranelift expects for stack slots.
+    pub const fn align_shift(self) -> u8 {
+        self.align.trailing_zeros() as u8
+    }
+
+    /// Lays out `fields` one after the other after `self`, each at the first
+    /// offset aligned for it. Returns the offsets of the fields, and the
+    /// layout of the whole, padded to a multiple of its alignment.
+    pub fn extend(self, fields: impl IntoIterator<Item = Self>) -> (Self, Vec<u32>) {
+        let mut end = self.size;
+        let mut align = self.align;
+        let offsets = fields
+            .into_iter()
+            .map(|field| {
+                let offset = end.next_multiple_of(field.align);
+                end = offset + field.size;
+                align = align.max(field.align);
+                offset
+            })
+            .collect();
+        let layout = Self {
+            size: end.next_multiple_of(align),
+            align,
+        };
+        (layout, offsets)
+    }
+}
+
+/// Records hold their fields in declaration order, each aligned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordLayout {
+    pub layout: Layout,
+    /// The offset of each field, by name.
+    offsets: Vec<(String, u32)>,
+}
+
+impl RecordLayout {
+    pub fn new(fields: &[Field]) -> Self {
+        let (layout, offsets) =
+            Layout::EMPTY.extend(fields.iter().map(|field| Layout::of(field.ty())));
+        let offsets = fields
+            .iter()
+            .map(|field| field.name().to_owned())
+            .zip(offsets)
+            .collect();
+        Self { layout, offsets }
+    }
+
+    pub fn field_offset(&self, field_name: &str) -> Option<i32> {
+        self.offsets
+            .iter()
+            .find(|(name, _)| name == field_name)
+            .map(|(_, offset)| *offset as i32)
+    }
+}
+
+/// Enum values are tagged unions: a one byte discriminant, followed by the
+/// payload of their variant. Every variant shares the same storage, large
+/// and aligned enough for any of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumLayout {
+    pub layout: Layout,
+    /// The offsets of the payload fields of each variant, by name.
+    payloads: Vec<(String, Vec<u32>)>,
+}
+
+/// Where the discriminant of an enum value is.
+pub const DISCRIMINANT_OFFSET: i32 = 0;
+
+impl EnumLayout {
+    pub fn new(variants: &[(u8, Variant)]) -> Self {
+        let discriminant = Layout::of(&Type::U8);
+        let mut layout = discriminant;
+        let payloads = variants
+            .iter()
+            .map(|(_, variant)| {
+                let (payload, offsets) =
+                    discriminant.extend(variant.types().iter().map(Layout::of));
+                layout.size = layout.size.max(payload.size);
+                layout.align = layout.align.max(payload.align);
+                (variant.name().to_owned(), offsets)
+            })
+            .collect();
+        Self { layout, payloads }
+    }
+
+    /// The offset of field `index` of the payload of `variant_name`.
+    pub fn field_offset(&self, variant_name: &str, index: usize) -> Option<i32> {
+        self.payloads
+            .iter()
+            .find(|(name, _)| name == variant_name)
+            .and_then(|(_, offsets)| offsets.get(index))
+            .map(|offset| *offset as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_are_aligned() {
+        let fields = [Type::U8, Type::I32, Type::Bool, Type::I64].map(|ty| Layout::of(&ty));
+        let (layout, offsets) = Layout::EMPTY.extend(fields);
+        assert_eq!(offsets, [0, 4, 8, 16]);
+        assert_eq!(layout, Layout { size: 24, align: 8 });
+    }
+
+    #[test]
+    fn test_record_fields_keep_their_order() {
+        let fields = [
+            Field::new("name", Type::String),
+            Field::new("age", Type::U8),
+            Field::new("height", Type::U16),
+            Field::new("favorite_flavor", Type::Custom("Option".to_owned(), vec![])),
+        ];
+        let layout = RecordLayout::new(&fields);
+        assert_eq!(layout.layout, Layout { size: 24, align: 8 });
+        assert_eq!(layout.field_offset("age"), Some(8));
+        assert_eq!(layout.field_offset("height"), Some(10));
+        assert_eq!(layout.field_offset("favorite_flavor"), Some(16));
+        assert_eq!(layout.field_offset("weight"), None);
+    }
+
+    #[test]
+    fn test_enum_fits_its_largest_variant() {
+        let variants = [
+            (0, Variant::new("Empty", vec![])),
+            (1, Variant::new("Small", vec![Type::U8, Type::U16])),
+            (2, Variant::new("Large", vec![Type::F64])),
+        ];
+        let layout = EnumLayout::new(&variants);
+        assert_eq!(layout.layout, Layout { size: 16, align: 8 });
+        assert_eq!(layout.field_offset("Small", 0), Some(1));
+        assert_eq!(layout.field_offset("Small", 1), Some(2));
+        assert_eq!(layout.field_offset("Large", 0), Some(8));
+        assert_eq!(layout.field_offset("Empty", 0), None);
+    }
+}
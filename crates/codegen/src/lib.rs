@@ -1,19 +1,35 @@
+use std::collections::BTreeMap;
+
 use ast::{
+    diagnostics::{Diagnostic, codes},
     functions::{FunctionDeclaration, FunctionImplementation},
     program::Program,
+    span::Span,
     statements::Block,
+    traits::{Bound, TraitDeclaration},
     types::{CustomType, FunctionSignature, Type},
 };
-use cranelift::prelude::{Variable, settings::Flags};
+use cranelift::prelude::{Configurable, FunctionBuilder, Value, Variable, settings::Flags};
 use cranelift_module::{FuncId, Module};
 use cranelift_object::{ObjectBuilder, ObjectModule};
+use escape::Escapes;
 use scope::{Scope, Var};
 
+mod checks;
+mod closures;
 mod custom_types;
+mod decision_tree;
+mod escape;
 mod expressions;
 mod functions;
+mod layout;
+mod lists;
+mod runtime;
 mod scope;
 mod statements;
+mod strings;
+mod traits;
+mod tuples;
 mod types;
 
 pub trait Generable {
@@ -25,15 +41,45 @@ pub trait Generable {
     fn to_cranelift(&self) -> Self::Output;
 }
 
+/// How programs are compiled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    /// Whether arithmetic that overflows or divides by zero aborts the
+    /// program, rather than wrapping or faulting.
+    pub checked_arithmetic: bool,
+}
+
 pub struct Codegen {
     scopes: Vec<Scope>,
+    /// The values escaping the function being compiled.
+    escapes: Escapes,
+    traits: Vec<TraitDeclaration>,
+    /// The bounds of the functions taking dictionaries, by name.
+    bounds: BTreeMap<String, Vec<Bound>>,
+    /// The dictionaries the function being compiled takes for its bounds.
+    dictionaries: Vec<(Bound, Value)>,
+    /// The block of the function being compiled that `return` jumps to,
+    /// with the value returned.
+    exit: Option<cranelift::prelude::Block>,
     pub module: ObjectModule,
     flags: Flags,
+    options: Options,
+    /// The program's source, to locate failed checks.
+    source: String,
 }
 
 impl Default for Codegen {
     fn default() -> Self {
-        let flags_builder = cranelift::prelude::settings::builder();
+        Self::new(Options::default(), "")
+    }
+}
+
+impl Codegen {
+    /// Compiles `source`, once parsed and typed, with `options`.
+    pub fn new(options: Options, source: &str) -> Self {
+        let mut flags_builder = cranelift::prelude::settings::builder();
+        // Executables are linked as position independent, and call into libc.
+        flags_builder.set("is_pic", "true").unwrap();
         let flags = cranelift::prelude::settings::Flags::new(flags_builder);
         let isa = cranelift_native::builder()
             .unwrap()
@@ -43,9 +89,16 @@ impl Default for Codegen {
             ObjectBuilder::new(isa, "main", cranelift_module::default_libcall_names()).unwrap();
         let module = ObjectModule::new(module_builder);
         Self {
-            scopes: vec![Scope::new(0)],
+            scopes: vec![Scope::new()],
+            escapes: Escapes::default(),
+            traits: vec![],
+            bounds: BTreeMap::new(),
+            dictionaries: vec![],
+            exit: None,
             module,
             flags,
+            options,
+            source: source.to_owned(),
         }
     }
 }
@@ -58,42 +111,115 @@ impl Codegen {
         }
     }
 
-    fn compile_function_declarations(&mut self, function_declarations: &[FunctionDeclaration]) {
+    fn compile_function_declarations(
+        &mut self,
+        function_declarations: &[FunctionDeclaration],
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
         for fd in function_declarations {
-            self.gen_function_declaration(fd);
+            if let Err(diagnostic) = self.gen_function_declaration(fd) {
+                diagnostics.push(diagnostic);
+            }
         }
     }
 
     fn compile_function_implementations(
         &mut self,
         function_implementations: &[FunctionImplementation],
+        diagnostics: &mut Vec<Diagnostic>,
     ) {
+        // The clauses of a function, in source order.
+        let mut functions: Vec<Vec<&FunctionImplementation>> = vec![];
         for fi in function_implementations {
-            self.gen_function_implementation(fi);
+            match functions
+                .iter_mut()
+                .find(|clauses| clauses[0].qualified_name() == fi.qualified_name())
+            {
+                Some(clauses) => clauses.push(fi),
+                None => functions.push(vec![fi]),
+            }
+        }
+        for clauses in functions {
+            if let Err(diagnostic) = self.gen_function_implementation(&clauses) {
+                diagnostics.push(diagnostic);
+            }
         }
     }
 
-    fn compile_entrypoint(&mut self, entry_point: &Block) {
-        let ty = entry_point.return_expr.get_type().unwrap();
-        self.gen_function_declaration(&FunctionDeclaration::main(ty));
+    fn compile_entrypoint(&mut self, entry_point: &Block) -> Result<(), Diagnostic> {
+        let return_expr = &entry_point.return_expr;
+        let ty = return_expr
+            .get_type()
+            .ok_or_else(|| untyped(return_expr.span()))?;
+        self.gen_function_declaration(&FunctionDeclaration::main(ty))?;
 
-        self.gen_function_implementation(&FunctionImplementation::main(entry_point));
+        self.gen_function_implementation(&[&FunctionImplementation::main(entry_point)])
     }
 
-    pub fn compile_program_to_object(mut self, program: &Program) -> Vec<u8> {
+    /// # Errors
+    /// Every construct that couldn't be compiled.
+    pub fn compile_program_to_object(
+        mut self,
+        program: &Program,
+    ) -> Result<Vec<u8>, Vec<Diagnostic>> {
+        let mut diagnostics = vec![];
+        self.define_runtime()
+            .map_err(|diagnostic| vec![diagnostic])?;
         self.compile_type_definitions(&program.type_definitions);
-        self.compile_function_declarations(&program.function_declarations);
-        self.compile_function_implementations(&program.function_implementations);
-        self.compile_entrypoint(&program.entry_point);
-        self.module.finish().emit().unwrap()
+        self.compile_type_definitions(&CustomType::builtins());
+        self.traits.clone_from(&program.traits);
+        self.compile_function_declarations(&program.function_declarations, &mut diagnostics);
+        self.compile_function_implementations(&program.function_implementations, &mut diagnostics);
+        if let Err(diagnostic) = self.compile_entrypoint(&program.entry_point) {
+            diagnostics.push(diagnostic);
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        self.module.finish().emit().map_err(|e| {
+            vec![internal_error(
+                format!("cannot emit object file: {e}"),
+                Span::DUMMY,
+            )]
+        })
     }
 }
 
+/// The name the function `Option::map` has in the object file:
+/// `elk.Option.map`, which linkers and debuggers accept. The prefix keeps the
+/// functions of the program apart from those of the C library, like `free` or
+/// `abs`; only the entry point keeps its name, for the C runtime to call it.
+fn symbol(qualified: &str) -> String {
+    match qualified {
+        "main" => qualified.to_string(),
+        _ => format!("elk.{}", qualified.replace("::", ".")),
+    }
+}
+
+/// A construct the compiler doesn't know how to generate code for yet.
+fn unsupported(what: &str, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("{what} is not supported yet"))
+        .with_code(codes::UNSUPPORTED)
+        .with_primary(span, "not supported by code generation")
+}
+
+/// A bug in the compiler rather than in the program being compiled.
+fn internal_error(message: impl Into<String>, span: Span) -> Diagnostic {
+    Diagnostic::error(message)
+        .with_code(codes::INTERNAL)
+        .with_primary(span, "while compiling this")
+        .with_note("this is a bug in the compiler")
+}
+
+/// Type inference should have annotated every expression before codegen.
+fn untyped(span: Span) -> Diagnostic {
+    internal_error("expression has no inferred type", span)
+}
+
 /// Scoping
 impl Codegen {
     fn enter_scope(&mut self) {
-        let current_counter = self.scopes.last().map_or(0, |scope| scope.counter());
-        self.scopes.push(Scope::new(current_counter + 1));
+        self.scopes.push(Scope::new());
     }
 
     fn exit_scope(&mut self) {
@@ -110,26 +236,40 @@ impl Codegen {
         result
     }
 
-    pub fn declare_variable(&mut self, var_name: &str, ty: Type) -> Variable {
+    /// Declares `var_name` in the function being built, and in the scope.
+    pub fn declare_variable(
+        &mut self,
+        var_name: &str,
+        ty: Type,
+        builder: &mut FunctionBuilder,
+    ) -> Variable {
+        let var = builder.declare_var(ty.to_cranelift());
         self.scopes
             .last_mut()
             .unwrap()
-            .declare_variable(var_name, ty)
+            .declare_variable(var_name, var, ty);
+        var
     }
 
-    fn declare_function(&mut self, func_name: &str, signature: FunctionSignature) {
+    fn declare_function(
+        &mut self,
+        func_name: &str,
+        signature: FunctionSignature,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
         let func_id = self
             .module
             .declare_function(
-                func_name,
+                &symbol(func_name),
                 cranelift_module::Linkage::Export,
-                &signature.to_cranelift(),
+                &self.function_signature(func_name, &signature),
             )
-            .unwrap();
+            .map_err(|e| internal_error(format!("cannot declare `{func_name}`: {e}"), span))?;
         self.scopes
             .last_mut()
             .unwrap()
             .declare_function(func_name, func_id, signature);
+        Ok(())
     }
 
     fn define_type(&mut self, custom_type: &CustomType) {
@@ -153,4 +293,34 @@ impl Codegen {
     fn get_type(&self, type_name: &str) -> Option<&CustomType> {
         self.scopes.iter().rev().find_map(|s| s.get_type(type_name))
     }
+
+    /// [`Self::get_variable`], reporting a diagnostic at `span` when missing.
+    fn find_variable(&self, var_name: &str, span: Span) -> Result<&Var, Diagnostic> {
+        self.get_variable(var_name).ok_or_else(|| {
+            Diagnostic::error(format!("cannot find variable `{var_name}`"))
+                .with_code(codes::UNKNOWN_VARIABLE)
+                .with_primary(span, "not found in this scope")
+        })
+    }
+
+    /// [`Self::get_type`], reporting a diagnostic at `span` when missing.
+    fn find_type(&self, type_name: &str, span: Span) -> Result<&CustomType, Diagnostic> {
+        self.get_type(type_name).ok_or_else(|| {
+            Diagnostic::error(format!("cannot find type `{type_name}`"))
+                .with_code(codes::UNKNOWN_TYPE)
+                .with_primary(span, "not defined")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbols_are_prefixed() {
+        assert_eq!(symbol("free"), "elk.free");
+        assert_eq!(symbol("Option::map"), "elk.Option.map");
+        assert_eq!(symbol("main"), "main");
+    }
 }
@@ -0,0 +1,314 @@
+use ast::{
+    diagnostics::Diagnostic,
+    expressions::Expression,
+    span::Span,
+    types::{CompoundType, FunctionSignature, Type},
+};
+use cranelift::{
+    codegen::ir::MemFlagsData,
+    prelude::{FunctionBuilder, InstBuilder, IntCC, Value, types},
+};
+use cranelift_module::Module;
+
+use crate::{Codegen, Generable, internal_error, layout::Layout, runtime::ALLOC, unsupported};
+
+// Lists are a pointer to their elements and their number, like strings. The
+// elements follow each other, each in a slot the size of its type. Lists are
+// immutable, so they can share their elements: the tail of a list points into
+// it.
+const LIST_PTR: i32 = 0;
+const LIST_LEN: i32 = 8;
+const LIST: Layout = Layout { size: 16, align: 8 };
+
+impl Codegen {
+    /// A list literal of type `ty`, with its elements stored right after it.
+    pub fn gen_list(
+        &mut self,
+        elements: &[Expression],
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let element = element_type(ty, span)?;
+        let (layout, offsets) = LIST.extend(elements.iter().map(|_| Layout::of(element)));
+        let list = self.gen_alloc(layout, span, builder)?;
+        let flags = MemFlagsData::trusted();
+        let ptr = builder.ins().iadd_imm_s(list, i64::from(LIST.size));
+        builder.ins().store(flags, ptr, list, LIST_PTR);
+        let len = builder.ins().iconst(types::I64, elements.len() as i64);
+        builder.ins().store(flags, len, list, LIST_LEN);
+        for (expr, offset) in elements.iter().zip(offsets) {
+            let value = self.gen_expression(expr, builder)?;
+            builder.ins().store(flags, value, list, offset as i32);
+        }
+        Ok(list)
+    }
+
+    pub fn gen_list_length(&self, list: Value, builder: &mut FunctionBuilder) -> Value {
+        builder
+            .ins()
+            .load(types::I64, MemFlagsData::trusted(), list, LIST_LEN)
+    }
+
+    /// The first element of `list`, a non-empty list of type `ty`.
+    pub fn gen_list_head(
+        &self,
+        list: Value,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let element = element_type(ty, span)?;
+        let flags = MemFlagsData::trusted();
+        let ptr = self.module.target_config().pointer_type();
+        let elements = builder.ins().load(ptr, flags, list, LIST_PTR);
+        Ok(builder
+            .ins()
+            .load(element.to_cranelift(), flags, elements, 0))
+    }
+
+    /// The elements of `list`, a non-empty list of type `ty`, but the first.
+    pub fn gen_list_tail(
+        &mut self,
+        list: Value,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let stride = Layout::of(element_type(ty, span)?).size;
+        let flags = MemFlagsData::trusted();
+        let ptr = self.module.target_config().pointer_type();
+        let elements = builder.ins().load(ptr, flags, list, LIST_PTR);
+        let elements = builder.ins().iadd_imm_s(elements, i64::from(stride));
+        let len = self.gen_list_length(list, builder);
+        let len = builder.ins().iadd_imm_s(len, -1);
+        let tail = self.gen_heap_alloc(LIST, span, builder)?;
+        builder.ins().store(flags, elements, tail, LIST_PTR);
+        builder.ins().store(flags, len, tail, LIST_LEN);
+        Ok(tail)
+    }
+
+    /// A call to `List::name`, a built-in function, with `args` evaluated to
+    /// `values`.
+    pub fn gen_list_builtin(
+        &mut self,
+        name: &str,
+        args: &[Expression],
+        values: &[Value],
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let types: Vec<Option<&Type>> = args.iter().map(Expression::get_type).collect();
+        match (name, values, types.as_slice()) {
+            ("length", [list], _) => Ok(self.gen_list_length(*list, builder)),
+            ("map", [list, f], [Some(ty), Some(Type::Function(signature))]) => {
+                self.gen_list_map(*list, ty, *f, signature, span, builder)
+            }
+            ("filter", [list, f], [Some(ty), Some(Type::Function(signature))]) => {
+                self.gen_list_filter(*list, ty, *f, signature, span, builder)
+            }
+            ("fold", [list, init, f], [Some(ty), _, Some(Type::Function(signature))]) => {
+                let element = element_type(ty, span)?.clone();
+                let acc_type = signature.return_type().to_cranelift();
+                let [acc] = self.gen_list_loop(
+                    *list,
+                    &element,
+                    [(*init, acc_type)],
+                    span,
+                    builder,
+                    |codegen, builder, _, element, [acc]| {
+                        Ok([codegen.gen_closure_call(*f, signature, &[acc, element], builder)])
+                    },
+                )?;
+                Ok(acc)
+            }
+            _ => Err(unsupported(&format!("`List::{name}`"), span)),
+        }
+    }
+
+    /// The list of `f`, of type `signature`, applied to each element of
+    /// `list`, of type `ty`.
+    fn gen_list_map(
+        &mut self,
+        list: Value,
+        ty: &Type,
+        f: Value,
+        signature: &FunctionSignature,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let element = element_type(ty, span)?.clone();
+        let stride = Layout::of(signature.return_type()).size;
+        let len = self.gen_list_length(list, builder);
+        let (mapped, elements) = self.gen_list_alloc(len, stride, span, builder)?;
+        self.gen_list_loop(
+            list,
+            &element,
+            [],
+            span,
+            builder,
+            |codegen, builder, index, element, []| {
+                let value = codegen.gen_closure_call(f, signature, &[element], builder);
+                let slot = element_address(elements, index, stride, builder);
+                builder.ins().store(MemFlagsData::trusted(), value, slot, 0);
+                Ok([])
+            },
+        )?;
+        Ok(mapped)
+    }
+
+    /// The list of the elements of `list`, of type `ty`, for which `f`, of
+    /// type `signature`, gives `True`.
+    fn gen_list_filter(
+        &mut self,
+        list: Value,
+        ty: &Type,
+        f: Value,
+        signature: &FunctionSignature,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let element = element_type(ty, span)?.clone();
+        let stride = Layout::of(&element).size;
+        let len = self.gen_list_length(list, builder);
+        let (filtered, elements) = self.gen_list_alloc(len, stride, span, builder)?;
+        let zero = builder.ins().iconst(types::I64, 0);
+        let [kept] = self.gen_list_loop(
+            list,
+            &element,
+            [(zero, types::I64)],
+            span,
+            builder,
+            |codegen, builder, _, element, [kept]| {
+                // Every element is copied after those kept, and only counted
+                // if it's kept too.
+                let keep = codegen.gen_closure_call(f, signature, &[element], builder);
+                let slot = element_address(elements, kept, stride, builder);
+                builder
+                    .ins()
+                    .store(MemFlagsData::trusted(), element, slot, 0);
+                let keep = builder.ins().uextend(types::I64, keep);
+                Ok([builder.ins().iadd(kept, keep)])
+            },
+        )?;
+        builder
+            .ins()
+            .store(MemFlagsData::trusted(), kept, filtered, LIST_LEN);
+        Ok(filtered)
+    }
+
+    /// A list of `len` elements of `stride` bytes, still to be written, and
+    /// the address of its first element.
+    fn gen_list_alloc(
+        &mut self,
+        len: Value,
+        stride: u32,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<(Value, Value), Diagnostic> {
+        let flags = MemFlagsData::trusted();
+        let ptr = self.module.target_config().pointer_type();
+        let stride = builder.ins().iconst(ptr, i64::from(stride));
+        let size = builder.ins().imul(len, stride);
+        let size = builder.ins().iadd_imm_s(size, i64::from(LIST.size));
+        let align = builder.ins().iconst(ptr, i64::from(LIST.align));
+        let list = self.gen_runtime_call(ALLOC, &[size, align], span, builder)?;
+        let elements = builder.ins().iadd_imm_s(list, i64::from(LIST.size));
+        builder.ins().store(flags, elements, list, LIST_PTR);
+        builder.ins().store(flags, len, list, LIST_LEN);
+        Ok((list, elements))
+    }
+
+    /// Runs `body` on each element of `list`, a list of `element`s, with its
+    /// index and the values `state` starts with, which each run updates.
+    /// Gives the values of `state` after the last element.
+    fn gen_list_loop<const N: usize>(
+        &mut self,
+        list: Value,
+        element: &Type,
+        state: [(Value, types::Type); N],
+        span: Span,
+        builder: &mut FunctionBuilder,
+        mut body: impl FnMut(
+            &mut Self,
+            &mut FunctionBuilder,
+            Value,
+            Value,
+            [Value; N],
+        ) -> Result<[Value; N], Diagnostic>,
+    ) -> Result<[Value; N], Diagnostic> {
+        let ptr = self.module.target_config().pointer_type();
+        let stride = Layout::of(element).size;
+        let header = builder.create_block();
+        let next = builder.create_block();
+        let exit = builder.create_block();
+        builder.append_block_param(header, types::I64);
+        for (_, ty) in &state {
+            builder.append_block_param(header, *ty);
+            builder.append_block_param(exit, *ty);
+        }
+
+        let len = self.gen_list_length(list, builder);
+        let elements = builder
+            .ins()
+            .load(ptr, MemFlagsData::trusted(), list, LIST_PTR);
+        let zero = builder.ins().iconst(types::I64, 0);
+        let args: Vec<_> = std::iter::once(zero)
+            .chain(state.iter().map(|(value, _)| *value))
+            .map(Into::into)
+            .collect();
+        builder.ins().jump(header, &args);
+
+        builder.switch_to_block(header);
+        let params = builder.block_params(header).to_vec();
+        let (index, values) = (params[0], &params[1..]);
+        let more = builder.ins().icmp(IntCC::UnsignedLessThan, index, len);
+        let values_args: Vec<_> = values.iter().map(|value| (*value).into()).collect();
+        builder.ins().brif(more, next, &[], exit, &values_args);
+
+        builder.switch_to_block(next);
+        builder.seal_block(next);
+        let slot = element_address(elements, index, stride, builder);
+        let value = builder
+            .ins()
+            .load(element.to_cranelift(), MemFlagsData::trusted(), slot, 0);
+        let values: [Value; N] = values
+            .try_into()
+            .map_err(|_| internal_error("loop state lost", span))?;
+        let values = body(self, builder, index, value, values)?;
+        let index = builder.ins().iadd_imm_s(index, 1);
+        let args: Vec<_> = std::iter::once(index)
+            .chain(values)
+            .map(Into::into)
+            .collect();
+        builder.ins().jump(header, &args);
+        builder.seal_block(header);
+
+        builder.switch_to_block(exit);
+        builder.seal_block(exit);
+        let params = builder.block_params(exit);
+        params
+            .try_into()
+            .map_err(|_| internal_error("loop state lost", span))
+    }
+}
+
+/// The address of element `index` of `elements`, each `stride` bytes.
+fn element_address(
+    elements: Value,
+    index: Value,
+    stride: u32,
+    builder: &mut FunctionBuilder,
+) -> Value {
+    let stride = builder.ins().iconst(types::I64, i64::from(stride));
+    let offset = builder.ins().imul(index, stride);
+    builder.ins().iadd(elements, offset)
+}
+
+/// The type of the elements of lists of type `ty`.
+pub fn element_type(ty: &Type, span: Span) -> Result<&Type, Diagnostic> {
+    match ty {
+        Type::Compound(CompoundType::List(element)) => Ok(element),
+        _ => Err(internal_error(format!("`{ty}` is not a list"), span)),
+    }
+}
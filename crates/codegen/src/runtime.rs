@@ -0,0 +1,206 @@
+//! The runtime compiled into every program.
+//!
+//! # Memory
+//! Records, enums, strings and lists are stored behind a pointer. Those that
+//! don't outlive the function building them live in its stack frame; the
+//! others, see [`crate::escape`], are allocated with [`ALLOC`] from an arena:
+//! a bump allocator over chunks of memory from `malloc`. Nothing allocated is
+//! ever freed, the whole arena is reclaimed when the program exits.
+
+use ast::{diagnostics::Diagnostic, span::Span};
+use cranelift::{
+    codegen::{
+        Context,
+        ir::{Function, MemFlagsData, TrapCode, UserFuncName},
+    },
+    prelude::{
+        AbiParam, FunctionBuilder, FunctionBuilderContext, InstBuilder, IntCC, Signature, Type,
+        Value, isa::CallConv,
+    },
+};
+use cranelift_module::{DataDescription, FuncId, FuncOrDataId, Linkage, Module};
+
+use crate::{Codegen, internal_error, layout::Layout};
+
+/// `elk_alloc(size, align) -> pointer`: memory for a value living until the
+/// program exits.
+pub const ALLOC: &str = "elk_alloc";
+
+/// The arena [`ALLOC`] allocates from: the next free address, and the end
+/// of the current chunk.
+const ARENA: &str = "elk_arena";
+const ARENA_NEXT: i32 = 0;
+const ARENA_END: i32 = 8;
+
+/// The size of the chunks the arena gets from `malloc`, unless a value
+/// needs more.
+const CHUNK_SIZE: i64 = 64 * 1024;
+
+/// The trap code of an allocation `malloc` couldn't satisfy.
+const OUT_OF_MEMORY: u8 = 2;
+
+impl Codegen {
+    /// Defines the functions and data of the runtime in the module.
+    pub fn define_runtime(&mut self) -> Result<(), Diagnostic> {
+        self.define_alloc()?;
+        self.define_abort()?;
+        self.define_string_runtime()
+    }
+
+    fn define_alloc(&mut self) -> Result<(), Diagnostic> {
+        let ptr = self.module.target_config().pointer_type();
+        let arena = self
+            .module
+            .declare_data(ARENA, Linkage::Local, true, false)
+            .map_err(runtime_error)?;
+        let mut description = DataDescription::new();
+        description.define_zeroinit(16);
+        description.set_align(8);
+        self.module
+            .define_data(arena, &description)
+            .map_err(runtime_error)?;
+        let malloc = self.import_function("malloc", &[ptr], ptr)?;
+
+        self.define_runtime_function(ALLOC, &[ptr, ptr], ptr, |codegen, builder, params| {
+            let flags = MemFlagsData::trusted();
+            let [size, align] = *params else {
+                unreachable!("`{ALLOC}` takes a size and an alignment")
+            };
+            let fits = builder.create_block();
+            let refill = builder.create_block();
+            // Every value gets its own address, even an empty one.
+            let one = builder.ins().iconst(ptr, 1);
+            let size = builder.ins().umax(size, one);
+            let arena = codegen.module.declare_data_in_func(arena, builder.func);
+            let arena = builder.ins().symbol_value(ptr, arena);
+            let next = builder.ins().load(ptr, flags, arena, ARENA_NEXT);
+            let end = builder.ins().load(ptr, flags, arena, ARENA_END);
+            let start = align_up(next, align, builder);
+            let new_next = builder.ins().iadd(start, size);
+            let in_chunk = builder
+                .ins()
+                .icmp(IntCC::UnsignedLessThanOrEqual, new_next, end);
+            builder.ins().brif(in_chunk, fits, &[], refill, &[]);
+
+            builder.switch_to_block(fits);
+            builder.ins().store(flags, new_next, arena, ARENA_NEXT);
+            builder.ins().return_(&[start]);
+
+            // A fresh chunk, large enough for the value at any alignment. The
+            // rest of the current one is lost.
+            builder.switch_to_block(refill);
+            let needed = builder.ins().iadd(size, align);
+            let chunk_size = builder.ins().iconst(ptr, CHUNK_SIZE);
+            let chunk_size = builder.ins().umax(needed, chunk_size);
+            let malloc = codegen.module.declare_func_in_func(malloc, builder.func);
+            let call = builder.ins().call(malloc, &[chunk_size]);
+            let chunk = builder.inst_results(call)[0];
+            builder
+                .ins()
+                .trapz(chunk, TrapCode::unwrap_user(OUT_OF_MEMORY));
+            let chunk_end = builder.ins().iadd(chunk, chunk_size);
+            builder.ins().store(flags, chunk_end, arena, ARENA_END);
+            let start = align_up(chunk, align, builder);
+            let new_next = builder.ins().iadd(start, size);
+            builder.ins().store(flags, new_next, arena, ARENA_NEXT);
+            builder.ins().return_(&[start]);
+            Ok(())
+        })
+    }
+
+    /// Declares `name`, a function of the C library.
+    pub fn import_function(
+        &mut self,
+        name: &str,
+        params: &[Type],
+        returns: Type,
+    ) -> Result<FuncId, Diagnostic> {
+        self.module
+            .declare_function(name, Linkage::Import, &signature(params, returns))
+            .map_err(runtime_error)
+    }
+
+    /// Defines `name`, a function of the runtime, whose body `build` emits
+    /// given its parameters, starting in the entry block.
+    pub fn define_runtime_function(
+        &mut self,
+        name: &str,
+        params: &[Type],
+        returns: Type,
+        build: impl FnOnce(&mut Self, &mut FunctionBuilder, &[Value]) -> Result<(), Diagnostic>,
+    ) -> Result<(), Diagnostic> {
+        let sig = signature(params, returns);
+        let id = self
+            .module
+            .declare_function(name, Linkage::Local, &sig)
+            .map_err(runtime_error)?;
+
+        let mut func = Function::with_name_signature(UserFuncName::user(0, 0), sig);
+        let mut func_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut func_ctx);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let params = builder.block_params(entry).to_vec();
+        build(self, &mut builder, &params)?;
+        builder.seal_all_blocks();
+        builder.finalize(self.module.isa().frontend_config());
+
+        let mut ctx = Context::for_function(func);
+        self.module
+            .define_function(id, &mut ctx)
+            .map_err(runtime_error)
+    }
+
+    /// Calls `name`, a function of the runtime or the C library.
+    pub fn gen_runtime_call(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let Some(FuncOrDataId::Func(id)) = self.module.get_name(name) else {
+            return Err(internal_error(
+                format!("`{name}` isn't in the runtime"),
+                span,
+            ));
+        };
+        let func = self.module.declare_func_in_func(id, builder.func);
+        let call = builder.ins().call(func, args);
+        Ok(builder.inst_results(call)[0])
+    }
+
+    /// Memory for a value of `layout` living until the program exits.
+    pub fn gen_heap_alloc(
+        &mut self,
+        layout: Layout,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let ptr = self.module.target_config().pointer_type();
+        let size = builder.ins().iconst(ptr, i64::from(layout.size));
+        let align = builder.ins().iconst(ptr, i64::from(layout.align));
+        self.gen_runtime_call(ALLOC, &[size, align], span, builder)
+    }
+}
+
+fn signature(params: &[Type], returns: Type) -> Signature {
+    let mut sig = Signature::new(CallConv::SystemV);
+    sig.params
+        .extend(params.iter().map(|param| AbiParam::new(*param)));
+    sig.returns.push(AbiParam::new(returns));
+    sig
+}
+
+fn runtime_error(e: impl std::fmt::Display) -> Diagnostic {
+    internal_error(format!("cannot define the runtime: {e}"), Span::DUMMY)
+}
+
+/// `address` rounded up to a multiple of `align`, a power of two.
+fn align_up(address: Value, align: Value, builder: &mut FunctionBuilder) -> Value {
+    let mask = builder.ins().iadd_imm_s(align, -1);
+    let address = builder.ins().iadd(address, mask);
+    let mask = builder.ins().bnot(mask);
+    builder.ins().band(address, mask)
+}
@@ -11,28 +11,19 @@ pub struct Scope {
     variables: BTreeMap<String, Var>,
     functions: BTreeMap<String, (FuncId, FunctionSignature)>,
     types: Vec<CustomType>,
-    var_counter: u32,
 }
 
 impl Scope {
-    pub fn new(counter_start: u32) -> Self {
+    pub fn new() -> Self {
         Self {
             variables: BTreeMap::new(),
             functions: BTreeMap::new(),
             types: Vec::new(),
-            var_counter: counter_start,
         }
     }
 
-    pub fn counter(&self) -> u32 {
-        self.var_counter
-    }
-
-    pub fn declare_variable(&mut self, var_name: &str, ty: Type) -> Variable {
-        let var = Variable::from_u32(self.var_counter);
+    pub fn declare_variable(&mut self, var_name: &str, var: Variable, ty: Type) {
         self.variables.insert(var_name.to_owned(), (var, ty));
-        self.var_counter += 1;
-        var
     }
 
     pub fn declare_function(
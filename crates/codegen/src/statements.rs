@@ -1,24 +1,37 @@
-use ast::statements::{Block, Statement};
-use cranelift::prelude::{FunctionBuilder, Value};
+use ast::{
+    diagnostics::Diagnostic,
+    statements::{Block, StatementKind},
+};
+use cranelift::prelude::{FunctionBuilder, InstBuilder, Value};
 
-use super::{Codegen, Generable};
+use super::{Codegen, internal_error, untyped};
 
 impl Codegen {
-    pub fn gen_block(&mut self, block: &Block, builder: &mut FunctionBuilder) -> Value {
-        let blk = builder.create_block();
-        builder.switch_to_block(blk);
-        builder.seal_block(blk);
+    pub fn gen_block(
+        &mut self,
+        block: &Block,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
         for stmt in block.statements() {
-            match stmt {
-                Statement::Assignment(var_name, expression) => {
-                    let ty = expression.get_type().unwrap();
-                    let val = self.gen_expression(expression, builder);
-                    let var = self.declare_variable(var_name, ty.to_owned());
-                    builder.declare_var(ty.to_cranelift());
+            match &stmt.kind {
+                StatementKind::Assignment(var_name, _, expression) => {
+                    let ty = expression
+                        .get_type()
+                        .ok_or_else(|| untyped(expression.span()))?;
+                    let val = self.gen_expression(expression, builder)?;
+                    let var = self.declare_variable(var_name, ty.to_owned(), builder);
                     builder.def_var(var, val);
                 }
-                Statement::Return(expr) => {
-                    return self.gen_expression(expr, builder);
+                StatementKind::Return(expr) => {
+                    let value = self.gen_expression(expr, builder)?;
+                    let exit = self.exit.ok_or_else(|| {
+                        internal_error("`return` outside of a function", expr.span())
+                    })?;
+                    builder.ins().jump(exit, &[value.into()]);
+                    // What follows the `return` is never run.
+                    let unreachable = builder.create_block();
+                    builder.switch_to_block(unreachable);
+                    builder.seal_block(unreachable);
                 }
             }
         }
@@ -0,0 +1,375 @@
+use ast::{diagnostics::Diagnostic, expressions::StringPart, span::Span, types::Type};
+use cranelift::{
+    codegen::ir::MemFlagsData,
+    prelude::{FunctionBuilder, InstBuilder, IntCC, Value, types},
+};
+use cranelift_module::{DataDescription, DataId, Module};
+
+use crate::{Codegen, internal_error, layout::Layout, runtime::ALLOC, unsupported, untyped};
+
+// Strings are a pointer to their bytes and length: UTF-8, not terminated.
+// Literals are read-only data of the object, other strings are allocated by
+// the runtime.
+const STRING_PTR: i32 = 0;
+const STRING_LEN: i32 = 8;
+const STRING: Layout = Layout { size: 16, align: 8 };
+
+/// `elk_string_eq(a, b) -> Bool`
+const STRING_EQ: &str = "elk_string_eq";
+/// `elk_string_concat(a, b) -> String`
+const STRING_CONCAT: &str = "elk_string_concat";
+/// `elk_int_to_string(magnitude, negative) -> String`: the decimal digits of
+/// `magnitude`, a `U64`, after a `-` if `negative`.
+const INT_TO_STRING: &str = "elk_int_to_string";
+/// `elk_float_to_string(F64) -> String`
+const FLOAT_TO_STRING: &str = "elk_float_to_string";
+
+/// The most characters an integer takes: 20 digits, and a sign.
+const MAX_INT_LEN: u32 = 21;
+/// Room for any float `%g` renders.
+const MAX_FLOAT_LEN: u32 = 32;
+
+impl Codegen {
+    /// Defines the string functions of the runtime.
+    pub fn define_string_runtime(&mut self) -> Result<(), Diagnostic> {
+        let ptr = self.module.target_config().pointer_type();
+        let memcmp = self.import_function("memcmp", &[ptr, ptr, ptr], types::I32)?;
+        let memcpy = self.import_function("memcpy", &[ptr, ptr, ptr], ptr)?;
+        let flags = MemFlagsData::trusted();
+
+        self.define_runtime_function(
+            STRING_EQ,
+            &[ptr, ptr],
+            types::I8,
+            |codegen, builder, params| {
+                let [a, b] = *params else {
+                    unreachable!("`{STRING_EQ}` takes two strings")
+                };
+                let same_length = builder.create_block();
+                let different = builder.create_block();
+                let a_len = builder.ins().load(ptr, flags, a, STRING_LEN);
+                let b_len = builder.ins().load(ptr, flags, b, STRING_LEN);
+                let equal = builder.ins().icmp(IntCC::Equal, a_len, b_len);
+                builder.ins().brif(equal, same_length, &[], different, &[]);
+
+                builder.switch_to_block(same_length);
+                let a_ptr = builder.ins().load(ptr, flags, a, STRING_PTR);
+                let b_ptr = builder.ins().load(ptr, flags, b, STRING_PTR);
+                let memcmp = codegen.module.declare_func_in_func(memcmp, builder.func);
+                let call = builder.ins().call(memcmp, &[a_ptr, b_ptr, a_len]);
+                let order = builder.inst_results(call)[0];
+                let equal = builder.ins().icmp_imm_s(IntCC::Equal, order, 0);
+                builder.ins().return_(&[equal]);
+
+                builder.switch_to_block(different);
+                let false_ = builder.ins().iconst(types::I8, 0);
+                builder.ins().return_(&[false_]);
+                Ok(())
+            },
+        )?;
+
+        self.define_runtime_function(
+            STRING_CONCAT,
+            &[ptr, ptr],
+            ptr,
+            |codegen, builder, params| {
+                let [a, b] = *params else {
+                    unreachable!("`{STRING_CONCAT}` takes two strings")
+                };
+                let a_len = builder.ins().load(ptr, flags, a, STRING_LEN);
+                let b_len = builder.ins().load(ptr, flags, b, STRING_LEN);
+                let len = builder.ins().iadd(a_len, b_len);
+                // The bytes follow the string.
+                let size = builder.ins().iadd_imm_s(len, i64::from(STRING.size));
+                let align = builder.ins().iconst(ptr, i64::from(STRING.align));
+                let string =
+                    codegen.gen_runtime_call(ALLOC, &[size, align], Span::DUMMY, builder)?;
+                let bytes = builder.ins().iadd_imm_s(string, i64::from(STRING.size));
+                builder.ins().store(flags, bytes, string, STRING_PTR);
+                builder.ins().store(flags, len, string, STRING_LEN);
+
+                let memcpy = codegen.module.declare_func_in_func(memcpy, builder.func);
+                let a_ptr = builder.ins().load(ptr, flags, a, STRING_PTR);
+                builder.ins().call(memcpy, &[bytes, a_ptr, a_len]);
+                let b_bytes = builder.ins().iadd(bytes, a_len);
+                let b_ptr = builder.ins().load(ptr, flags, b, STRING_PTR);
+                builder.ins().call(memcpy, &[b_bytes, b_ptr, b_len]);
+                builder.ins().return_(&[string]);
+                Ok(())
+            },
+        )?;
+        self.define_int_to_string()?;
+        self.define_float_to_string()
+    }
+
+    fn define_int_to_string(&mut self) -> Result<(), Diagnostic> {
+        let ptr = self.module.target_config().pointer_type();
+        let flags = MemFlagsData::trusted();
+        self.define_runtime_function(
+            INT_TO_STRING,
+            &[types::I64, types::I8],
+            ptr,
+            |codegen, builder, params| {
+                let [magnitude, negative] = *params else {
+                    unreachable!("`{INT_TO_STRING}` takes a magnitude and a sign")
+                };
+                let digit = builder.create_block();
+                let sign = builder.create_block();
+                let minus = builder.create_block();
+                let done = builder.create_block();
+                builder.append_block_param(digit, types::I64);
+                builder.append_block_param(digit, ptr);
+                builder.append_block_param(sign, ptr);
+                builder.append_block_param(done, ptr);
+
+                let size = builder
+                    .ins()
+                    .iconst(ptr, i64::from(STRING.size + MAX_INT_LEN));
+                let align = builder.ins().iconst(ptr, i64::from(STRING.align));
+                let string =
+                    codegen.gen_runtime_call(ALLOC, &[size, align], Span::DUMMY, builder)?;
+                let end = builder.ins().iadd(string, size);
+                builder.ins().jump(digit, &[magnitude.into(), end.into()]);
+
+                // Digits are written from the last one.
+                builder.switch_to_block(digit);
+                let [n, pos] = *builder.block_params(digit) else {
+                    unreachable!()
+                };
+                let pos = builder.ins().iadd_imm_s(pos, -1);
+                let ten = builder.ins().iconst(types::I64, 10);
+                let d = builder.ins().urem(n, ten);
+                let c = builder.ins().iadd_imm_u(d, i64::from(b'0'));
+                let c = builder.ins().ireduce(types::I8, c);
+                builder.ins().store(flags, c, pos, 0);
+                let n = builder.ins().udiv(n, ten);
+                builder
+                    .ins()
+                    .brif(n, digit, &[n.into(), pos.into()], sign, &[pos.into()]);
+
+                builder.switch_to_block(sign);
+                let pos = builder.block_params(sign)[0];
+                builder
+                    .ins()
+                    .brif(negative, minus, &[], done, &[pos.into()]);
+
+                builder.switch_to_block(minus);
+                let pos = builder.ins().iadd_imm_s(pos, -1);
+                let c = builder.ins().iconst(types::I8, i64::from(b'-'));
+                builder.ins().store(flags, c, pos, 0);
+                builder.ins().jump(done, &[pos.into()]);
+
+                builder.switch_to_block(done);
+                let start = builder.block_params(done)[0];
+                let len = builder.ins().isub(end, start);
+                builder.ins().store(flags, start, string, STRING_PTR);
+                builder.ins().store(flags, len, string, STRING_LEN);
+                builder.ins().return_(&[string]);
+                Ok(())
+            },
+        )
+    }
+
+    fn define_float_to_string(&mut self) -> Result<(), Diagnostic> {
+        let ptr = self.module.target_config().pointer_type();
+        let flags = MemFlagsData::trusted();
+        // `strfromd` formats like `printf`, but isn't variadic.
+        let strfromd =
+            self.import_function("strfromd", &[ptr, ptr, ptr, types::F64], types::I32)?;
+        let format = self.gen_c_string(b"%g\0")?;
+
+        self.define_runtime_function(
+            FLOAT_TO_STRING,
+            &[types::F64],
+            ptr,
+            |codegen, builder, params| {
+                let [x] = *params else {
+                    unreachable!("`{FLOAT_TO_STRING}` takes a float")
+                };
+                let size = builder
+                    .ins()
+                    .iconst(ptr, i64::from(STRING.size + MAX_FLOAT_LEN));
+                let align = builder.ins().iconst(ptr, i64::from(STRING.align));
+                let string =
+                    codegen.gen_runtime_call(ALLOC, &[size, align], Span::DUMMY, builder)?;
+                let bytes = builder.ins().iadd_imm_s(string, i64::from(STRING.size));
+                let capacity = builder.ins().iconst(ptr, i64::from(MAX_FLOAT_LEN));
+                let format = codegen.module.declare_data_in_func(format, builder.func);
+                let format = builder.ins().symbol_value(ptr, format);
+                let strfromd = codegen.module.declare_func_in_func(strfromd, builder.func);
+                let call = builder.ins().call(strfromd, &[bytes, capacity, format, x]);
+                let len = builder.inst_results(call)[0];
+                let len = builder.ins().uextend(ptr, len);
+                // Without its terminating nul.
+                let max = builder.ins().iconst(ptr, i64::from(MAX_FLOAT_LEN - 1));
+                let len = builder.ins().umin(len, max);
+                builder.ins().store(flags, bytes, string, STRING_PTR);
+                builder.ins().store(flags, len, string, STRING_LEN);
+                builder.ins().return_(&[string]);
+                Ok(())
+            },
+        )
+    }
+
+    /// Read-only `bytes`, for the C library.
+    pub fn gen_c_string(&mut self, bytes: &[u8]) -> Result<DataId, Diagnostic> {
+        let error = |e| internal_error(format!("cannot define the runtime: {e}"), Span::DUMMY);
+        let id = self
+            .module
+            .declare_anonymous_data(false, false)
+            .map_err(error)?;
+        let mut description = DataDescription::new();
+        description.define(bytes.into());
+        self.module.define_data(id, &description).map_err(error)?;
+        Ok(id)
+    }
+
+    /// An interpolated string: its parts rendered, one after the other.
+    pub fn gen_interpolation(
+        &mut self,
+        parts: &[StringPart],
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let mut string = None;
+        for part in parts {
+            let rendered = match part {
+                StringPart::Text(text) => self.gen_string_literal(text, span, builder)?,
+                StringPart::Hole(hole) => {
+                    let ty = hole.get_type().ok_or_else(|| untyped(hole.span()))?;
+                    let value = self.gen_expression(hole, builder)?;
+                    self.gen_to_string(value, ty, hole.span(), builder)?
+                }
+            };
+            string = Some(match string {
+                None => rendered,
+                Some(string) => {
+                    self.gen_runtime_call(STRING_CONCAT, &[string, rendered], span, builder)?
+                }
+            });
+        }
+        match string {
+            Some(string) => Ok(string),
+            None => self.gen_string_literal("", span, builder),
+        }
+    }
+
+    /// `value`, of type `ty`, as a string.
+    fn gen_to_string(
+        &mut self,
+        value: Value,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        match ty {
+            Type::String => Ok(value),
+            Type::Bool => {
+                let true_ = self.gen_string_literal("True", span, builder)?;
+                let false_ = self.gen_string_literal("False", span, builder)?;
+                Ok(builder.ins().select(value, true_, false_))
+            }
+            Type::F32 => {
+                let value = builder.ins().fpromote(types::F64, value);
+                self.gen_runtime_call(FLOAT_TO_STRING, &[value], span, builder)
+            }
+            Type::F64 => self.gen_runtime_call(FLOAT_TO_STRING, &[value], span, builder),
+            ty if ty.is_signed() => {
+                let value = if *ty == Type::I64 {
+                    value
+                } else {
+                    builder.ins().sextend(types::I64, value)
+                };
+                let negative = builder.ins().icmp_imm_s(IntCC::SignedLessThan, value, 0);
+                // `-I64::MIN` wraps to itself, which is its magnitude as a `U64`.
+                let negated = builder.ins().ineg(value);
+                let magnitude = builder.ins().select(negative, negated, value);
+                self.gen_runtime_call(INT_TO_STRING, &[magnitude, negative], span, builder)
+            }
+            ty if ty.is_integer() => {
+                let value = if *ty == Type::U64 {
+                    value
+                } else {
+                    builder.ins().uextend(types::I64, value)
+                };
+                let negative = builder.ins().iconst(types::I8, 0);
+                self.gen_runtime_call(INT_TO_STRING, &[value, negative], span, builder)
+            }
+            _ => Err(internal_error(format!("cannot render `{ty}`"), span)),
+        }
+    }
+
+    /// A string literal, stored in the read-only data of the object.
+    pub fn gen_string_literal(
+        &mut self,
+        s: &str,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let error = |e| internal_error(format!("cannot store string literal: {e}"), span);
+        let bytes = self
+            .module
+            .declare_anonymous_data(false, false)
+            .map_err(error)?;
+        let mut description = DataDescription::new();
+        description.define(s.as_bytes().into());
+        self.module
+            .define_data(bytes, &description)
+            .map_err(error)?;
+
+        let string = self
+            .module
+            .declare_anonymous_data(false, false)
+            .map_err(error)?;
+        let mut contents = vec![0; STRING.size as usize];
+        let len = s.len() as u64;
+        let len = match self.module.isa().endianness() {
+            cranelift::codegen::ir::Endianness::Little => len.to_le_bytes(),
+            cranelift::codegen::ir::Endianness::Big => len.to_be_bytes(),
+        };
+        contents[STRING_LEN as usize..].copy_from_slice(&len);
+        let mut description = DataDescription::new();
+        description.define(contents.into());
+        description.set_align(u64::from(STRING.align));
+        let bytes = self.module.declare_data_in_data(bytes, &mut description);
+        description.write_data_addr(STRING_PTR as u32, bytes, 0);
+        self.module
+            .define_data(string, &description)
+            .map_err(error)?;
+
+        let string = self.module.declare_data_in_func(string, builder.func);
+        let ptr = self.module.target_config().pointer_type();
+        Ok(builder.ins().symbol_value(ptr, string))
+    }
+
+    /// Whether strings `a` and `b` have the same bytes.
+    pub fn gen_string_eq(
+        &mut self,
+        a: Value,
+        b: Value,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        self.gen_runtime_call(STRING_EQ, &[a, b], span, builder)
+    }
+
+    /// A call to `String::name`, a built-in function.
+    pub fn gen_string_builtin(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        match (name, args) {
+            ("concat", [a, b]) => self.gen_runtime_call(STRING_CONCAT, &[*a, *b], span, builder),
+            ("length", [s]) => {
+                let ptr = self.module.target_config().pointer_type();
+                Ok(builder
+                    .ins()
+                    .load(ptr, MemFlagsData::trusted(), *s, STRING_LEN))
+            }
+            _ => Err(unsupported(&format!("`String::{name}`"), span)),
+        }
+    }
+}
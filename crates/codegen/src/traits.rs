@@ -0,0 +1,306 @@
+//! Calls to trait methods, and to functions with bounded type parameters.
+//!
+//! A function bounded by traits, like `describe<A: Show>`, takes a
+//! dictionary for each bound after its arguments: the address of the
+//! closures of the methods implementing the trait for the type its caller
+//! picked, in the order the trait declares them. Dictionaries are built once,
+//! in the data of the program. A method called on a type the function knows
+//! calls the implementation directly.
+
+use std::collections::BTreeMap;
+
+use ast::{
+    diagnostics::Diagnostic,
+    expressions::Expression,
+    span::Span,
+    traits::{Bound, SELF, TraitDeclaration, instance_method},
+    types::{CompoundType, FunctionSignature, Type},
+};
+use cranelift::{
+    codegen::ir::MemFlagsData,
+    prelude::{AbiParam, FunctionBuilder, InstBuilder, Signature, Value},
+};
+use cranelift_module::{DataDescription, DataId, FuncOrDataId, Linkage, Module};
+
+use crate::{Codegen, Generable, internal_error, symbol, unsupported, untyped};
+
+/// The size of an entry of a dictionary: the address of a closure.
+const ENTRY: u32 = 8;
+
+/// How a method is called.
+pub enum Method {
+    /// Its implementation for a known type: the function of that name.
+    Direct(String),
+    /// The closure at `index` in `dictionary`, of type `signature`.
+    Dispatched {
+        dictionary: Value,
+        index: usize,
+        signature: FunctionSignature,
+    },
+}
+
+impl Codegen {
+    pub fn get_trait(&self, name: &str) -> Option<&TraitDeclaration> {
+        self.traits.iter().find(|td| td.name() == name)
+    }
+
+    /// The signature of the function `name`: that of its arguments, and a
+    /// dictionary for each of its bounds.
+    pub fn function_signature(&self, name: &str, signature: &FunctionSignature) -> Signature {
+        let mut sig = signature.to_cranelift();
+        let ptr = self.module.target_config().pointer_type();
+        let bounds = self.bounds.get(name).map_or(0, Vec::len);
+        sig.params
+            .extend(std::iter::repeat_n(AbiParam::new(ptr), bounds));
+        sig
+    }
+
+    /// How to call `method` of `td` on `arguments`, to give a `ty`.
+    pub fn resolve_method(
+        &self,
+        td: &TraitDeclaration,
+        method: &str,
+        arguments: &[Expression],
+        ty: Option<&Type>,
+        span: Span,
+    ) -> Result<Method, Diagnostic> {
+        let (index, declaration) = td.method(method).ok_or_else(|| {
+            internal_error(
+                format!("`{method}` is not a method of `{}`", td.name()),
+                span,
+            )
+        })?;
+        let params = [SELF.to_owned()];
+        let bindings = bind_arguments(declaration.signature(), &params, arguments, ty)?;
+        let self_type = bindings
+            .get(SELF)
+            .ok_or_else(|| internal_error(format!("`{SELF}` of `{method}` is unknown"), span))?;
+        let Some(dictionary) = self.get_dictionary(td.name(), self_type) else {
+            let name = instance_method(td.name(), &type_name(self_type), method).qualified();
+            // Inference checked the type implements the trait, so this is a
+            // type parameter whose dictionary isn't at hand: in a lambda.
+            if self.get_function(&name).is_none() {
+                return Err(unsupported(
+                    "calling a trait method on a type parameter in a lambda",
+                    span,
+                ));
+            }
+            return Ok(Method::Direct(name));
+        };
+        if !declaration.bounds().is_empty() {
+            return Err(unsupported("calling a method with bounds of its own", span));
+        }
+        let signature = declaration.signature();
+        let signature = FunctionSignature::new(
+            signature
+                .arguments()
+                .iter()
+                .map(|arg| arg.replace(SELF, self_type))
+                .collect(),
+            signature.return_type().replace(SELF, self_type),
+        );
+        Ok(Method::Dispatched {
+            dictionary,
+            index,
+            signature,
+        })
+    }
+
+    /// Calls the closure at `index` in `dictionary` with `args`.
+    pub fn gen_dispatched_call(
+        &mut self,
+        dictionary: Value,
+        index: usize,
+        signature: &FunctionSignature,
+        args: &[Value],
+        builder: &mut FunctionBuilder,
+    ) -> Value {
+        let ptr = self.module.target_config().pointer_type();
+        let offset = (index as u32 * ENTRY) as i32;
+        let closure = builder
+            .ins()
+            .load(ptr, MemFlagsData::trusted(), dictionary, offset);
+        self.gen_closure_call(closure, signature, args, builder)
+    }
+
+    /// The dictionaries `function`, of type `signature`, takes for its
+    /// bounds, when called with `arguments` to give a `ty`.
+    pub fn gen_dictionaries(
+        &mut self,
+        function: &str,
+        signature: &FunctionSignature,
+        arguments: &[Expression],
+        ty: Option<&Type>,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Vec<Value>, Diagnostic> {
+        let Some(bounds) = self.bounds.get(function).cloned() else {
+            return Ok(vec![]);
+        };
+        let params: Vec<String> = bounds.iter().map(|bound| bound.param.clone()).collect();
+        let bindings = bind_arguments(signature, &params, arguments, ty)?;
+        bounds
+            .iter()
+            .map(|bound| {
+                let ty = bindings.get(&bound.param).ok_or_else(|| {
+                    internal_error(format!("type parameter `{}` is unknown", bound.param), span)
+                })?;
+                self.gen_dictionary(&bound.trait_name, ty, span, builder)
+            })
+            .collect()
+    }
+
+    /// The dictionary of `trait_name` for `ty`.
+    fn gen_dictionary(
+        &mut self,
+        trait_name: &str,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        if let Some(dictionary) = self.get_dictionary(trait_name, ty) {
+            return Ok(dictionary);
+        }
+        let type_name = type_name(ty);
+        let data_name = symbol(&format!("{trait_name}<{type_name}>$dictionary"));
+        let data = match self.module.get_name(&data_name) {
+            Some(FuncOrDataId::Data(data)) => data,
+            _ => self.define_dictionary(trait_name, &type_name, &data_name, span)?,
+        };
+        let data = self.module.declare_data_in_func(data, builder.func);
+        let ptr = self.module.target_config().pointer_type();
+        Ok(builder.ins().symbol_value(ptr, data))
+    }
+
+    /// The dictionary the function being compiled was given for `ty`, one
+    /// of its type parameters.
+    fn get_dictionary(&self, trait_name: &str, ty: &Type) -> Option<Value> {
+        let Type::Custom(param, generics) = ty else {
+            return None;
+        };
+        self.dictionaries
+            .iter()
+            .find(|(bound, _)| {
+                generics.is_empty() && bound.param == *param && bound.trait_name == trait_name
+            })
+            .map(|(_, dictionary)| *dictionary)
+    }
+
+    fn define_dictionary(
+        &mut self,
+        trait_name: &str,
+        type_name: &str,
+        data_name: &str,
+        span: Span,
+    ) -> Result<DataId, Diagnostic> {
+        let td = self
+            .get_trait(trait_name)
+            .ok_or_else(|| internal_error(format!("no trait `{trait_name}`"), span))?
+            .clone();
+        let closures = td
+            .methods()
+            .iter()
+            .map(|method| {
+                let function = instance_method(trait_name, type_name, method.name()).qualified();
+                self.function_closure(&function, span)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let error = |e| internal_error(format!("cannot define `{data_name}`: {e}"), span);
+        let data = self
+            .module
+            .declare_data(data_name, Linkage::Local, false, false)
+            .map_err(error)?;
+        let mut description = DataDescription::new();
+        description.define(vec![0; (closures.len() as u32 * ENTRY) as usize].into());
+        description.set_align(u64::from(ENTRY));
+        for (i, closure) in closures.into_iter().enumerate() {
+            let closure = self.module.declare_data_in_data(closure, &mut description);
+            description.write_data_addr(i as u32 * ENTRY, closure, 0);
+        }
+        self.module.define_data(data, &description).map_err(error)?;
+        Ok(data)
+    }
+
+    /// Records the bounds of the function `name`, which take dictionaries.
+    pub fn declare_bounds(&mut self, name: &str, bounds: &[Bound]) {
+        if !bounds.is_empty() {
+            self.bounds.insert(name.to_owned(), bounds.to_vec());
+        }
+    }
+}
+
+/// What the type parameters `params` of `signature` stand for, in a call
+/// with `arguments` giving a `ty`.
+fn bind_arguments(
+    signature: &FunctionSignature,
+    params: &[String],
+    arguments: &[Expression],
+    ty: Option<&Type>,
+) -> Result<BTreeMap<String, Type>, Diagnostic> {
+    let mut bindings = BTreeMap::new();
+    for (declared, argument) in signature.arguments().iter().zip(arguments) {
+        let actual = argument
+            .get_type()
+            .ok_or_else(|| untyped(argument.span()))?;
+        bind(declared, actual, params, &mut bindings);
+    }
+    if let Some(ty) = ty {
+        bind(signature.return_type(), ty, params, &mut bindings);
+    }
+    Ok(bindings)
+}
+
+/// Binds the type parameters `params` in `declared` to the matching parts
+/// of `actual`.
+fn bind(declared: &Type, actual: &Type, params: &[String], bindings: &mut BTreeMap<String, Type>) {
+    match (declared, actual) {
+        (Type::Custom(name, generics), _) if generics.is_empty() && params.contains(name) => {
+            bindings
+                .entry(name.clone())
+                .or_insert_with(|| actual.clone());
+        }
+        (Type::Custom(_, declared), Type::Custom(_, actual)) => {
+            for (declared, actual) in declared.iter().zip(actual) {
+                if params.contains(declared) {
+                    let actual = actual
+                        .parse()
+                        .unwrap_or_else(|_| Type::Custom(actual.clone(), vec![]));
+                    bindings.entry(declared.clone()).or_insert(actual);
+                }
+            }
+        }
+        (Type::Function(declared), Type::Function(actual)) => {
+            for (declared, actual) in declared.arguments().iter().zip(actual.arguments()) {
+                bind(declared, actual, params, bindings);
+            }
+            bind(
+                declared.return_type(),
+                actual.return_type(),
+                params,
+                bindings,
+            );
+        }
+        (
+            Type::Compound(CompoundType::List(declared)),
+            Type::Compound(CompoundType::List(actual)),
+        ) => bind(declared, actual, params, bindings),
+        (
+            Type::Compound(CompoundType::Tuple(declared)),
+            Type::Compound(CompoundType::Tuple(actual)),
+        ) => {
+            for (declared, actual) in declared.iter().zip(actual) {
+                bind(declared, actual, params, bindings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The name implementations for `ty` are known by: `U8`, `Option`.
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Custom(name, _) => name.clone(),
+        _ => ty.to_string(),
+    }
+}
@@ -0,0 +1,145 @@
+use ast::{
+    diagnostics::Diagnostic,
+    expressions::Expression,
+    span::Span,
+    types::{CompoundType, Type},
+};
+use cranelift::{
+    codegen::ir::MemFlagsData,
+    prelude::{AbiParam, FunctionBuilder, InstBuilder, Value},
+};
+
+use crate::{Codegen, Generable, internal_error, layout::Layout};
+
+// Tuples are a pointer to their elements, laid out like the fields of a
+// record. Functions return small tuples in registers instead, one per
+// element, so a pair built to be returned needs no memory of its own.
+const MAX_REGISTER_ELEMENTS: usize = 2;
+
+impl Codegen {
+    /// A tuple literal of type `ty`.
+    pub fn gen_tuple(
+        &mut self,
+        elements: &[Expression],
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let values = elements
+            .iter()
+            .map(|element| self.gen_expression(element, builder))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.gen_tuple_of(&values, ty, span, builder)
+    }
+
+    /// A tuple of type `ty` built at `span`, holding `values`.
+    pub fn gen_tuple_of(
+        &mut self,
+        values: &[Value],
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let (layout, _) = tuple_layout(tuple_elements(ty, span)?);
+        let tuple = self.gen_alloc(layout, span, builder)?;
+        self.gen_store_elements(tuple, values, ty, span, builder)
+    }
+
+    /// Stores `values` as the elements of `tuple`, a tuple of type `ty`.
+    pub fn gen_store_elements(
+        &self,
+        tuple: Value,
+        values: &[Value],
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let (_, offsets) = tuple_layout(tuple_elements(ty, span)?);
+        for (value, offset) in values.iter().zip(offsets) {
+            builder
+                .ins()
+                .store(MemFlagsData::trusted(), *value, tuple, offset as i32);
+        }
+        Ok(tuple)
+    }
+
+    /// Loads element `index` of `tuple`, a tuple of type `ty`.
+    pub fn gen_tuple_element(
+        &self,
+        tuple: Value,
+        ty: &Type,
+        index: usize,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        let elements = tuple_elements(ty, span)?;
+        let (_, offsets) = tuple_layout(elements);
+        Ok(builder.ins().load(
+            elements[index].to_cranelift(),
+            MemFlagsData::trusted(),
+            tuple,
+            offsets[index] as i32,
+        ))
+    }
+
+    /// The values a function returns for `value`, of type `ty`: its
+    /// elements if it's returned in registers, or itself.
+    pub fn gen_return_values(
+        &self,
+        value: Value,
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Vec<Value>, Diagnostic> {
+        if !returned_in_registers(ty) {
+            return Ok(vec![value]);
+        }
+        (0..tuple_elements(ty, span)?.len())
+            .map(|index| self.gen_tuple_element(value, ty, index, span, builder))
+            .collect()
+    }
+
+    /// The value of type `ty` a call at `span` returned as `results`.
+    pub fn gen_call_result(
+        &mut self,
+        results: &[Value],
+        ty: &Type,
+        span: Span,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Value, Diagnostic> {
+        match results {
+            [result] if !returned_in_registers(ty) => Ok(*result),
+            _ => self.gen_tuple_of(results, ty, span, builder),
+        }
+    }
+}
+
+/// Whether functions return values of type `ty` in registers, one per
+/// element, rather than as a pointer.
+pub fn returned_in_registers(ty: &Type) -> bool {
+    matches!(ty, Type::Compound(CompoundType::Tuple(elements)) if elements.len() <= MAX_REGISTER_ELEMENTS)
+}
+
+/// The return values of a function returning `ty`.
+pub fn return_params(ty: &Type) -> Vec<AbiParam> {
+    match ty {
+        Type::Compound(CompoundType::Tuple(elements)) if returned_in_registers(ty) => elements
+            .iter()
+            .map(|element| AbiParam::new(element.to_cranelift()))
+            .collect(),
+        _ => vec![AbiParam::new(ty.to_cranelift())],
+    }
+}
+
+/// Elements follow each other like the fields of a record.
+pub fn tuple_layout(elements: &[Type]) -> (Layout, Vec<u32>) {
+    Layout::EMPTY.extend(elements.iter().map(Layout::of))
+}
+
+/// The types of the elements of tuples of type `ty`.
+pub fn tuple_elements(ty: &Type, span: Span) -> Result<&[Type], Diagnostic> {
+    match ty {
+        Type::Compound(CompoundType::Tuple(elements)) => Ok(elements),
+        _ => Err(internal_error(format!("`{ty}` is not a tuple"), span)),
+    }
+}
@@ -9,9 +9,15 @@ impl Generable for Type {
         match self {
             Self::I8 | Self::U8 | Self::Bool => 1,
             Self::I16 | Self::U16 => 2,
-            Self::I32 | Self::U32 | Self::F32 => 4,
-            Self::I64 | Self::U64 | Self::F64 | Self::Function(_) | Self::Custom(_, _) => 8,
-            _ => todo!(),
+            // `()` is a placeholder `0`.
+            Self::I32 | Self::U32 | Self::F32 | Self::Unit => 4,
+            Self::I64
+            | Self::U64
+            | Self::F64
+            | Self::String
+            | Self::Function(_)
+            | Self::Compound(_)
+            | Self::Custom(_, _) => 8,
         }
     }
 
@@ -20,12 +26,27 @@ impl Generable for Type {
         match self {
             Self::I8 | Self::U8 => T::I8,
             Self::I16 | Self::U16 => T::I16,
-            Self::I32 | Self::U32 => T::I32,
-            Self::I64 | Self::U64 | Self::Function(_) | Self::Custom(_, _) => T::I64,
+            Self::I32 | Self::U32 | Self::Unit => T::I32,
+            Self::I64
+            | Self::U64
+            | Self::String
+            | Self::Function(_)
+            | Self::Compound(_)
+            | Self::Custom(_, _) => T::I64,
             Self::F32 => T::F32,
             Self::F64 => T::F64,
             Self::Bool => T::I8,
-            _ => todo!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_is_a_placeholder() {
+        assert_eq!(Type::Unit.to_cranelift(), cranelift::prelude::types::I32);
+        assert_eq!(Type::Unit.size(), 4);
+    }
+}
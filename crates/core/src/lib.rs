@@ -1,15 +1,302 @@
 #![warn(clippy::all, clippy::perf, clippy::style)]
 
+pub use ast::{diagnostics, span};
+pub use codegen::Options;
+
+use ast::{diagnostics::Diagnostic, span::FileId};
 use codegen::Codegen;
 use inference::TypeInference;
 
+/// A program compiled to an object file.
+pub struct Compiled {
+    pub object: Vec<u8>,
+    pub warnings: Vec<Diagnostic>,
+}
+
 /// Exposes the pipeline for compiling source code.
-pub fn compile_to_object(source: &mut &str) -> Vec<u8> {
-    let mut program = parser::program::parse_program(source).unwrap();
+///
+/// # Errors
+/// The diagnostics reported by the first stage that failed, after the
+/// warnings of the stages before it.
+pub fn compile_to_object(
+    source: &str,
+    file: FileId,
+    options: Options,
+) -> Result<Compiled, Vec<Diagnostic>> {
+    let mut program = parser::program::parse_program(source, file)?;
 
     let mut inference = TypeInference::default();
-    inference.infer_program(&mut program);
+    let mut warnings = inference.infer_program(&mut program)?;
+
+    let codegen = Codegen::new(options, source);
+    match codegen.compile_program_to_object(&program) {
+        Ok(object) => Ok(Compiled { object, warnings }),
+        Err(errors) => {
+            warnings.extend(errors);
+            Err(warnings)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    /// Compiles `source`, links it with `cc` and runs it: its exit status,
+    /// or `None` when a signal killed it.
+    fn run(name: &str, source: &str, options: Options) -> Option<i32> {
+        let compiled = compile_to_object(source, FileId::default(), options)
+            .unwrap_or_else(|errors| panic!("`{name}` doesn't compile: {errors:#?}"));
+        let dir = std::env::temp_dir().join(format!("elk-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (object, binary) = (dir.join("main.o"), dir.join("main"));
+        std::fs::write(&object, compiled.object).unwrap();
+        let linked = Command::new("cc")
+            .arg(&object)
+            .arg("-o")
+            .arg(&binary)
+            .status()
+            .unwrap();
+        assert!(linked.success(), "cannot link `{name}`");
+        let status = Command::new(&binary).status().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        status.code()
+    }
+
+    #[test]
+    fn test_match() {
+        let source = "type Shape { Circle(U8), Square(U8, U8), Dot }\n\
+                      area(Shape) -> U8;\n\
+                      area(s) = match s {\n\
+                          Shape::Circle(r) => r * 3,\n\
+                          Shape::Square(w, h) => w * h,\n\
+                          Shape::Dot => 1\n\
+                      };\n\
+                      main { area(Shape::Circle(2)) + area(Shape::Square(3, 4)) + area(Shape::Dot) }";
+        assert_eq!(run("match", source, Options::default()), Some(19));
+    }
+
+    #[test]
+    fn test_return_from_nested_blocks() {
+        let source = "f(U8) -> Bool;\n\
+                      f(x) {\n\
+                          y = match x { 0 => { return True; }, _ => x };\n\
+                          y > 5\n\
+                      }\n\
+                      g(U8) -> U8;\n\
+                      g(x) {\n\
+                          y = if x > 3 { return 10; } else { x };\n\
+                          y + 1\n\
+                      }\n\
+                      apply(f: (U8) -> U8, x: U8) -> U8;\n\
+                      apply(f, x) = f(x);\n\
+                      h(U8) -> U8;\n\
+                      h(x) = apply((v) -> match v { 0 => { return 7; }, _ => v * 2 }, x) + 1;\n\
+                      k(U8) -> U8;\n\
+                      k(x) {\n\
+                          y = x + 1;\n\
+                          return y;\n\
+                      }\n\
+                      main {\n\
+                          a = match f(0) { True => 1, False => 0 };\n\
+                          a + g(5) + g(1) + h(0) + h(2) + k(3)\n\
+                      }";
+        assert_eq!(run("return", source, Options::default()), Some(30));
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        let source = "safe(U8, U8) -> Bool;\n\
+                      safe(x, y) = y != 0 && x / y > 1;\n\
+                      either(U8, U8) -> Bool;\n\
+                      either(x, y) = y == 0 || x / y > 1;\n\
+                      main {\n\
+                          a = if safe(4, 0) { 1 } else { 0 };\n\
+                          b = if either(4, 0) { 2 } else { 0 };\n\
+                          c = if safe(4, 2) && either(4, 1) { 4 } else { 0 };\n\
+                          a + b + c\n\
+                      }";
+        assert_eq!(run("short_circuit", source, Options::default()), Some(6));
+    }
+
+    #[test]
+    fn test_operators_by_operand_type() {
+        let source = "sdiv(I8, I8) -> I8;\nsdiv(a, b) = a / b;\n\
+                      udiv(U8, U8) -> U8;\nudiv(a, b) = a / b;\n\
+                      srem(I8, I8) -> I8;\nsrem(a, b) = a % b;\n\
+                      urem(U8, U8) -> U8;\nurem(a, b) = a % b;\n\
+                      slt(I8, I8) -> Bool;\nslt(a, b) = a < b;\n\
+                      ult(U8, U8) -> Bool;\nult(a, b) = a < b;\n\
+                      fdiv(F64, F64) -> F64;\nfdiv(a, b) = a / b;\n\
+                      frem(F32, F32) -> F32;\nfrem(a, b) = a % b;\n\
+                      main {\n\
+                          a = if sdiv(-7, 2) == -3 { 1 } else { 0 };\n\
+                          b = if udiv(249, 2) == 124 { 2 } else { 0 };\n\
+                          c = if srem(-7, 2) == -1 { 4 } else { 0 };\n\
+                          d = if urem(249, 2) == 1 { 8 } else { 0 };\n\
+                          e = if slt(-1, 1) { 16 } else { 0 };\n\
+                          f = if ult(255, 1) { 0 } else { 32 };\n\
+                          g = if fdiv(7.5, 2.0) == 3.75 { 64 } else { 0 };\n\
+                          h = if frem(-7.5, 2.0) == -1.5 && 2.5 >= 1.0 { 128 } else { 0 };\n\
+                          a + b + c + d + e + f + g + h\n\
+                      }";
+        assert_eq!(run("operators", source, Options::default()), Some(255));
+    }
+
+    #[test]
+    fn test_functions_named_like_runtime_imports() {
+        let source = "malloc(U8) -> U8;\nmalloc(x) = x + 1;\n\
+                      write(U8, U8) -> U8;\nwrite(a, b) = a * b;\n\
+                      main {\n\
+                          words = [\"one\", \"{malloc(2)}\"];\n\
+                          n = if \"{List::length(words)}\" == \"2\" { 1 } else { 0 };\n\
+                          malloc(3) + write(2, 5) + n\n\
+                      }";
+        assert_eq!(run("runtime_names", source, Options::default()), Some(15));
+    }
+
+    #[test]
+    fn test_functions_named_like_libc_ones() {
+        let source = "free(U8) -> U8;\nfree(x) = x + 1;\n\
+                      abs(I8) -> I8;\nabs(x) = 0 - x;\n\
+                      main { \"{free(3)}{abs(-5)}\" == \"45\" }";
+        assert_eq!(run("libc_names", source, Options::default()), Some(1));
+    }
+
+    #[test]
+    fn test_checked_negation() {
+        let checked = Options {
+            checked_arithmetic: true,
+        };
+        let source = "neg(I8) -> I8;\nneg(x) = -x;\nmain { neg(-127) + neg(-128) }";
+        assert_eq!(run("negation", source, checked), Some(101));
+        let source = "neg(I8) -> I8;\nneg(x) = -x;\nmain { neg(-128) }";
+        assert_eq!(
+            run("wrapping_negation", source, Options::default()),
+            Some(128)
+        );
+    }
+
+    #[test]
+    fn test_float_patterns() {
+        let source = "f(F64) -> U8;\nf(2.5) = 1;\nf(_) = 0;\n\
+                      main {\n\
+                          a = match 2.5 { 2.5 => 2, _ => 0 };\n\
+                          b = match 1.5 { 2.5 => 0, _ => 4 };\n\
+                          f(2.5) + f(3.0) + a + b\n\
+                      }";
+        assert_eq!(run("float_patterns", source, Options::default()), Some(7));
+    }
+
+    #[test]
+    fn test_strings() {
+        let source = "greet(String) -> String;\n\
+                      greet(name) = String::concat(\"hi \", name);\n\
+                      main {\n\
+                          greeting = greet(\"elk\");\n\
+                          same = if greeting == \"hi elk\" { 10 } else { 0 };\n\
+                          String::length(greeting) + String::length(\"\") + same\n\
+                      }";
+        assert_eq!(run("strings", source, Options::default()), Some(16));
+    }
+
+    #[test]
+    fn test_lists() {
+        let source = "double(U8) -> U8;\ndouble(x) = x * 2;\n\
+                      big(U8) -> Bool;\nbig(x) = x > 2;\n\
+                      add(U8, U8) -> U8;\nadd(a, b) = a + b;\n\
+                      first([U8]) -> U8;\nfirst([]) = 0;\nfirst([x, ..]) = x;\n\
+                      main {\n\
+                          xs = List::filter(List::map([1, 2, 3], double), big);\n\
+                          two = if List::length(xs) == 2 { 1 } else { 0 };\n\
+                          List::fold(xs, 0, add) + first(xs) + first([]) + two\n\
+                      }";
+        assert_eq!(run("lists", source, Options::default()), Some(15));
+    }
+
+    #[test]
+    fn test_tuples() {
+        let source = "divmod(U8, U8) -> (U8, U8);\n\
+                      divmod(a, b) = (a / b, a % b);\n\
+                      widen(U8) -> (U8, U64, Bool);\n\
+                      widen(x) = (x, 1000, x > 1);\n\
+                      sum((U8, U8)) -> U8;\n\
+                      sum((q, r)) = q * 10 + r;\n\
+                      main {\n\
+                          c = match widen(2) { (a, 1000, True) => a, _ => 0 };\n\
+                          sum(divmod(17, 5)) + c\n\
+                      }";
+        assert_eq!(run("tuples", source, Options::default()), Some(34));
+    }
+
+    #[test]
+    fn test_record_patterns() {
+        let source = "type Person { name: String, age: U8 }\n\
+                      age(Person) -> U8;\n\
+                      age(Person { age, .. }) = age;\n\
+                      score(Person) -> U8;\n\
+                      score(Person { name: \"Al\", age }) = age * 2;\n\
+                      score(Person { age: 0, .. }) = 1;\n\
+                      score(_) = 0;\n\
+                      main {\n\
+                          al = Person { name: \"Al\", age: 5 };\n\
+                          bo = Person { name: \"Bo\", age: 0 };\n\
+                          cy = Person { name: \"Cy\", age: 7 };\n\
+                          age(cy) + score(al) + score(bo) + score(cy)\n\
+                      }";
+        assert_eq!(run("records", source, Options::default()), Some(18));
+    }
+
+    #[test]
+    fn test_closures() {
+        let source = "apply(f: (U8) -> U8, x: U8) -> U8;\n\
+                      apply(f, x) = f(x);\n\
+                      adder(U8) -> (U8) -> U8;\n\
+                      adder(n) = (x) -> x + n;\n\
+                      main {\n\
+                          k = 3;\n\
+                          add_k = (x) -> x + k;\n\
+                          twice = (x) -> apply(add_k, apply(add_k, x));\n\
+                          apply(adder(10), 1) + twice(2) + List::fold(List::map([1, 2], add_k), 0, (a, b) -> a + b)\n\
+                      }";
+        assert_eq!(run("closures", source, Options::default()), Some(28));
+    }
+
+    #[test]
+    fn test_match_on_literals_and_nested_variants() {
+        let source = "type Shape { Circle(U8), Dot }\n\
+                      size(Option<Shape>) -> U8;\n\
+                      size(s) = match s {\n\
+                          Option::Some(Shape::Circle(0)) => 1,\n\
+                          Option::Some(Shape::Circle(r)) => r,\n\
+                          Option::Some(Shape::Dot) => 2,\n\
+                          Option::None => 3\n\
+                      };\n\
+                      word(String) -> U8;\n\
+                      word(w) = match w { \"one\" => 1, \"two\" => 2, _ => 0 };\n\
+                      main {\n\
+                          a = size(Option::Some(Shape::Circle(0))) + size(Option::Some(Shape::Circle(9)));\n\
+                          b = size(Option::Some(Shape::Dot)) + size(Option::None);\n\
+                          a + b + word(\"two\") + word(\"six\") + match True { True => 10, False => 0 }\n\
+                      }";
+        assert_eq!(run("match_literals", source, Options::default()), Some(27));
+    }
 
-    let codegen = Codegen::default();
-    codegen.compile_program_to_object(&program)
+    #[test]
+    fn test_comparisons_and_bitwise_operators() {
+        let source = "uge(U8, U8) -> Bool;\nuge(a, b) = a >= b;\n\
+                      sgt(I8, I8) -> Bool;\nsgt(a, b) = a > b;\n\
+                      flip(U8, U8) -> Bool;\nflip(a, b) = !((a ^ b) == 0);\n\
+                      main {\n\
+                          a = if uge(200, 100) { 1 } else { 0 };\n\
+                          b = if sgt(-100, 100) { 0 } else { 2 };\n\
+                          c = if flip(5, 3) { 4 } else { 0 };\n\
+                          d = if flip(6, 6) { 0 } else { 8 };\n\
+                          e = if 1.5 != 2.5 && 0.5 <= 0.5 { 16 } else { 0 };\n\
+                          a + b + c + d + e\n\
+                      }";
+        assert_eq!(run("comparisons", source, Options::default()), Some(31));
+    }
 }
@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ast::{expressions::ExpressionKind, functions::FunctionBody};
+
+use crate::visit::walk_body;
+
+/// Every name a function body refers to: called functions, and identifiers
+/// (which may name a function passed as a value). A method call, whose
+/// namespace depends on the type of its receiver, refers to `.map`: see
+/// [`resolve`].
+pub fn references(body: &FunctionBody) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    walk_body(body, &mut |expr| match &expr.kind {
+        ExpressionKind::FunctionCall {
+            namespace, name, ..
+        } => {
+            names.insert(
+                namespace
+                    .as_ref()
+                    .map_or_else(|| name.clone(), |ns| format!("{ns}::{name}")),
+            );
+        }
+        ExpressionKind::MethodCall { name, .. } => {
+            names.insert(format!(".{name}"));
+        }
+        ExpressionKind::Identifier(name) => {
+            names.insert(name.clone());
+        }
+        _ => {}
+    });
+    names
+}
+
+/// The functions among `names` that `reference` may be: itself, or for a
+/// method `.map`, `map` in any namespace.
+pub fn resolve<'a>(reference: &'a str, names: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+    match reference.strip_prefix('.') {
+        Some(method) => names
+            .filter(|name| {
+                name.split_once("::")
+                    .is_some_and(|(_, name)| name == method)
+            })
+            .map(String::as_str)
+            .collect(),
+        None => vec![reference],
+    }
+}
+
+/// Splits functions into binding groups: sets of mutually recursive
+/// functions, ordered so that every group comes after the groups it uses.
+///
+/// Each group is inferred together and generalised before the groups that
+/// use it, which is what lets them use it at different types.
+/// This is Tarjan's strongly connected components algorithm.
+pub fn binding_groups(
+    names: &[String],
+    edges: &BTreeMap<String, BTreeSet<String>>,
+) -> Vec<Vec<String>> {
+    struct Tarjan<'a> {
+        edges: &'a BTreeMap<String, BTreeSet<String>>,
+        index: BTreeMap<&'a str, usize>,
+        low: BTreeMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        groups: Vec<Vec<String>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, name: &'a str) {
+            let index = self.index.len();
+            self.index.insert(name, index);
+            self.low.insert(name, index);
+            self.stack.push(name);
+            self.on_stack.insert(name);
+
+            for next in self.edges.get(name).into_iter().flatten() {
+                let next = next.as_str();
+                if !self.edges.contains_key(next) {
+                    continue;
+                }
+                if !self.index.contains_key(next) {
+                    self.visit(next);
+                    let low = self.low[name].min(self.low[next]);
+                    self.low.insert(name, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.low[name].min(self.index[next]);
+                    self.low.insert(name, low);
+                }
+            }
+
+            if self.low[name] == self.index[name] {
+                let mut group = vec![];
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    group.push(member.to_owned());
+                    if member == name {
+                        break;
+                    }
+                }
+                group.reverse();
+                self.groups.push(group);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        edges,
+        index: BTreeMap::new(),
+        low: BTreeMap::new(),
+        stack: vec![],
+        on_stack: BTreeSet::new(),
+        groups: vec![],
+    };
+    for name in names {
+        if !tarjan.index.contains_key(name.as_str()) {
+            tarjan.visit(name);
+        }
+    }
+    tarjan.groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> BTreeMap<String, BTreeSet<String>> {
+        edges
+            .iter()
+            .map(|(from, to)| {
+                (
+                    (*from).to_owned(),
+                    to.iter().map(|s| (*s).to_owned()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dependencies_come_first() {
+        let edges = graph(&[("a", &["b"]), ("b", &["c"]), ("c", &[])]);
+        let names = ["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        assert_eq!(
+            binding_groups(&names, &edges),
+            [vec!["c"], vec!["b"], vec!["a"]]
+        );
+    }
+
+    #[test]
+    fn test_mutual_recursion_is_one_group() {
+        let edges = graph(&[
+            ("even", &["odd"]),
+            ("odd", &["even"]),
+            ("f", &["even", "x"]),
+        ]);
+        let names = ["even".to_owned(), "odd".to_owned(), "f".to_owned()];
+        assert_eq!(
+            binding_groups(&names, &edges),
+            [vec!["even", "odd"], vec!["f"]]
+        );
+    }
+
+    #[test]
+    fn test_methods_resolve_to_any_namespace() {
+        let names = [
+            "map".to_owned(),
+            "Option::map".to_owned(),
+            "List::map".to_owned(),
+            "Option::or".to_owned(),
+        ];
+        assert_eq!(resolve(".map", names.iter()), ["Option::map", "List::map"]);
+        assert_eq!(resolve("map", names.iter()), ["map"]);
+    }
+}
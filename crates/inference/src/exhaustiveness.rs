@@ -0,0 +1,492 @@
+use ast::{
+    expressions::Literal,
+    patterns::{Pattern, PatternKind},
+    types::CustomType,
+};
+
+/// What a pattern other than a wildcard or a binding matches.
+#[derive(Debug, Clone, PartialEq)]
+enum Constructor {
+    Literal(Literal),
+    Variant {
+        enum_name: String,
+        variant_name: String,
+        arity: usize,
+    },
+    EmptyList,
+    /// A list's first element, and the list of the others.
+    Cons,
+    /// The only constructor of tuples of the given arity.
+    Tuple(usize),
+    /// The only constructor of a record, with its fields in order.
+    Record {
+        type_name: String,
+        fields: Vec<String>,
+    },
+}
+
+impl Constructor {
+    fn of(pattern: &Pattern) -> Option<Self> {
+        match &pattern.kind {
+            PatternKind::Wildcard | PatternKind::Identifier(_) => None,
+            PatternKind::Literal(lit) => Some(Self::Literal(lit.clone())),
+            PatternKind::EnumInstance {
+                enum_name,
+                variant_name,
+                args,
+            } => Some(Self::Variant {
+                enum_name: enum_name.clone(),
+                variant_name: variant_name.clone(),
+                arity: args.len(),
+            }),
+            PatternKind::EmptyList => Some(Self::EmptyList),
+            PatternKind::Cons { .. } => Some(Self::Cons),
+            PatternKind::Tuple(elements) => Some(Self::Tuple(elements.len())),
+            PatternKind::Record {
+                type_name, fields, ..
+            } => Some(Self::Record {
+                type_name: type_name.clone(),
+                fields: fields.iter().map(|(name, _)| name.clone()).collect(),
+            }),
+        }
+    }
+
+    const fn arity(&self) -> usize {
+        match self {
+            Self::Literal(_) | Self::EmptyList => 0,
+            Self::Variant { arity, .. } | Self::Tuple(arity) => *arity,
+            Self::Cons => 2,
+            Self::Record { fields, .. } => fields.len(),
+        }
+    }
+
+    /// The pattern matching exactly this constructor, applied to `args`.
+    fn apply(&self, args: Vec<Pattern>) -> Pattern {
+        match self {
+            Self::Literal(lit) => Pattern::literal(lit.clone()),
+            Self::Variant {
+                enum_name,
+                variant_name,
+                ..
+            } => Pattern::enum_instance(enum_name.clone(), variant_name.clone(), args),
+            Self::EmptyList => Pattern::list(vec![], None),
+            Self::Cons => {
+                let [head, tail] = <[Pattern; 2]>::try_from(args).expect("a head and a tail");
+                Pattern::list(vec![head], Some(tail))
+            }
+            Self::Tuple(_) => Pattern::tuple(args),
+            Self::Record { type_name, fields } => Pattern::record(
+                type_name.clone(),
+                fields.iter().cloned().zip(args).collect(),
+                false,
+            ),
+        }
+    }
+}
+
+/// Pattern-matrix analysis of a list of clauses, each a row of patterns
+/// tried against the same values, as described in Maranget's "Warnings for
+/// pattern matching".
+pub struct Matrix<'a> {
+    types: &'a [CustomType],
+}
+
+impl<'a> Matrix<'a> {
+    pub const fn new(types: &'a [CustomType]) -> Self {
+        Self { types }
+    }
+
+    /// The values matched by no row, as a row of patterns: `None` when the
+    /// rows are exhaustive.
+    pub fn missing(&self, rows: &[Vec<Pattern>], width: usize) -> Option<Vec<Pattern>> {
+        let wildcards = vec![Pattern::wildcard(); width];
+        self.useful(rows, &wildcards)
+    }
+
+    /// Whether `row` matches some value no row of `rows` does, if so giving
+    /// such a value. A clause that isn't useful after the ones before it is
+    /// unreachable.
+    pub fn useful(&self, rows: &[Vec<Pattern>], row: &[Pattern]) -> Option<Vec<Pattern>> {
+        let complete = |row: &[Pattern]| row.iter().map(|p| self.complete(p)).collect();
+        let rows: Vec<Vec<Pattern>> = rows.iter().map(|row| complete(row)).collect();
+        self.find_useful(&rows, &complete(row))
+    }
+
+    fn find_useful(&self, rows: &[Vec<Pattern>], row: &[Pattern]) -> Option<Vec<Pattern>> {
+        let Some((head, tail)) = row.split_first() else {
+            return rows.is_empty().then(Vec::new);
+        };
+
+        if let Some(constructor) = Constructor::of(head) {
+            let rows = specialize(rows, &constructor);
+            let row = specialize_row(row, &constructor)?;
+            return self
+                .find_useful(&rows, &row)
+                .map(|witness| rebuild(&constructor, witness));
+        }
+
+        let mut used: Vec<Constructor> = vec![];
+        for c in rows.iter().filter_map(|row| Constructor::of(&row[0])) {
+            if !used.contains(&c) {
+                used.push(c);
+            }
+        }
+        match self.missing_constructor(&used) {
+            // Every constructor appears: the value must be built with one of
+            // them, so try each.
+            None => used.iter().find_map(|constructor| {
+                let rows = specialize(rows, constructor);
+                let mut row = vec![Pattern::wildcard(); constructor.arity()];
+                row.extend_from_slice(tail);
+                self.find_useful(&rows, &row)
+                    .map(|witness| rebuild(constructor, witness))
+            }),
+            // Some value isn't built with any of them: only the rows starting
+            // with a wildcard can match it.
+            Some(missing) => {
+                let rows: Vec<Vec<Pattern>> = rows
+                    .iter()
+                    .filter(|row| Constructor::of(&row[0]).is_none())
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                let mut witness = self.find_useful(&rows, tail)?;
+                witness.insert(0, missing);
+                Some(witness)
+            }
+        }
+    }
+
+    /// `pattern`, with its record patterns listing every field of their type
+    /// in order, so the patterns of a record all have the same constructor.
+    fn complete(&self, pattern: &Pattern) -> Pattern {
+        let kind = match &pattern.kind {
+            PatternKind::Record {
+                type_name, fields, ..
+            } => {
+                let declared = self
+                    .types
+                    .iter()
+                    .find(|ty| ty.name() == type_name)
+                    .and_then(CustomType::get_record_fields);
+                let fields = match declared {
+                    Some(declared) => declared
+                        .iter()
+                        .map(|field| {
+                            let pattern = fields
+                                .iter()
+                                .find(|(name, _)| name == field.name())
+                                .map_or_else(Pattern::wildcard, |(_, p)| self.complete(p));
+                            (field.name().to_owned(), pattern)
+                        })
+                        .collect(),
+                    None => fields.clone(),
+                };
+                PatternKind::Record {
+                    type_name: type_name.clone(),
+                    fields,
+                    rest: false,
+                }
+            }
+            PatternKind::EnumInstance {
+                enum_name,
+                variant_name,
+                args,
+            } => PatternKind::EnumInstance {
+                enum_name: enum_name.clone(),
+                variant_name: variant_name.clone(),
+                args: args.iter().map(|arg| self.complete(arg)).collect(),
+            },
+            PatternKind::Cons { head, tail } => PatternKind::Cons {
+                head: Box::new(self.complete(head)),
+                tail: Box::new(self.complete(tail)),
+            },
+            PatternKind::Tuple(elements) => {
+                PatternKind::Tuple(elements.iter().map(|e| self.complete(e)).collect())
+            }
+            kind => kind.clone(),
+        };
+        Pattern::new(kind).with_span(pattern.span())
+    }
+
+    /// A pattern for values built with none of `used`, if there are any.
+    fn missing_constructor(&self, used: &[Constructor]) -> Option<Pattern> {
+        match used.first() {
+            None => Some(Pattern::wildcard()),
+            Some(Constructor::Literal(Literal::Bool(_))) => [true, false]
+                .into_iter()
+                .map(Literal::Bool)
+                .find(|b| !used.contains(&Constructor::Literal(b.clone())))
+                .map(Pattern::literal),
+            Some(Constructor::Literal(Literal::Integer(_))) => (0..)
+                .map(Literal::Integer)
+                .find(|n| !used.contains(&Constructor::Literal(n.clone())))
+                .map(Pattern::literal),
+            Some(Constructor::Literal(_)) => Some(Pattern::wildcard()),
+            Some(Constructor::Tuple(_) | Constructor::Record { .. }) => None,
+            Some(Constructor::EmptyList | Constructor::Cons) => [
+                (Constructor::EmptyList, vec![]),
+                (Constructor::Cons, vec![Pattern::wildcard(); 2]),
+            ]
+            .into_iter()
+            .find(|(c, _)| !used.contains(c))
+            .map(|(c, args)| c.apply(args)),
+            Some(Constructor::Variant { enum_name, .. }) => {
+                let Some(variants) = self
+                    .types
+                    .iter()
+                    .find(|ty| ty.name() == enum_name)
+                    .and_then(CustomType::get_enum_variants)
+                else {
+                    return Some(Pattern::wildcard());
+                };
+                let is_used = |name: &str| {
+                    used.iter().any(|c| {
+                        matches!(c, Constructor::Variant { variant_name, .. } if variant_name == name)
+                    })
+                };
+                variants
+                    .iter()
+                    .find(|(_, variant)| !is_used(variant.name()))
+                    .map(|(_, variant)| {
+                        Pattern::enum_instance(
+                            enum_name.clone(),
+                            variant.name().to_owned(),
+                            vec![Pattern::wildcard(); variant.types().len()],
+                        )
+                    })
+            }
+        }
+    }
+}
+
+/// The rows that match values built with `constructor`, with its arguments
+/// in place of their first pattern.
+fn specialize(rows: &[Vec<Pattern>], constructor: &Constructor) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter_map(|row| specialize_row(row, constructor))
+        .collect()
+}
+
+fn specialize_row(row: &[Pattern], constructor: &Constructor) -> Option<Vec<Pattern>> {
+    let (head, tail) = row.split_first()?;
+    let mut specialized = match &head.kind {
+        PatternKind::Wildcard | PatternKind::Identifier(_) => {
+            vec![Pattern::wildcard(); constructor.arity()]
+        }
+        PatternKind::EnumInstance { args, .. }
+            if Constructor::of(head).as_ref() == Some(constructor) =>
+        {
+            args.clone()
+        }
+        PatternKind::Cons { head, tail } if *constructor == Constructor::Cons => {
+            vec![(**head).clone(), (**tail).clone()]
+        }
+        PatternKind::Tuple(elements) => elements.clone(),
+        PatternKind::Record { fields, .. } => {
+            fields.iter().map(|(_, pattern)| pattern.clone()).collect()
+        }
+        PatternKind::Literal(_) | PatternKind::EmptyList
+            if Constructor::of(head).as_ref() == Some(constructor) =>
+        {
+            vec![]
+        }
+        _ => return None,
+    };
+    specialized.extend_from_slice(tail);
+    Some(specialized)
+}
+
+/// Puts the first `arity` patterns of `witness` back under `constructor`.
+fn rebuild(constructor: &Constructor, mut witness: Vec<Pattern>) -> Vec<Pattern> {
+    let rest = witness.split_off(constructor.arity());
+    let mut rebuilt = vec![constructor.apply(witness)];
+    rebuilt.extend(rest);
+    rebuilt
+}
+
+/// A pattern as written in the source.
+pub fn show(pattern: &Pattern) -> String {
+    match &pattern.kind {
+        PatternKind::Wildcard => "_".to_owned(),
+        PatternKind::Identifier(name) => name.clone(),
+        PatternKind::Literal(Literal::Bool(true)) => "True".to_owned(),
+        PatternKind::Literal(Literal::Bool(false)) => "False".to_owned(),
+        PatternKind::Literal(Literal::Integer(n)) => n.to_string(),
+        PatternKind::Literal(Literal::Float(f)) => f.to_string(),
+        PatternKind::Literal(Literal::String(s)) => format!("{s:?}"),
+        PatternKind::EnumInstance {
+            enum_name,
+            variant_name,
+            args,
+        } if args.is_empty() => format!("{enum_name}::{variant_name}"),
+        PatternKind::EnumInstance {
+            enum_name,
+            variant_name,
+            args,
+        } => {
+            let args: Vec<_> = args.iter().map(show).collect();
+            format!("{enum_name}::{variant_name}({})", args.join(", "))
+        }
+        PatternKind::EmptyList => "[]".to_owned(),
+        PatternKind::Cons { .. } => {
+            let mut elements = vec![];
+            let mut list = pattern;
+            while let PatternKind::Cons { head, tail } = &list.kind {
+                elements.push(show(head));
+                list = tail;
+            }
+            match &list.kind {
+                PatternKind::EmptyList => {}
+                PatternKind::Wildcard => elements.push("..".to_owned()),
+                _ => elements.push(format!("..{}", show(list))),
+            }
+            format!("[{}]", elements.join(", "))
+        }
+        PatternKind::Tuple(elements) => {
+            let elements: Vec<_> = elements.iter().map(show).collect();
+            format!("({})", elements.join(", "))
+        }
+        // Fields matching anything are left out.
+        PatternKind::Record {
+            type_name,
+            fields,
+            rest,
+        } => {
+            let mut shown: Vec<_> = fields
+                .iter()
+                .filter(|(_, p)| p.kind != PatternKind::Wildcard)
+                .map(|(name, p)| match &p.kind {
+                    PatternKind::Identifier(var) if var == name => name.clone(),
+                    _ => format!("{name}: {}", show(p)),
+                })
+                .collect();
+            if *rest || shown.len() < fields.len() {
+                shown.push("..".to_owned());
+            }
+            format!("{type_name} {{ {} }}", shown.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::types::{
+        Type,
+        custom::{CustomTypeContent, Field, Variant},
+    };
+
+    use super::*;
+
+    fn option() -> CustomType {
+        let variants = vec![
+            (0, Variant::new("None", vec![])),
+            (1, Variant::new("Some", vec![Type::Bool])),
+        ];
+        CustomType::new("Option", Some(CustomTypeContent::Enum(variants)), vec![])
+    }
+
+    fn some(arg: Pattern) -> Pattern {
+        Pattern::enum_instance("Option".to_owned(), "Some".to_owned(), vec![arg])
+    }
+
+    fn boolean(b: bool) -> Pattern {
+        Pattern::literal(Literal::Bool(b))
+    }
+
+    #[test]
+    fn test_missing_variant() {
+        let types = [option()];
+        let rows = [vec![some(Pattern::wildcard())]];
+        let missing = Matrix::new(&types).missing(&rows, 1).unwrap();
+        assert_eq!(show(&missing[0]), "Option::None");
+    }
+
+    #[test]
+    fn test_missing_nested_case() {
+        let types = [option()];
+        let rows = [
+            vec![some(boolean(true))],
+            vec![Pattern::enum_instance(
+                "Option".to_owned(),
+                "None".to_owned(),
+                vec![],
+            )],
+        ];
+        let missing = Matrix::new(&types).missing(&rows, 1).unwrap();
+        assert_eq!(show(&missing[0]), "Option::Some(False)");
+    }
+
+    #[test]
+    fn test_exhaustive_rows() {
+        let types = [option()];
+        let rows = [
+            vec![boolean(true), Pattern::wildcard()],
+            vec![
+                Pattern::identifier("x".to_owned()),
+                some(Pattern::wildcard()),
+            ],
+            vec![boolean(false), Pattern::wildcard()],
+        ];
+        assert_eq!(Matrix::new(&types).missing(&rows, 2), None);
+    }
+
+    #[test]
+    fn test_missing_list_lengths() {
+        let x = || Pattern::identifier("x".to_owned());
+        let rows = [
+            vec![Pattern::list(vec![], None)],
+            vec![Pattern::list(vec![x()], None)],
+            vec![Pattern::list(
+                vec![x(), boolean(true)],
+                Some(Pattern::wildcard()),
+            )],
+        ];
+        let missing = Matrix::new(&[]).missing(&rows, 1).unwrap();
+        assert_eq!(show(&missing[0]), "[_, False, ..]");
+
+        let rows = [
+            vec![Pattern::list(vec![], None)],
+            vec![Pattern::list(vec![x()], Some(x()))],
+        ];
+        assert_eq!(Matrix::new(&[]).missing(&rows, 1), None);
+    }
+
+    #[test]
+    fn test_missing_tuple() {
+        let rows = [
+            vec![Pattern::tuple(vec![boolean(true), Pattern::wildcard()])],
+            vec![Pattern::tuple(vec![Pattern::wildcard(), boolean(false)])],
+        ];
+        let missing = Matrix::new(&[]).missing(&rows, 1).unwrap();
+        assert_eq!(show(&missing[0]), "(False, True)");
+    }
+
+    #[test]
+    fn test_missing_record_field_value() {
+        let fields = vec![
+            Field::new("name", Type::String),
+            Field::new("adult", Type::Bool),
+        ];
+        let person = CustomType::new("Person", Some(CustomTypeContent::Record(fields)), vec![]);
+        let adult = |b| {
+            Pattern::record(
+                "Person".to_owned(),
+                vec![("adult".to_owned(), boolean(b))],
+                true,
+            )
+        };
+        let rows = [vec![adult(true)]];
+        let types = [person];
+        let missing = Matrix::new(&types).missing(&rows, 1).unwrap();
+        assert_eq!(show(&missing[0]), "Person { adult: False, .. }");
+
+        let rows = [vec![adult(true)], vec![adult(false)]];
+        assert_eq!(Matrix::new(&types).missing(&rows, 1), None);
+    }
+
+    #[test]
+    fn test_row_after_wildcard_is_useless() {
+        let rows = [vec![Pattern::wildcard()]];
+        assert_eq!(Matrix::new(&[]).useful(&rows, &[boolean(true)]), None);
+    }
+}
@@ -1,150 +1,2229 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use ast::{
-    expressions::{AssociatedType, Expression, ExpressionKind, Literal, MatchArm, MatchBody},
+    diagnostics::{Diagnostic, codes},
+    expressions::{
+        AssociatedType, BinaryOp, Expression, ExpressionKind, Literal, MatchArm, MatchBody,
+        StringPart, UnaryOp,
+    },
+    functions::{FunctionBody, FunctionDeclaration, FunctionImplementation},
+    patterns::{Pattern, PatternKind},
     program::Program,
-    statements::Statement,
-    types::{CustomType, FunctionSignature, Type},
+    span::Span,
+    statements::{Block, StatementKind},
+    traits::{Bound, TraitDeclaration},
+    types::{CustomType, Type},
 };
 
+use crate::{
+    exhaustiveness::Matrix,
+    traits::Predicate,
+    ty::{LIST, Scheme, TUPLE, Ty, TypeVar},
+    unify::{Substitution, UnifyError},
+    visit::{walk_block, walk_block_mut, walk_body, walk_body_mut},
+};
+
+mod deps;
+mod exhaustiveness;
+mod traits;
+mod ty;
+mod unify;
+mod visit;
+
+/// Hindley–Milner type inference.
+///
+/// Walking the program generates [`Constraint`]s between types, most of
+/// them type variables standing for the types of expressions. Constraints
+/// are solved by unification whenever a type has to be known: to generalise
+/// the type of a variable or a function, to look up a record field, and at
+/// the end. Finally every expression's [`AssociatedType`] is replaced by
+/// its solution.
 #[derive(Default)]
 pub struct TypeInference {
-    variables: BTreeMap<String, Type>,
+    /// Local variables, innermost scope last.
+    scopes: Vec<BTreeMap<String, Scheme>>,
     types: Vec<CustomType>,
-    functions: BTreeMap<String, FunctionSignature>,
-    _constraints: Vec<Constraint>,
+    traits: Vec<TraitDeclaration>,
+    /// The traits implemented by each type: `(Show, U8)`.
+    instances: BTreeSet<(String, String)>,
+    functions: BTreeMap<String, Scheme>,
+    constraints: Vec<Constraint>,
+    substitution: Substitution,
+    next_var: u32,
+    /// What `return` statements must return in the function being inferred.
+    return_type: Option<Ty>,
+    /// Every number literal, to check it fits in its type.
+    literals: Vec<NumberLiteral>,
+    /// The holes of interpolated strings, to check their values can be
+    /// rendered.
+    holes: Vec<(Ty, Span)>,
+    /// The operands of operators that only apply to some types, to check
+    /// once their types are known.
+    operands: Vec<Operand>,
+    /// The types calls require to implement a trait, to check once they're
+    /// known.
+    predicates: Vec<Predicate>,
+    /// The type parameters of the declared function being checked, and
+    /// their bounds.
+    assumed: (Vec<String>, Vec<Bound>),
+    diagnostics: Vec<Diagnostic>,
 }
 
-struct Constraint;
+/// `found` must be the same type as `expected`.
+struct Constraint {
+    expected: Ty,
+    found: Ty,
+    /// Where `found` comes from.
+    span: Span,
+    /// Where `expected` comes from, when it's worth pointing at.
+    because: Option<Span>,
+}
+
+/// A number literal. Its type is taken from the context it's used in, and
+/// defaults to `I32` for integers and `F64` for floats when nothing decides
+/// it: by the time the function containing it is generalised, or at the end.
+struct NumberLiteral {
+    literal: Literal,
+    /// Whether it's the operand of `-`: `-128` fits in `I8` but `128`
+    /// doesn't.
+    negative: bool,
+    ty: Ty,
+    span: Span,
+}
+
+/// The operand of an operator, which must be one of the types `operator`
+/// applies to.
+struct Operand {
+    operator: String,
+    applies_to: Applicable,
+    ty: Ty,
+    span: Span,
+}
+
+/// The types an operator applies to.
+#[derive(Clone, Copy)]
+enum Applicable {
+    /// `+ - * / %`, `< <= > >=`
+    Numbers,
+    /// `^`
+    Integers,
+    /// `-x`
+    Signed,
+    /// `== !=`
+    Equatable,
+    /// `!x`: `Bool`, or an integer for its bits.
+    Bits,
+}
+
+impl Applicable {
+    fn accepts(self, ty: &Type) -> bool {
+        match self {
+            Self::Numbers => ty.is_integer() || ty.is_float(),
+            Self::Integers => ty.is_integer(),
+            Self::Signed => ty.is_signed() || ty.is_float(),
+            Self::Equatable => {
+                ty.is_integer() || ty.is_float() || matches!(ty, Type::Bool | Type::String)
+            }
+            Self::Bits => ty.is_integer() || *ty == Type::Bool,
+        }
+    }
+
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Numbers => "numbers",
+            Self::Integers => "integers",
+            Self::Signed => "signed integers and floats",
+            Self::Equatable => "numbers, booleans and strings",
+            Self::Bits => "booleans and integers",
+        }
+    }
+}
 
 impl TypeInference {
-    pub fn infer_program(&mut self, program: &mut Program) {
-        for fd in &program.function_declarations {
+    /// Gives the warnings found in the program.
+    ///
+    /// # Errors
+    /// Every type error found in the program, and the warnings.
+    pub fn infer_program(
+        &mut self,
+        program: &mut Program,
+    ) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+        self.types.extend(program.type_definitions.iter().cloned());
+        self.check_builtin_types(program);
+        self.check_implemented_functions(program);
+        self.types.extend(CustomType::builtins());
+        self.declare_traits(program);
+
+        for fd in &FunctionDeclaration::builtins() {
+            let scheme = self.declared_scheme(fd);
             self.functions
-                .insert(fd.qualified_name().qualified(), fd.signature().to_owned());
+                .insert(fd.qualified_name().qualified(), scheme);
         }
+        self.declare_functions(program);
+
+        let inferred = self.infer_undeclared_functions(program);
+        self.check_declared_functions(program);
+        program.function_declarations.extend(inferred);
+        self.infer_entry_point(&mut program.entry_point);
+        self.check_literals();
+        self.check_holes();
+        self.check_operands();
+        self.check_predicates();
 
-        for a in &program.type_definitions {
-            self.types.push(a.to_owned());
+        for fi in &mut program.function_implementations {
+            walk_body_mut(fi.body_mut(), &mut |expr| self.resolve(expr));
+        }
+        walk_block_mut(&mut program.entry_point, &mut |expr| self.resolve(expr));
+        self.report_unresolved(program);
+        // Patterns that don't type check can't be analysed.
+        if !self.diagnostics.iter().any(Diagnostic::is_error) {
+            self.check_patterns(program);
         }
 
-        for stmt in &mut program.entry_point.statements {
-            match stmt {
-                Statement::Assignment(var_name, expr) => {
-                    let new_ty = self.infer_expr(expr);
-                    expr.set_type(AssociatedType::Concrete(new_ty.to_owned()));
-                    self.variables.insert(var_name.to_owned(), new_ty);
+        let diagnostics = std::mem::take(&mut self.diagnostics);
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            Err(diagnostics)
+        } else {
+            Ok(diagnostics)
+        }
+    }
+
+    /// The type of a declared function, generalised over its type parameters.
+    fn declared_scheme(&mut self, fd: &FunctionDeclaration) -> Scheme {
+        let params: BTreeMap<String, Ty> = fd
+            .type_params()
+            .iter()
+            .map(|param| (param.clone(), self.fresh()))
+            .collect();
+        let signature = fd.signature();
+        let ty = Ty::Fun(
+            signature
+                .arguments()
+                .iter()
+                .map(|arg| Ty::from_type(arg, &params))
+                .collect(),
+            Box::new(Ty::from_type(signature.return_type(), &params)),
+        );
+        let vars = params
+            .values()
+            .filter_map(|param| match param {
+                Ty::Var(var) => Some(*var),
+                _ => None,
+            })
+            .collect();
+        let bounds = fd
+            .bounds()
+            .iter()
+            .filter_map(|bound| Some((bound.trait_name.clone(), params.get(&bound.param)?.clone())))
+            .collect();
+        Scheme { vars, ty, bounds }
+    }
+
+    /// Reports the types named like a builtin one without its variants: such
+    /// a type replaces the builtin, also in the results of builtin functions
+    /// like `Int::checked_add`, which build those variants.
+    fn check_builtin_types(&mut self, program: &Program) {
+        // Variants are looked up by name, so their order doesn't matter.
+        let shape = |ty: &CustomType| -> BTreeSet<(String, usize)> {
+            ty.get_enum_variants()
+                .into_iter()
+                .flatten()
+                .map(|(_, variant)| (variant.name().to_owned(), variant.types().len()))
+                .collect()
+        };
+        for builtin in CustomType::builtins() {
+            let expected = shape(&builtin);
+            for ty in &program.type_definitions {
+                if ty.name() != builtin.name() || shape(ty) == expected {
+                    continue;
                 }
-                Statement::Return(_) => todo!(),
+                let variants = expected
+                    .iter()
+                    .map(|(name, fields)| match fields {
+                        0 => format!("`{name}`"),
+                        _ => format!("`{name}({})`", vec!["_"; *fields].join(", ")),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" and ");
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "`{}` doesn't have the variants of the builtin type it replaces",
+                        ty.name()
+                    ))
+                    .with_code(codes::INCOMPATIBLE_BUILTIN)
+                    .with_primary(ty.span(), format!("expected {variants}"))
+                    .with_note(
+                        "builtin functions build these variants; give the type another name",
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Infers the principal type of every function implemented without a
+    /// declaration, and returns declarations for those whose type turned out
+    /// to be monomorphic, so later stages can treat them as declared.
+    fn infer_undeclared_functions(&mut self, program: &mut Program) -> Vec<FunctionDeclaration> {
+        let mut names = vec![];
+        let mut clauses: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, fi) in program.function_implementations.iter().enumerate() {
+            let name = fi.qualified_name().qualified();
+            if self.functions.contains_key(&name) {
+                continue;
+            }
+            if !clauses.contains_key(&name) {
+                names.push(name.clone());
             }
+            clauses.entry(name).or_default().push(i);
         }
 
-        self.infer_expr(&mut program.entry_point.return_expr);
+        let edges = clauses
+            .iter()
+            .map(|(name, indices)| {
+                let references = indices
+                    .iter()
+                    .flat_map(|&i| deps::references(program.function_implementations[i].body()))
+                    .flat_map(|reference| {
+                        deps::resolve(&reference, clauses.keys())
+                            .into_iter()
+                            .filter(|name| clauses.contains_key(*name))
+                            .map(str::to_owned)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+                (name.clone(), references)
+            })
+            .collect();
+
+        let mut declarations = vec![];
+        for group in deps::binding_groups(&names, &edges) {
+            let types: Vec<Ty> = group.iter().map(|_| self.fresh()).collect();
+            for (name, ty) in group.iter().zip(&types) {
+                self.functions
+                    .insert(name.clone(), Scheme::mono(ty.clone()));
+            }
+            for (name, ty) in group.iter().zip(&types) {
+                for &i in &clauses[name] {
+                    self.infer_clause(&mut program.function_implementations[i], ty);
+                }
+            }
+            self.solve();
+            for ty in &types {
+                self.default_literals(ty);
+            }
+
+            // The group's own (monomorphic) types mustn't keep their
+            // variables from being generalised.
+            for name in &group {
+                self.functions.remove(name);
+            }
+            for (name, ty) in group.iter().zip(&types) {
+                let scheme = self.generalize(ty);
+                let fi = &program.function_implementations[clauses[name][0]];
+                if scheme.vars.is_empty()
+                    && let Some(Type::Function(signature)) = scheme.ty.to_type()
+                {
+                    declarations.push(
+                        FunctionDeclaration::new(fi.qualified_name().clone(), vec![], signature)
+                            .with_span(fi.span()),
+                    );
+                } else {
+                    let parameters = vec!["A"; fi.arguments().len()].join(", ");
+                    // Generic functions are compiled once, from their
+                    // declaration, which gives their type parameters and bounds.
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "cannot compile polymorphic function `{name}` without a declaration"
+                        ))
+                        .with_code(codes::CANNOT_INFER)
+                        .with_primary(fi.span(), format!("its type is `{}`", scheme.ty))
+                        .with_note(format!(
+                            "declare its signature, e.g. `{name}<A>({parameters}) -> A;`"
+                        )),
+                    );
+                }
+                self.functions.insert(name.clone(), scheme);
+            }
+        }
+        declarations
+    }
+
+    /// Declares the functions of the program, each once, and none named
+    /// like a builtin one.
+    fn declare_functions(&mut self, program: &Program) {
+        let mut declared: BTreeMap<String, Span> = BTreeMap::new();
+        for fd in &program.function_declarations {
+            let name = fd.qualified_name().qualified();
+            if let Some(&first) = declared.get(&name) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("function `{name}` is declared more than once"))
+                        .with_code(codes::DUPLICATE_DECLARATION)
+                        .with_primary(fd.span(), "declared again here")
+                        .with_secondary(first, "first declared here"),
+                );
+                continue;
+            }
+            if self.functions.contains_key(&name) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("function `{name}` is a builtin function"))
+                        .with_code(codes::DUPLICATE_DECLARATION)
+                        .with_primary(fd.span(), "declared here")
+                        .with_note("give the function another name"),
+                );
+                continue;
+            }
+            declared.insert(name.clone(), fd.span());
+            let scheme = self.declared_scheme(fd);
+            self.functions.insert(name, scheme);
+        }
+    }
+
+    /// Reports the functions the program declares but never implements,
+    /// which couldn't be linked.
+    fn check_implemented_functions(&mut self, program: &Program) {
+        let implemented: BTreeSet<String> = program
+            .function_implementations
+            .iter()
+            .map(|fi| fi.qualified_name().qualified())
+            .collect();
+        for fd in &program.function_declarations {
+            let name = fd.qualified_name().qualified();
+            if implemented.contains(&name) {
+                continue;
+            }
+            let parameters = vec!["_"; fd.signature().arguments().len()].join(", ");
+            self.diagnostics.push(
+                Diagnostic::error(format!("function `{name}` is declared but not implemented"))
+                    .with_code(codes::MISSING_IMPLEMENTATION)
+                    .with_primary(fd.span(), "declared here")
+                    .with_note(format!("implement it: `{name}({parameters}) = ...;`")),
+            );
+        }
+    }
+
+    /// Checks the body of every function implemented with a declaration
+    /// against its signature.
+    fn check_declared_functions(&mut self, program: &mut Program) {
+        for fi in &mut program.function_implementations {
+            let name = fi.qualified_name().qualified();
+            let Some(fd) = program
+                .function_declarations
+                .iter()
+                .find(|fd| fd.qualified_name().qualified() == name)
+            else {
+                continue;
+            };
+            let signature = fd.signature();
+            let expected = signature.arguments().len();
+            if expected != fi.arguments().len() {
+                self.report(
+                    Diagnostic::error(format!(
+                        "function `{name}` is declared with {expected} parameter{} but implemented with {}",
+                        if expected == 1 { "" } else { "s" },
+                        fi.arguments().len(),
+                    ))
+                    .with_code(codes::WRONG_ARGUMENT_COUNT)
+                    .with_primary(fi.span(), "wrong number of parameters")
+                    .with_secondary(fd.span(), "declared here"),
+                );
+                continue;
+            }
+            // Inside the body, type parameters are rigid: `A` is only equal
+            // to itself, since callers may pick any type for it.
+            let params = fd
+                .type_params()
+                .iter()
+                .map(|param| (param.clone(), Ty::con(param)))
+                .collect();
+            let arguments: Vec<Ty> = signature
+                .arguments()
+                .iter()
+                .map(|arg| Ty::from_type(arg, &params))
+                .collect();
+            let return_type = Ty::from_type(signature.return_type(), &params);
+            self.assumed = (fd.type_params().to_vec(), fd.bounds().to_vec());
+            self.check_clause(fi, &arguments, return_type, Some(fd.span()));
+            self.assumed = Default::default();
+            self.solve();
+        }
+    }
+
+    /// Infers one clause of a function whose type is `function_type`.
+    fn infer_clause(&mut self, fi: &mut FunctionImplementation, function_type: &Ty) {
+        let arguments: Vec<Ty> = fi.arguments().iter().map(|_| self.fresh()).collect();
+        let return_type = self.fresh();
+        self.constrain(
+            function_type.clone(),
+            Ty::Fun(arguments.clone(), Box::new(return_type.clone())),
+            fi.span(),
+            None,
+        );
+        self.check_clause(fi, &arguments, return_type, None);
     }
 
-    pub fn infer_expr(&self, expr: &mut Expression) -> Type {
+    /// Binds the parameters of `fi` to `arguments` and checks that its body
+    /// returns `return_type`, which is declared at `because`, if anywhere.
+    fn check_clause(
+        &mut self,
+        fi: &mut FunctionImplementation,
+        arguments: &[Ty],
+        return_type: Ty,
+        because: Option<Span>,
+    ) {
+        self.scopes.push(BTreeMap::new());
+        for (pattern, ty) in fi.arguments().iter().zip(arguments) {
+            self.infer_pattern(pattern, ty, because);
+        }
+
+        let outer = self.return_type.replace(return_type.clone());
+        let fallback = fi.span();
+        let (ty, span) = match fi.body_mut() {
+            FunctionBody::SingleLine(expr) => (self.infer_expr(expr), expr.span()),
+            FunctionBody::MultiLine(block) => {
+                (self.infer_block(block), block_span(block, fallback))
+            }
+        };
+        self.constrain(return_type, ty, span, because);
+        self.return_type = outer;
+        self.scopes.pop();
+    }
+
+    fn infer_entry_point(&mut self, block: &mut Block) {
+        let return_type = self.fresh();
+        self.return_type = Some(return_type.clone());
+        let ty = self.infer_block(block);
+        let span = block.return_expr.span();
+        self.constrain(return_type, ty, span, None);
+        self.return_type = None;
+        self.solve();
+    }
+
+    fn infer_block(&mut self, block: &mut Block) -> Ty {
+        self.scopes.push(BTreeMap::new());
+        for statement in &mut block.statements {
+            match &mut statement.kind {
+                StatementKind::Assignment(name, annotation, expr) => {
+                    let ty = self.infer_expr(expr);
+                    if let Some(annotation) = annotation {
+                        // Type parameters are rigid in the body, like in its
+                        // signature.
+                        let params = self
+                            .assumed
+                            .0
+                            .iter()
+                            .map(|param| (param.clone(), Ty::con(param)))
+                            .collect();
+                        let expected = Ty::from_type(annotation, &params);
+                        self.constrain(expected, ty.clone(), expr.span(), None);
+                    }
+                    self.solve();
+                    // A function value is compiled once, for the types of its
+                    // first uses, so it's never generalised.
+                    let scheme = match self.substitution.apply(&ty) {
+                        ty @ Ty::Fun(..) => Scheme::mono(ty),
+                        _ => self.generalize(&ty),
+                    };
+                    self.bind(name, scheme);
+                }
+                StatementKind::Return(expr) => {
+                    let ty = self.infer_expr(expr);
+                    let expected = self.return_type.clone().unwrap_or(Ty::Error);
+                    self.constrain(expected, ty, expr.span(), None);
+                }
+            }
+        }
+        // A block ending in `return` doesn't give a value, so it fits
+        // wherever it is.
+        let ty = match block.ends_in_return() {
+            true => {
+                let ty = self.fresh();
+                self.annotate(&mut block.return_expr, &ty);
+                ty
+            }
+            false => self.infer_expr(&mut block.return_expr),
+        };
+        self.scopes.pop();
+        ty
+    }
+
+    fn infer_expr(&mut self, expr: &mut Expression) -> Ty {
+        let span = expr.span();
+        let mut resolved = None;
         let ty = match expr.kind_mut() {
-            ExpressionKind::Identifier(var_name) => {
-                self.variables.get(var_name).unwrap().to_owned()
-            }
-            ExpressionKind::Literal(lit) => match lit {
-                Literal::Integer(_) => Type::U8,
-                Literal::Float(_) => Type::F64,
-                Literal::Bool(_) => Type::Bool,
-                Literal::String(_) => Type::String,
-            },
-            ExpressionKind::BinaryOp(lhs, _, rhs) => self.infer_binary_op(lhs, rhs),
-            ExpressionKind::UnaryOp(_, expr) => self.infer_expr(expr),
-            ExpressionKind::Unit => Type::Unit,
+            ExpressionKind::Identifier(name) => self.infer_identifier(name, span),
+            ExpressionKind::Literal(lit) => self.literal_type(lit, span),
+            ExpressionKind::BinaryOp(lhs, op, rhs) => self.infer_binary_op(lhs, op, rhs),
+            ExpressionKind::UnaryOp(op, operand) => self.infer_unary_op(op, operand),
+            ExpressionKind::Unit => Ty::con("Unit"),
+            ExpressionKind::Interpolation(parts) => {
+                for part in parts {
+                    if let StringPart::Hole(hole) = part {
+                        let ty = self.infer_expr(hole);
+                        self.holes.push((ty, hole.span()));
+                    }
+                }
+                Ty::con("String")
+            }
             ExpressionKind::FunctionCall {
-                namespace, name, ..
+                namespace,
+                name,
+                arguments,
+            } => self.infer_call(namespace.as_deref(), name, arguments, span, None),
+            ExpressionKind::MethodCall {
+                receiver,
+                name,
+                arguments,
             } => {
-                let qualified = namespace
-                    .as_ref()
-                    .map_or_else(|| name.clone(), |ns| format!("{ns}::{name}"));
-                self.functions
-                    .get(&qualified)
-                    .map(|s| s.return_type().to_owned())
-                    .unwrap()
+                let (call, ty) = self.infer_method_call(receiver, name, arguments, span);
+                resolved = call;
+                ty
+            }
+            ExpressionKind::Match(scrutinee, arms) => self.infer_match(scrutinee, arms),
+            ExpressionKind::If(condition, then, otherwise) => {
+                self.infer_if(condition, then, otherwise, span)
+            }
+            ExpressionKind::List(elements) => self.infer_list(elements),
+            ExpressionKind::Lambda(params, body) => self.infer_lambda(params, body, None),
+            ExpressionKind::Tuple(elements) => Ty::tuple(
+                elements
+                    .iter_mut()
+                    .map(|element| self.infer_expr(element))
+                    .collect(),
+            ),
+            ExpressionKind::NewEnumInstance(enum_name, variant_name, args) => {
+                self.infer_new_enum_instance(enum_name, variant_name, args, span)
             }
-            ExpressionKind::Match(expr, arms) => self.infer_match(expr, arms),
             ExpressionKind::NewRecordInstance(type_name, fields) => {
-                self.infer_new_record_instance(type_name, fields)
+                self.infer_new_record_instance(type_name, fields, span)
             }
             ExpressionKind::RecordAccess(var_name, field_name) => {
-                self.infer_record_access(var_name, field_name)
+                self.infer_record_access(var_name, field_name, span)
             }
-            _ => todo!("Expression {expr:?} is not implemented yet"),
         };
-        expr.set_type(AssociatedType::Concrete(ty.to_owned()));
+        if let Some(call) = resolved {
+            expr.kind = call;
+        }
+        self.annotate(expr, &ty);
         ty
     }
 
-    fn infer_binary_op(&self, lhs: &mut Expression, rhs: &mut Expression) -> Type {
-        let lhs_type = self.infer_expr(lhs);
-        let rhs_type = self.infer_expr(rhs);
-        if lhs_type != rhs_type {
-            panic!("type mismatch")
+    fn infer_identifier(&mut self, name: &str, span: Span) -> Ty {
+        if let Some(scheme) = self.get_variable(name).or_else(|| self.functions.get(name)) {
+            let scheme = scheme.clone();
+            return self.instantiate(&scheme, span);
         }
-        lhs_type
+        self.report(
+            Diagnostic::error(format!("cannot find variable `{name}`"))
+                .with_code(codes::UNKNOWN_VARIABLE)
+                .with_primary(span, "not found in this scope"),
+        )
     }
 
-    fn infer_match(&self, expr: &mut Expression, arms: &[MatchArm]) -> Type {
-        self.infer_expr(expr);
-        let arms: Vec<_> = arms
-            .iter()
-            .map(|arm| match &arm.body {
-                MatchBody::Block(block) => block.return_expr.associated_type.to_owned(),
-                MatchBody::Expr(expr) => expr.associated_type.to_owned(),
+    /// The type of a call to `name`, whose first argument is already
+    /// inferred to be a `receiver` when it's a method call.
+    fn infer_call(
+        &mut self,
+        namespace: Option<&str>,
+        name: &str,
+        arguments: &mut [Expression],
+        span: Span,
+        mut receiver: Option<Ty>,
+    ) -> Ty {
+        let qualified = namespace.map_or_else(|| name.to_owned(), |ns| format!("{ns}::{name}"));
+        // A local variable holding a function shadows a function of the same name.
+        let variable = match namespace {
+            None => self.get_variable(name),
+            Some(_) => None,
+        };
+        // Declared functions can be applied partially: `add(1)` is the
+        // function adding 1.
+        let is_variable = variable.is_some();
+        let curried = !is_variable && namespace.is_none();
+        let callee = variable
+            .or_else(|| self.functions.get(&qualified))
+            .cloned()
+            .map(|scheme| self.instantiate(&scheme, span));
+        // Like the arithmetic operators, the `Int::*` builtins only apply to
+        // integers.
+        if namespace == Some("Int")
+            && !is_variable
+            && let Some(Ty::Fun(params, _)) = &callee
+            && let (Some(param), Some(arg)) = (params.first(), arguments.first())
+            && FunctionDeclaration::builtins()
+                .iter()
+                .any(|fd| fd.qualified_name().qualified() == qualified)
+        {
+            self.operands.push(Operand {
+                operator: qualified.clone(),
+                applies_to: Applicable::Integers,
+                ty: param.clone(),
+                span: arg.span(),
+            });
+        }
+        self.solve();
+        if let Some(Ty::Fun(mut params, return_type)) =
+            callee.as_ref().map(|c| self.substitution.apply(c))
+            && (params.len() == arguments.len() || curried && arguments.len() < params.len())
+        {
+            let rest = params.split_off(arguments.len());
+            // Each argument is checked before the next one is inferred, so a
+            // lambda's parameters get their types from the arguments before
+            // it: in `List::map(people, (p) -> p.age)`, `p` is a record.
+            for (param, arg) in params.into_iter().zip(arguments) {
+                let ty = match receiver.take() {
+                    Some(ty) => ty,
+                    None => self.infer_argument(arg, &param),
+                };
+                self.constrain(param, ty, arg.span(), None);
+                self.solve();
+            }
+            return match rest.is_empty() {
+                true => *return_type,
+                false => Ty::Fun(rest, return_type),
+            };
+        }
+        let args: Vec<(Ty, Span)> = arguments
+            .iter_mut()
+            .map(|arg| {
+                let ty = receiver.take().unwrap_or_else(|| self.infer_expr(arg));
+                (ty, arg.span())
             })
             .collect();
-        if arms.iter().all(|x| x == &arms[0]) {
-            arms[0]
-                .to_owned()
-                .and_then(|t| match t {
-                    AssociatedType::Concrete(ty) => Some(ty),
-                    AssociatedType::Unknown(_) => None,
-                })
-                .unwrap()
+
+        let Some(callee) = callee else {
+            return self.report(
+                Diagnostic::error(format!("cannot find function `{qualified}`"))
+                    .with_code(codes::UNKNOWN_FUNCTION)
+                    .with_primary(span, "not declared"),
+            );
+        };
+        self.solve();
+        match self.substitution.apply(&callee) {
+            Ty::Fun(params, return_type) => {
+                if params.len() != args.len() {
+                    self.report(
+                        Diagnostic::error(format!(
+                            "function `{qualified}` takes {} argument{} but {} were supplied",
+                            params.len(),
+                            if params.len() == 1 { "" } else { "s" },
+                            args.len()
+                        ))
+                        .with_code(codes::WRONG_ARGUMENT_COUNT)
+                        .with_primary(span, format!("`{qualified}` is `{callee}`")),
+                    );
+                } else {
+                    for (param, (arg, arg_span)) in params.into_iter().zip(args) {
+                        self.constrain(param, arg, arg_span, None);
+                    }
+                }
+                *return_type
+            }
+            Ty::Var(_) => {
+                let return_type = self.fresh();
+                let params = args.into_iter().map(|(ty, _)| ty).collect();
+                self.constrain(
+                    callee,
+                    Ty::Fun(params, Box::new(return_type.clone())),
+                    span,
+                    None,
+                );
+                return_type
+            }
+            Ty::Error => Ty::Error,
+            ty => self.report(
+                Diagnostic::error(format!("`{qualified}` is not a function"))
+                    .with_code(codes::NOT_A_FUNCTION)
+                    .with_primary(span, format!("`{qualified}` is `{ty}`")),
+            ),
+        }
+    }
+
+    /// `receiver.name(arguments)` is `Type::name(receiver, arguments)`,
+    /// where `Type` is the type of the receiver, or the trait declaring
+    /// `name` it implements. Gives the call it stands for, once resolved.
+    fn infer_method_call(
+        &mut self,
+        receiver: &mut Expression,
+        name: &str,
+        arguments: &mut Vec<Expression>,
+        span: Span,
+    ) -> (Option<ExpressionKind>, Ty) {
+        let receiver_type = self.infer_expr(receiver);
+        self.solve();
+        let Some(namespace) = self.method_namespace(&receiver_type, name, receiver.span()) else {
+            for arg in arguments.iter_mut() {
+                self.infer_expr(arg);
+            }
+            return (None, Ty::Error);
+        };
+        let mut args = vec![std::mem::replace(receiver, Expression::unit())];
+        args.append(arguments);
+        let ty = self.infer_call(Some(&namespace), name, &mut args, span, Some(receiver_type));
+        let call = ExpressionKind::FunctionCall {
+            namespace: Some(namespace),
+            name: name.to_owned(),
+            arguments: args,
+        };
+        (Some(call), ty)
+    }
+
+    /// The namespace of the function `name` called on a `receiver`.
+    fn method_namespace(&mut self, receiver: &Ty, name: &str, span: Span) -> Option<String> {
+        let receiver = self.substitution.apply(receiver);
+        let type_name = match &receiver {
+            Ty::Error => return None,
+            Ty::Var(_) => {
+                self.report(
+                    Diagnostic::error(format!(
+                        "cannot infer the type `.{name}` is called on"
+                    ))
+                    .with_code(codes::CANNOT_INFER)
+                    .with_primary(span, "type is `_`")
+                    .with_note(format!(
+                        "declare the function's signature, or call it by its full name: `Type::{name}(...)`"
+                    )),
+                );
+                return None;
+            }
+            Ty::Con(type_name, _) if type_name == LIST => "List",
+            Ty::Con(type_name, _) if type_name == TUPLE => "",
+            Ty::Con(type_name, _) => type_name,
+            Ty::Integer(_) => "Int",
+            Ty::Float(_) | Ty::Fun(..) => "",
+        };
+        let is_integer = matches!(receiver, Ty::Integer(_))
+            || type_name.parse::<Type>().is_ok_and(|ty| ty.is_integer());
+        let namespaces = [type_name, if is_integer { "Int" } else { "" }];
+        if let Some(namespace) = namespaces
+            .into_iter()
+            .find(|ns| !ns.is_empty() && self.functions.contains_key(&format!("{ns}::{name}")))
+        {
+            return Some(namespace.to_owned());
+        }
+        let traits = self.traits_declaring(name, type_name);
+        match traits.as_slice() {
+            [trait_name] => return Some(trait_name.clone()),
+            [] => {}
+            _ => {
+                self.report(
+                    Diagnostic::error(format!(
+                        "`.{name}` is a method of several traits `{receiver}` implements"
+                    ))
+                    .with_code(codes::CANNOT_INFER)
+                    .with_primary(
+                        span,
+                        format!("`{receiver}` implements `{}`", traits.join("` and `")),
+                    )
+                    .with_note(format!(
+                        "call it by its full name: `{}::{name}(...)`",
+                        traits[0]
+                    )),
+                );
+                return None;
+            }
+        }
+        self.report(
+            Diagnostic::error(format!("no function `{name}` for `{receiver}`"))
+                .with_code(codes::UNKNOWN_FUNCTION)
+                .with_primary(span, format!("`.{name}` called on `{receiver}`"))
+                .with_note(match type_name {
+                    "" => "only named types, like `String` or `Option`, have functions".to_owned(),
+                    _ => format!("declare it: `{type_name}::{name}({type_name}) -> ...;`"),
+                }),
+        );
+        None
+    }
+
+    /// [`Self::infer_expr`] for an argument of type `expected`, which gives
+    /// the parameters of a lambda their types.
+    fn infer_argument(&mut self, arg: &mut Expression, expected: &Ty) -> Ty {
+        let ExpressionKind::Lambda(params, body) = arg.kind_mut() else {
+            return self.infer_expr(arg);
+        };
+        let ty = self.infer_lambda(params, body, Some(expected));
+        self.annotate(arg, &ty);
+        ty
+    }
+
+    /// A lambda's parameters are monomorphic, like those of a function
+    /// clause. Their types are taken from `expected` when it's a function
+    /// type of the same arity.
+    fn infer_lambda(
+        &mut self,
+        params: &[String],
+        body: &mut Expression,
+        expected: Option<&Ty>,
+    ) -> Ty {
+        let param_types: Vec<Ty> = match expected.map(|ty| self.substitution.apply(ty)) {
+            Some(Ty::Fun(types, _)) if types.len() == params.len() => types,
+            _ => params.iter().map(|_| self.fresh()).collect(),
+        };
+        self.scopes.push(BTreeMap::new());
+        for (param, ty) in params.iter().zip(&param_types) {
+            self.bind(param, Scheme::mono(ty.clone()));
+        }
+        // A `return` in the body returns from the lambda.
+        let return_type = self.fresh();
+        let outer = self.return_type.replace(return_type.clone());
+        let ty = self.infer_expr(body);
+        self.constrain(return_type.clone(), ty, body.span(), None);
+        self.return_type = outer;
+        self.scopes.pop();
+        Ty::Fun(param_types, Box::new(return_type))
+    }
+
+    fn infer_match(&mut self, scrutinee: &mut Expression, arms: &mut [MatchArm]) -> Ty {
+        let scrutinee_type = self.infer_expr(scrutinee);
+        let result = self.fresh();
+        let mut first_arm = None;
+        for arm in arms {
+            self.scopes.push(BTreeMap::new());
+            self.infer_pattern(&arm.pattern, &scrutinee_type, Some(scrutinee.span()));
+            let fallback = arm.pattern.span();
+            let (ty, span) = match &mut arm.body {
+                MatchBody::Expr(body) => (self.infer_expr(body), body.span()),
+                MatchBody::Block(block) => (self.infer_block(block), block_span(block, fallback)),
+            };
+            self.constrain(result.clone(), ty, span, first_arm);
+            first_arm.get_or_insert(span);
+            self.scopes.pop();
+        }
+        result
+    }
+
+    /// The condition is a `Bool`, and both branches have the same type.
+    fn infer_if(
+        &mut self,
+        condition: &mut Expression,
+        then: &mut Block,
+        otherwise: &mut Block,
+        span: Span,
+    ) -> Ty {
+        let ty = self.infer_expr(condition);
+        self.constrain(Ty::con("Bool"), ty, condition.span(), None);
+        let result = self.infer_block(then);
+        let ty = self.infer_block(otherwise);
+        let because = block_span(then, span);
+        self.constrain(
+            result.clone(),
+            ty,
+            block_span(otherwise, span),
+            Some(because),
+        );
+        result
+    }
+
+    /// Every element must have the type of the first.
+    fn infer_list(&mut self, elements: &mut [Expression]) -> Ty {
+        let element = self.fresh();
+        let mut first = None;
+        for expr in elements {
+            let ty = self.infer_expr(expr);
+            self.constrain(element.clone(), ty, expr.span(), first);
+            first.get_or_insert(expr.span());
+        }
+        Ty::list(element)
+    }
+
+    /// Checks `pattern` against the type of the value it matches, binding
+    /// the variables it introduces in the innermost scope.
+    fn infer_pattern(&mut self, pattern: &Pattern, expected: &Ty, because: Option<Span>) {
+        match &pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Identifier(name) => self.bind(name, Scheme::mono(expected.clone())),
+            PatternKind::Literal(lit) => {
+                let ty = self.literal_type(lit, pattern.span());
+                self.constrain(expected.clone(), ty, pattern.span(), because);
+            }
+            PatternKind::EnumInstance {
+                enum_name,
+                variant_name,
+                args,
+            } => {
+                let payload = match self.enum_variant(enum_name, variant_name, pattern.span()) {
+                    Ok((ty, payload)) => {
+                        self.constrain(expected.clone(), ty, pattern.span(), because);
+                        if payload.len() != args.len() {
+                            self.report(wrong_payload_count(
+                                enum_name,
+                                variant_name,
+                                payload.len(),
+                                args.len(),
+                                pattern.span(),
+                            ));
+                        }
+                        payload
+                    }
+                    Err(diagnostic) => {
+                        self.report(diagnostic);
+                        vec![]
+                    }
+                };
+                for (i, arg) in args.iter().enumerate() {
+                    let ty = payload.get(i).cloned().unwrap_or(Ty::Error);
+                    self.infer_pattern(arg, &ty, None);
+                }
+            }
+            PatternKind::EmptyList => {
+                let list = Ty::list(self.fresh());
+                self.constrain(expected.clone(), list, pattern.span(), because);
+            }
+            PatternKind::Cons { head, tail } => {
+                let element = self.fresh();
+                let list = Ty::list(element.clone());
+                self.constrain(expected.clone(), list.clone(), pattern.span(), because);
+                self.infer_pattern(head, &element, None);
+                self.infer_pattern(tail, &list, None);
+            }
+            PatternKind::Tuple(elements) => {
+                let types: Vec<_> = elements.iter().map(|_| self.fresh()).collect();
+                let tuple = Ty::tuple(types.clone());
+                self.constrain(expected.clone(), tuple, pattern.span(), because);
+                for (element, ty) in elements.iter().zip(&types) {
+                    self.infer_pattern(element, ty, None);
+                }
+            }
+            PatternKind::Record {
+                type_name,
+                fields,
+                rest,
+            } => self.infer_record_pattern(
+                type_name,
+                fields,
+                *rest,
+                expected,
+                pattern.span(),
+                because,
+            ),
+        }
+    }
+
+    /// Checks the fields of a `type_name { fields }` pattern, matching a value
+    /// of type `expected`.
+    fn infer_record_pattern(
+        &mut self,
+        type_name: &str,
+        fields: &[(String, Pattern)],
+        rest: bool,
+        expected: &Ty,
+        span: Span,
+        because: Option<Span>,
+    ) {
+        let field_types = match self.record_fields(type_name, span) {
+            Ok((ty, field_types)) => {
+                self.constrain(expected.clone(), ty, span, because);
+                field_types
+            }
+            Err(diagnostic) => {
+                self.report(diagnostic);
+                BTreeMap::new()
+            }
+        };
+        for (i, (name, pattern)) in fields.iter().enumerate() {
+            let ty = match field_types.get(name) {
+                Some(ty) => ty.clone(),
+                None if field_types.is_empty() => Ty::Error,
+                None => self.report(
+                    Diagnostic::error(format!("record `{type_name}` has no field `{name}`"))
+                        .with_code(codes::UNKNOWN_FIELD)
+                        .with_primary(pattern.span(), "unknown field"),
+                ),
+            };
+            if let Some((_, first)) = fields[..i].iter().find(|(field, _)| field == name) {
+                self.report(
+                    Diagnostic::error(format!("field `{name}` is matched more than once"))
+                        .with_code(codes::DUPLICATE_FIELD)
+                        .with_primary(pattern.span(), "matched again")
+                        .with_secondary(first.span(), "first matched here"),
+                );
+            }
+            self.infer_pattern(pattern, &ty, None);
+        }
+        if rest {
+            return;
+        }
+        for name in field_types.keys() {
+            if !fields.iter().any(|(field, _)| field == name) {
+                self.report(
+                    Diagnostic::error(format!("missing field `{name}` in `{type_name}` pattern"))
+                        .with_code(codes::MISSING_FIELD)
+                        .with_primary(span, format!("`{name}` not matched"))
+                        .with_note("add `..` to ignore the fields left out"),
+                );
+            }
+        }
+    }
+
+    fn infer_new_enum_instance(
+        &mut self,
+        enum_name: &str,
+        variant_name: &str,
+        args: &mut [Expression],
+        span: Span,
+    ) -> Ty {
+        let args: Vec<(Ty, Span)> = args
+            .iter_mut()
+            .map(|arg| (self.infer_expr(arg), arg.span()))
+            .collect();
+        let (ty, payload) = match self.enum_variant(enum_name, variant_name, span) {
+            Ok(variant) => variant,
+            Err(diagnostic) => return self.report(diagnostic),
+        };
+        if payload.len() != args.len() {
+            self.report(wrong_payload_count(
+                enum_name,
+                variant_name,
+                payload.len(),
+                args.len(),
+                span,
+            ));
         } else {
-            panic!("type mismatch in match arms")
+            for (expected, (found, arg_span)) in payload.into_iter().zip(args) {
+                self.constrain(expected, found, arg_span, None);
+            }
         }
+        ty
     }
 
     fn infer_new_record_instance(
-        &self,
+        &mut self,
         type_name: &str,
         fields: &mut [(String, Expression)],
-    ) -> Type {
-        let ty = self
-            .types
+        span: Span,
+    ) -> Ty {
+        let values: Vec<(Ty, Span)> = fields
+            .iter_mut()
+            .map(|(_, value)| (self.infer_expr(value), value.span()))
+            .collect();
+        let (ty, field_types) = match self.record_fields(type_name, span) {
+            Ok(record) => record,
+            Err(diagnostic) => return self.report(diagnostic),
+        };
+        for ((name, _), (found, value_span)) in fields.iter().zip(values) {
+            match field_types.get(name) {
+                Some(expected) => self.constrain(expected.clone(), found, value_span, None),
+                None => {
+                    self.report(
+                        Diagnostic::error(format!("record `{type_name}` has no field `{name}`"))
+                            .with_code(codes::UNKNOWN_FIELD)
+                            .with_primary(value_span, "unknown field"),
+                    );
+                }
+            }
+        }
+        for name in field_types.keys() {
+            if !fields.iter().any(|(field, _)| field == name) {
+                self.report(
+                    Diagnostic::error(format!("missing field `{name}` in `{type_name}`"))
+                        .with_code(codes::MISSING_FIELD)
+                        .with_primary(span, format!("`{name}` not initialized")),
+                );
+            }
+        }
+        ty
+    }
+
+    fn infer_record_access(&mut self, var_name: &str, field_name: &str, span: Span) -> Ty {
+        let ty = self.infer_identifier(var_name, span);
+        // The record's type has to be known to find the field.
+        self.solve();
+        let (name, args) = match self.substitution.apply(&ty) {
+            Ty::Con(name, args) => (name, args),
+            Ty::Error => return Ty::Error,
+            Ty::Var(_) => {
+                return self.report(
+                    Diagnostic::error(format!("cannot infer the type of `{var_name}`"))
+                        .with_code(codes::CANNOT_INFER)
+                        .with_primary(span, format!("type must be known to access `{field_name}`")),
+                );
+            }
+            ty => {
+                return self.report(
+                    Diagnostic::error(format!("`{var_name}` is not a record"))
+                        .with_code(codes::NOT_A_RECORD)
+                        .with_primary(span, format!("`{var_name}` is `{ty}`")),
+                );
+            }
+        };
+        let (record, field_types) = match self.record_fields(&name, span) {
+            Ok(record) => record,
+            Err(diagnostic) => return self.report(diagnostic),
+        };
+        self.constrain(record, Ty::Con(name.clone(), args), span, None);
+        match field_types.get(field_name) {
+            Some(field) => field.clone(),
+            None => self.report(
+                Diagnostic::error(format!("record `{name}` has no field `{field_name}`"))
+                    .with_code(codes::UNKNOWN_FIELD)
+                    .with_primary(span, "unknown field"),
+            ),
+        }
+    }
+
+    /// The type of a fresh instance of enum `enum_name` and the payload types
+    /// of its variant `variant_name`.
+    fn enum_variant(
+        &mut self,
+        enum_name: &str,
+        variant_name: &str,
+        span: Span,
+    ) -> Result<(Ty, Vec<Ty>), Diagnostic> {
+        let ct = self.get_type(enum_name, span)?.clone();
+        let variants = ct.get_enum_variants().ok_or_else(|| {
+            Diagnostic::error(format!("`{enum_name}` is not an enum"))
+                .with_code(codes::NOT_AN_ENUM)
+                .with_primary(span, "has no variants")
+        })?;
+        let (_, variant) = variants
             .iter()
-            .find(|t| t.name() == type_name)
-            .expect("Type not found");
-        let original_fields = ty.get_record_fields().expect("Type is not a record");
-        original_fields.iter().for_each(|f| {
-            let (_, expr) = fields
-                .iter_mut()
-                .find(|(field_name, _)| f.name() == field_name)
-                .expect("Field not found");
-            expr.set_type(AssociatedType::Concrete(f.ty().to_owned()));
+            .find(|(_, v)| v.name() == variant_name)
+            .ok_or_else(|| {
+                Diagnostic::error(format!("no variant `{variant_name}` on enum `{enum_name}`"))
+                    .with_code(codes::UNKNOWN_VARIANT)
+                    .with_primary(span, "unknown variant")
+            })?;
+        let (ty, params) = self.instantiate_type(&ct);
+        let payload = variant
+            .types()
+            .iter()
+            .map(|ty| Ty::from_type(ty, &params))
+            .collect();
+        Ok((ty, payload))
+    }
+
+    /// The type of a fresh instance of record `type_name` and the types of
+    /// its fields.
+    fn record_fields(
+        &mut self,
+        type_name: &str,
+        span: Span,
+    ) -> Result<(Ty, BTreeMap<String, Ty>), Diagnostic> {
+        let ct = self.get_type(type_name, span)?.clone();
+        let fields = ct.get_record_fields().ok_or_else(|| {
+            Diagnostic::error(format!("`{type_name}` is not a record"))
+                .with_code(codes::NOT_A_RECORD)
+                .with_primary(span, "has no fields")
+        })?;
+        let (ty, params) = self.instantiate_type(&ct);
+        let fields = fields
+            .iter()
+            .map(|f| (f.name().to_owned(), Ty::from_type(f.ty(), &params)))
+            .collect();
+        Ok((ty, fields))
+    }
+
+    /// A custom type with fresh variables for its type parameters.
+    fn instantiate_type(&mut self, ct: &CustomType) -> (Ty, BTreeMap<String, Ty>) {
+        let params: BTreeMap<String, Ty> = ct
+            .generics()
+            .iter()
+            .map(|generic| (generic.clone(), self.fresh()))
+            .collect();
+        let args = ct.generics().iter().map(|g| params[g].clone()).collect();
+        (Ty::Con(ct.name().to_owned(), args), params)
+    }
+
+    fn literal_type(&mut self, lit: &Literal, span: Span) -> Ty {
+        let ty = match lit {
+            Literal::Integer(_) => Ty::Integer(self.fresh_var()),
+            Literal::Float(_) => Ty::Float(self.fresh_var()),
+            Literal::Bool(_) => return Ty::con("Bool"),
+            Literal::String(_) => return Ty::con("String"),
+        };
+        self.literals.push(NumberLiteral {
+            literal: lit.clone(),
+            negative: false,
+            ty: ty.clone(),
+            span,
         });
-        Type::Custom(type_name.to_owned(), vec![])
+        ty
+    }
+
+    /// Gives the literals in `ty` whose type is still unknown their default
+    /// type. Constraints must be solved first.
+    fn default_literals(&mut self, ty: &Ty) {
+        for literal in self.substitution.apply(ty).literals() {
+            let (var, default) = match literal {
+                Ty::Integer(var) => (var, "I32"),
+                Ty::Float(var) => (var, "F64"),
+                _ => continue,
+            };
+            self.substitution.bind(var, Ty::con(default));
+        }
+    }
+
+    /// Defaults the type of every literal still unknown, and reports those
+    /// that don't fit in their type.
+    fn check_literals(&mut self) {
+        for literal in std::mem::take(&mut self.literals) {
+            self.default_literals(&literal.ty);
+            let Some(ty) = self.substitution.apply(&literal.ty).to_type() else {
+                continue;
+            };
+            let fits = match (&literal.literal, ty.max_integer()) {
+                (Literal::Integer(value), Some(max)) if literal.negative && ty.is_signed() => {
+                    *value <= max + 1
+                }
+                (Literal::Integer(value), Some(max)) => *value <= max,
+                (Literal::Float(value), _) if ty == Type::F32 => value.abs() <= f64::from(f32::MAX),
+                _ => true,
+            };
+            if fits {
+                continue;
+            }
+            let sign = if literal.negative { "-" } else { "" };
+            let value = match &literal.literal {
+                Literal::Integer(value) => value.to_string(),
+                Literal::Float(value) => value.to_string(),
+                _ => continue,
+            };
+            let range = match ty.max_integer() {
+                Some(max) if ty.is_signed() => format!("`-{}` to `{max}`", max + 1),
+                Some(max) => format!("`0` to `{max}`"),
+                None => format!("`-{}` to `{}`", f32::MAX, f32::MAX),
+            };
+            self.diagnostics.push(
+                Diagnostic::error(format!("literal out of range for `{ty}`"))
+                    .with_code(codes::LITERAL_OUT_OF_RANGE)
+                    .with_primary(
+                        literal.span,
+                        format!("`{sign}{value}` doesn't fit in `{ty}`"),
+                    )
+                    .with_note(format!("`{ty}` ranges from {range}")),
+            );
+        }
     }
 
-    fn infer_record_access(&self, var_name: &str, field_name: &str) -> Type {
-        let ty = self.variables.get(var_name).expect("Variable not found");
-        let Type::Custom(name, _) = ty else {
-            panic!("Expected custom type for record access");
+    /// Strings, numbers and booleans can be rendered in a string.
+    fn check_holes(&mut self) {
+        for (ty, span) in std::mem::take(&mut self.holes) {
+            let Some(ty) = self.substitution.apply(&ty).to_type() else {
+                continue;
+            };
+            if ty.is_integer() || ty.is_float() || matches!(ty, Type::Bool | Type::String) {
+                continue;
+            }
+            self.diagnostics.push(
+                Diagnostic::error(format!("cannot render `{ty}` in a string"))
+                    .with_code(codes::NOT_RENDERABLE)
+                    .with_primary(span, format!("this is `{ty}`"))
+                    .with_note("only strings, numbers and booleans can be interpolated"),
+            );
+        }
+    }
+
+    fn infer_binary_op(&mut self, lhs: &mut Expression, op: &BinaryOp, rhs: &mut Expression) -> Ty {
+        let lhs_type = self.infer_expr(lhs);
+        let rhs_type = self.infer_expr(rhs);
+        let applies_to = match op {
+            BinaryOp::And | BinaryOp::Or => {
+                self.constrain(Ty::con("Bool"), lhs_type, lhs.span(), None);
+                self.constrain(Ty::con("Bool"), rhs_type, rhs.span(), None);
+                return Ty::con("Bool");
+            }
+            BinaryOp::Eq | BinaryOp::NotEq => Applicable::Equatable,
+            BinaryOp::Xor => Applicable::Integers,
+            _ => Applicable::Numbers,
         };
+        self.constrain(lhs_type.clone(), rhs_type, rhs.span(), Some(lhs.span()));
+        self.operands.push(Operand {
+            operator: op.symbol().to_owned(),
+            applies_to,
+            ty: lhs_type.clone(),
+            span: lhs.span(),
+        });
+        if op.is_comparison() {
+            Ty::con("Bool")
+        } else {
+            lhs_type
+        }
+    }
+
+    fn infer_unary_op(&mut self, op: &UnaryOp, operand: &mut Expression) -> Ty {
+        let ty = self.infer_expr(operand);
+        // The operand's literal is the last one inferred.
+        if *op == UnaryOp::Negate
+            && let ExpressionKind::Literal(_) = operand.kind
+            && let Some(literal) = self.literals.last_mut()
+        {
+            literal.negative = true;
+        }
+        let applies_to = match op {
+            UnaryOp::Negate => Applicable::Signed,
+            UnaryOp::Not => Applicable::Bits,
+        };
+        self.operands.push(Operand {
+            operator: op.symbol().to_owned(),
+            applies_to,
+            ty: ty.clone(),
+            span: operand.span(),
+        });
+        ty
+    }
+
+    /// Reports the operands of a type their operator doesn't apply to.
+    /// Those whose type is still unknown, in generic functions, are let
+    /// through.
+    fn check_operands(&mut self) {
+        for operand in std::mem::take(&mut self.operands) {
+            let Some(ty) = self.substitution.apply(&operand.ty).to_type() else {
+                continue;
+            };
+            if operand.applies_to.accepts(&ty) {
+                continue;
+            }
+            let operator = &operand.operator;
+            self.diagnostics.push(
+                Diagnostic::error(format!("cannot apply `{operator}` to `{ty}`"))
+                    .with_code(codes::INVALID_OPERAND)
+                    .with_primary(operand.span, format!("this is `{ty}`"))
+                    .with_note(format!(
+                        "`{operator}` applies to {}",
+                        operand.applies_to.description()
+                    )),
+            );
+        }
+    }
+
+    fn get_variable(&self, name: &str) -> Option<&Scheme> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn get_type(&self, type_name: &str, span: Span) -> Result<&CustomType, Diagnostic> {
         self.types
             .iter()
-            .find(|t| t.name() == name)
-            .and_then(|t| t.get_record_fields())
-            .expect("Record type not found")
-            .iter()
-            .find(|f| f.name() == field_name)
-            .map(|f| f.ty().to_owned())
-            .expect("Record field not found")
+            .find(|t| t.name() == type_name)
+            .ok_or_else(|| {
+                Diagnostic::error(format!("cannot find type `{type_name}`"))
+                    .with_code(codes::UNKNOWN_TYPE)
+                    .with_primary(span, "not defined")
+            })
+    }
+
+    fn bind(&mut self, name: &str, scheme: Scheme) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), scheme);
+        }
+    }
+
+    fn fresh_var(&mut self) -> TypeVar {
+        let var = TypeVar(self.next_var);
+        self.next_var += 1;
+        var
+    }
+
+    fn fresh(&mut self) -> Ty {
+        Ty::Var(self.fresh_var())
+    }
+
+    /// A type of `scheme` with fresh variables, used at `span`, where its
+    /// bounds must hold.
+    fn instantiate(&mut self, scheme: &Scheme, span: Span) -> Ty {
+        let mapping = scheme.vars.iter().map(|var| (*var, self.fresh())).collect();
+        for (trait_name, ty) in &scheme.bounds {
+            self.require(trait_name, ty.rename(&mapping), span);
+        }
+        scheme.ty.rename(&mapping)
+    }
+
+    /// Quantifies `ty` over the variables that don't appear in the
+    /// environment. Constraints must be solved first.
+    fn generalize(&self, ty: &Ty) -> Scheme {
+        let ty = self.substitution.apply(ty);
+        let mut environment = BTreeSet::new();
+        let schemes = self.scopes.iter().flat_map(|s| s.values());
+        for scheme in schemes.chain(self.functions.values()) {
+            let applied = Scheme {
+                vars: scheme.vars.clone(),
+                ty: self.substitution.apply(&scheme.ty),
+                bounds: vec![],
+            };
+            environment.extend(applied.free_vars());
+        }
+        if let Some(return_type) = &self.return_type {
+            environment.extend(self.substitution.apply(return_type).free_vars());
+        }
+        let vars = ty
+            .free_vars()
+            .into_iter()
+            .filter(|var| !environment.contains(var))
+            .collect();
+        Scheme {
+            vars,
+            ty,
+            bounds: vec![],
+        }
+    }
+
+    fn constrain(&mut self, expected: Ty, found: Ty, span: Span, because: Option<Span>) {
+        self.constraints.push(Constraint {
+            expected,
+            found,
+            span,
+            because,
+        });
+    }
+
+    /// Unifies every pending constraint, reporting the ones that fail.
+    fn solve(&mut self) {
+        for constraint in std::mem::take(&mut self.constraints) {
+            let expected = self.substitution.apply(&constraint.expected);
+            let found = self.substitution.apply(&constraint.found);
+            let diagnostic = match self.substitution.unify(&expected, &found) {
+                Ok(()) => continue,
+                Err(UnifyError::Mismatch) => mismatch(&expected, &found, constraint.span),
+                Err(UnifyError::Occurs(_, ty)) => Diagnostic::error("infinite type")
+                    .with_code(codes::INFINITE_TYPE)
+                    .with_primary(
+                        constraint.span,
+                        format!("this would need a type containing itself: `{ty}`"),
+                    ),
+            };
+            let diagnostic = match constraint.because {
+                Some(because) => diagnostic.with_secondary(because, "expected due to this"),
+                None => diagnostic,
+            };
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Records `ty` as the type of `expr`, as a variable to be resolved once
+    /// all constraints are solved.
+    fn annotate(&mut self, expr: &mut Expression, ty: &Ty) {
+        let var = match ty {
+            Ty::Var(var) => *var,
+            _ => {
+                let var = self.fresh_var();
+                self.substitution.bind(var, ty.clone());
+                var
+            }
+        };
+        expr.set_type(AssociatedType::Unknown(var.0));
+    }
+
+    /// Replaces the type variable of `expr` with its solution, if it has one.
+    /// Variables of generic functions stay unknown.
+    fn resolve(&self, expr: &mut Expression) {
+        if let Some(AssociatedType::Unknown(var)) = expr.associated_type {
+            let ty = self.substitution.apply(&Ty::Var(TypeVar(var)));
+            match (ty.to_type(), ty) {
+                (Some(ty), _) => expr.set_type(AssociatedType::Concrete(ty)),
+                (None, Ty::Var(TypeVar(root))) => expr.set_type(AssociatedType::Unknown(root)),
+                (None, _) => {}
+            }
+        }
+    }
+
+    /// The entry point and non-generic functions are compiled as is, so
+    /// every value in them needs a concrete type.
+    fn report_unresolved(&mut self, program: &Program) {
+        let mut unresolved = vec![];
+        let mut collect = |expr: &Expression| {
+            if let Some(AssociatedType::Unknown(var)) = expr.associated_type {
+                unresolved.push((var, expr.span()));
+            }
+        };
+        for fi in &program.function_implementations {
+            let monomorphic = program.function_declarations.iter().any(|fd| {
+                fd.qualified_name() == fi.qualified_name() && fd.type_params().is_empty()
+            });
+            if monomorphic {
+                walk_body(fi.body(), &mut collect);
+            }
+        }
+        walk_block(&program.entry_point, &mut collect);
+        // Report the outermost expression of each unresolved tree.
+        let mut reported: Vec<Span> = vec![];
+        for (var, span) in unresolved.into_iter().rev() {
+            let ty = self.substitution.apply(&Ty::Var(TypeVar(var)));
+            if ty.contains_error() || reported.iter().any(|r| r.contains(span)) {
+                continue;
+            }
+            reported.push(span);
+            self.diagnostics.push(
+                Diagnostic::error("cannot infer the type of this expression")
+                    .with_code(codes::CANNOT_INFER)
+                    .with_primary(span, format!("type is `{ty}`"))
+                    .with_note("compiled code can only use values of a known type"),
+            );
+        }
+    }
+
+    /// Reports the patterns that can't be reached, and the values no pattern
+    /// matches, across the arms of each `match` and the clauses of each
+    /// function.
+    fn check_patterns(&mut self, program: &Program) {
+        let matrix = Matrix::new(&self.types);
+        let mut diagnostics = vec![];
+
+        let mut functions: Vec<Vec<&FunctionImplementation>> = vec![];
+        for fi in &program.function_implementations {
+            match functions
+                .iter_mut()
+                .find(|clauses| clauses[0].qualified_name() == fi.qualified_name())
+            {
+                Some(clauses) => clauses.push(fi),
+                None => functions.push(vec![fi]),
+            }
+        }
+        for clauses in functions {
+            let rows: Vec<Vec<Pattern>> =
+                clauses.iter().map(|fi| fi.arguments().to_vec()).collect();
+            let spans: Vec<Span> = clauses.iter().map(|fi| fi.span()).collect();
+            let width = rows[0].len();
+            let Some(missing) = check_rows(&matrix, &rows, width, &spans, &mut diagnostics) else {
+                continue;
+            };
+            let name = clauses[0].qualified_name().qualified();
+            let args: Vec<_> = missing.iter().map(exhaustiveness::show).collect();
+            let call = format!("{name}({})", args.join(", "));
+            diagnostics.push(
+                Diagnostic::error(format!("non-exhaustive clauses: `{call}` not covered"))
+                    .with_code(codes::NON_EXHAUSTIVE)
+                    .with_primary(spans[0], format!("`{call}` not covered"))
+                    .with_note("add a clause matching it"),
+            );
+        }
+
+        let mut check_match = |expr: &Expression| {
+            let ExpressionKind::Match(scrutinee, arms) = &expr.kind else {
+                return;
+            };
+            let rows: Vec<Vec<Pattern>> =
+                arms.iter().map(|arm| vec![arm.pattern.clone()]).collect();
+            let spans: Vec<Span> = arms.iter().map(|arm| arm.pattern.span()).collect();
+            if let Some(missing) = check_rows(&matrix, &rows, 1, &spans, &mut diagnostics) {
+                let pattern = exhaustiveness::show(&missing[0]);
+                diagnostics.push(
+                    Diagnostic::error(format!("non-exhaustive patterns: `{pattern}` not covered"))
+                        .with_code(codes::NON_EXHAUSTIVE)
+                        .with_primary(scrutinee.span(), format!("pattern `{pattern}` not covered"))
+                        .with_note("add an arm matching it, or a wildcard `_` arm"),
+                );
+            }
+        };
+        for fi in &program.function_implementations {
+            walk_body(fi.body(), &mut check_match);
+        }
+        walk_block(&program.entry_point, &mut check_match);
+
+        self.diagnostics.extend(diagnostics);
+    }
+
+    /// Reports `diagnostic` and gives the type of the erroneous expression.
+    fn report(&mut self, diagnostic: Diagnostic) -> Ty {
+        self.diagnostics.push(diagnostic);
+        Ty::Error
+    }
+}
+
+/// Warns about the rows that no value reaches, and gives the values that
+/// reach none, if any.
+fn check_rows(
+    matrix: &Matrix,
+    rows: &[Vec<Pattern>],
+    width: usize,
+    spans: &[Span],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Vec<Pattern>> {
+    // Clauses with the wrong number of parameters were reported already.
+    if rows.iter().any(|row| row.len() != width) {
+        return None;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        if matrix.useful(&rows[..i], row).is_none() {
+            diagnostics.push(
+                Diagnostic::warning("unreachable pattern")
+                    .with_code(codes::UNREACHABLE_PATTERN)
+                    .with_primary(spans[i], "no value reaches this")
+                    .with_note("it only matches values matched before"),
+            );
+        }
+    }
+    matrix.missing(rows, width)
+}
+
+/// Blocks have no span of their own: use their result's, if it's not the
+/// implicit `()`.
+fn block_span(block: &Block, fallback: Span) -> Span {
+    match block.return_expr.span() {
+        Span::DUMMY => fallback,
+        span => span,
+    }
+}
+
+fn mismatch(expected: &Ty, found: &Ty, span: Span) -> Diagnostic {
+    Diagnostic::error("mismatched types")
+        .with_code(codes::TYPE_MISMATCH)
+        .with_primary(span, format!("expected `{expected}`, found `{found}`"))
+}
+
+fn wrong_payload_count(
+    enum_name: &str,
+    variant_name: &str,
+    expected: usize,
+    found: usize,
+    span: Span,
+) -> Diagnostic {
+    Diagnostic::error(format!(
+        "variant `{enum_name}::{variant_name}` has {expected} field{} but {found} were supplied",
+        if expected == 1 { "" } else { "s" },
+    ))
+    .with_code(codes::WRONG_ARGUMENT_COUNT)
+    .with_primary(span, format!("expected {expected}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::span::FileId;
+    use parser::program::parse_program;
+
+    use super::*;
+
+    fn infer(source: &str) -> Result<Program, Vec<Diagnostic>> {
+        let mut program = parse_program(source, FileId::default()).unwrap();
+        TypeInference::default().infer_program(&mut program)?;
+        Ok(program)
+    }
+
+    #[test]
+    fn test_let_polymorphism() {
+        let source = "id<A>(A) -> A;\nid(x) = x;\nmain {\n    a = id(True);\n    id(1)\n}";
+        let program = infer(source).unwrap();
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::I32));
+        let a = &program.entry_point.statements[0];
+        let StatementKind::Assignment(_, _, value) = &a.kind else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(value.get_type(), Some(&Type::Bool));
+    }
+
+    #[test]
+    fn test_function_values_stay_monomorphic() {
+        let program = infer("main {\n    f = (x) -> x;\n    f(True)\n}").unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::Bool)
+        );
+        let source = "main {\n    f = (x) -> x;\n    List::length(List::map([True], f))\n}";
+        assert!(infer(source).is_ok());
+        let errors = infer("main {\n    f = (x) -> x;\n    a = f(True);\n    f(1)\n}");
+        assert_eq!(errors.unwrap_err()[0].code, Some(codes::TYPE_MISMATCH));
+    }
+
+    #[test]
+    fn test_polymorphic_functions_need_a_declaration() {
+        let errors = infer("sum(a, b) = a + b;\nmain { sum(1, 2) }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(codes::CANNOT_INFER));
+        assert_eq!(
+            errors[0].message,
+            "cannot compile polymorphic function `sum` without a declaration"
+        );
+    }
+
+    #[test]
+    fn test_declared_functions_need_an_implementation() {
+        let errors = infer("sum(U8) -> U8;\nsmu(x) = x + 1;\nmain { 1 }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(codes::MISSING_IMPLEMENTATION));
+        assert_eq!(
+            errors[0].message,
+            "function `sum` is declared but not implemented"
+        );
+    }
+
+    #[test]
+    fn test_functions_are_declared_once() {
+        let errors = infer("f(U8) -> U8;\nf(Bool) -> U8;\nf(x) = 1;\nmain { f(1) }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(codes::DUPLICATE_DECLARATION));
+        assert_eq!(errors[0].message, "function `f` is declared more than once");
+        assert_eq!(errors[0].secondary().count(), 1);
+        let source = "List::length<A>([A]) -> U8;\nList::length(_) = 0;\nmain { 1 }";
+        let errors = infer(source).unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "function `List::length` is a builtin function"
+        );
+    }
+
+    #[test]
+    fn test_monomorphic_functions_get_declared() {
+        let program = infer("double(x) = x + 1;\nmain { double(2) }").unwrap();
+        let declaration = &program.function_declarations[0];
+        assert_eq!(declaration.name(), "double");
+        assert_eq!(declaration.signature().return_type(), &Type::I32);
+    }
+
+    #[test]
+    fn test_mismatch_points_at_both_sides() {
+        let errors = infer("main {\n    x = 1;\n    x + True\n}").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(codes::TYPE_MISMATCH));
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `{integer}`, found `Bool`"
+        );
+        assert_eq!(errors[0].secondary().count(), 1);
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        let program =
+            infer("main {\n    s = String::concat(\"a\", \"b\");\n    String::length(s) == 2\n}")
+                .unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::Bool)
+        );
+        let errors = infer("main { String::length(1) }").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::TYPE_MISMATCH));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let program = infer("main { n = 1;\n \"n is {n + 1}\" }").unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::String)
+        );
+        let errors =
+            infer("type Point { x: U8 }\nmain { p = Point { x: 1 };\n \"{p}\" }").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::NOT_RENDERABLE));
+    }
+
+    #[test]
+    fn test_lists() {
+        let program = infer(
+            "double(U8) -> U8;\ndouble(x) = x * 2;\nhead([U8]) -> U8;\nhead([]) = 0;\nhead([x, .._]) = x;\nmain { head(List::map([1, 2], double)) }",
+        )
+        .unwrap();
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::U8));
+        let errors = infer("main { [1, True] }").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::TYPE_MISMATCH));
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `{integer}`, found `Bool`"
+        );
+    }
+
+    #[test]
+    fn test_tuples() {
+        let program = infer(
+            "swap((U8, Bool)) -> (Bool, U8);\nswap((x, b)) = (b, x);\nmain { swap((1, True)) }",
+        )
+        .unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::tuple(vec![Type::Bool, Type::U8]))
+        );
+        let errors =
+            infer("f((U8, U8)) -> U8;\nf((x, True)) = x;\nmain { f((1, 2)) }").unwrap_err();
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `U8`, found `Bool`"
+        );
+    }
+
+    #[test]
+    fn test_lambdas() {
+        let program = infer("main { k = 1;\nList::map([1, 2], (x) -> x + k == 3) }").unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::list(Type::Bool))
+        );
+        let errors = infer(
+            "apply((U8) -> U8, U8) -> U8;\napply(f, x) = f(x);\nmain { apply((x) -> True, 1) }",
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `(U8) -> U8`, found `(U8) -> Bool`"
+        );
+    }
+
+    #[test]
+    fn test_partial_application() {
+        let program = infer(
+            "add(U8, U16) -> U16;\nadd(a, b) = b;\nmain { xs = [1];\nxs |> List::map(add(2)) }",
+        )
+        .unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::list(Type::U16))
+        );
+        let errors = infer("main { f = (x) -> x;\nf() }").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::WRONG_ARGUMENT_COUNT));
+    }
+
+    #[test]
+    fn test_if() {
+        let program = infer("main { if 1 > 2 { 1 } else if True { 2 } else { 3 } }").unwrap();
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::I32));
+        let errors = infer("main { if 1 { 1 } else { 2 } }").unwrap_err();
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `Bool`, found `{integer}`"
+        );
+        let errors = infer("main { if True { 1 } else { False } }").unwrap_err();
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `{integer}`, found `Bool`"
+        );
+    }
+
+    #[test]
+    fn test_operators() {
+        let program = infer("main { 1 + 2 == 3 && !(1.5 < 2.0) }").unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::Bool)
+        );
+
+        let error = |source: &str| {
+            let errors = infer(source).unwrap_err();
+            assert_eq!(errors[0].code, Some(codes::INVALID_OPERAND));
+            errors[0].message.clone()
+        };
+        assert_eq!(error("main { True + False }"), "cannot apply `+` to `Bool`");
+        assert_eq!(
+            error("main { \"a\" < \"b\" }"),
+            "cannot apply `<` to `String`"
+        );
+        assert_eq!(error("main { 1.0 ^ 2.0 }"), "cannot apply `^` to `F64`");
+        assert_eq!(
+            error("f(U8) -> U8;\nf(x) = -x;\nmain { f(1) }"),
+            "cannot apply `-` to `U8`"
+        );
+        assert_eq!(
+            error("f(U8) -> U8;\nf(x) = x;\nmain { f(-1) }"),
+            "cannot apply `-` to `U8`"
+        );
+        assert_eq!(
+            error("main { (1, 2) == (1, 2) }"),
+            "cannot apply `==` to `(I32, I32)`"
+        );
+
+        let errors = infer("main { 1 && True }").unwrap_err();
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `Bool`, found `{integer}`"
+        );
+        let errors = infer("main { 1 + 2.0 }").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::TYPE_MISMATCH));
+    }
+
+    #[test]
+    fn test_int_builtins() {
+        let program = infer("main { Int::checked_add(1, 2) }").unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::Custom("Option".to_owned(), vec!["I32".to_owned()]))
+        );
+        let program =
+            infer("f(U8) -> U8;\nf(x) = Int::wrapping_mul(x, 3);\nmain { f(2) }").unwrap();
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::U8));
+        let errors = infer("main { Int::wrapping_sub(1, True) }").unwrap_err();
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `{integer}`, found `Bool`"
+        );
+        for (source, ty) in [
+            ("Int::checked_add(\"a\", \"b\")", "String"),
+            ("Int::wrapping_add(True, False)", "Bool"),
+            ("Int::wrapping_add(1.5, 2.5)", "F64"),
+        ] {
+            let errors = infer(&format!("main {{ {source} }}")).unwrap_err();
+            assert_eq!(errors[0].code, Some(codes::INVALID_OPERAND));
+            assert_eq!(
+                errors[0].primary().unwrap().message,
+                format!("this is `{ty}`")
+            );
+        }
+    }
+
+    #[test]
+    fn test_traits() {
+        let show = "trait Show { show(Self) -> String; }\n\
+                    impl Show for Bool { show(True) = \"yes\"; show(False) = \"no\"; }\n";
+        let program = infer(&format!(
+            "{show}describe<A: Show>(A) -> String;\ndescribe(x) = Show::show(x);\n\
+             main {{ describe(True) }}"
+        ))
+        .unwrap();
+        assert_eq!(
+            program.entry_point.return_expr.get_type(),
+            Some(&Type::String)
+        );
+        assert!(program.trait_implementations.is_empty());
+        assert!(
+            program
+                .function_declarations
+                .iter()
+                .any(|fd| fd.qualified_name().qualified() == "Show::show<Bool>")
+        );
+
+        let error = |source: &str| {
+            let errors = infer(&format!("{show}{source}")).unwrap_err();
+            (errors[0].code, errors[0].message.clone())
+        };
+        assert_eq!(
+            error("main { Show::show(1.5) }"),
+            (
+                Some(codes::NOT_IMPLEMENTED),
+                "`F64` doesn't implement `Show`".to_owned()
+            )
+        );
+        assert_eq!(
+            error("f<A>(A) -> String;\nf(x) = Show::show(x);\nmain { f(True) }"),
+            (
+                Some(codes::NOT_IMPLEMENTED),
+                "`A` doesn't implement `Show`".to_owned()
+            )
+        );
+        assert_eq!(
+            error("impl Show for U8 { }\nmain { 0 }"),
+            (
+                Some(codes::MISSING_METHOD),
+                "missing method `show` in the implementation of `Show` for `U8`".to_owned()
+            )
+        );
+        assert_eq!(
+            error("impl Show for Bool { show(_) = \"\"; }\nmain { 0 }"),
+            (
+                Some(codes::DUPLICATE_IMPLEMENTATION),
+                "`Show` is implemented more than once for `Bool`".to_owned()
+            )
+        );
+        assert_eq!(
+            error("impl Eq for Bool { }\nmain { 0 }"),
+            (
+                Some(codes::UNKNOWN_TRAIT),
+                "cannot find trait `Eq`".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_method_calls() {
+        let option = "Option::or<A>(Option<A>, A) -> A;\n\
+                      Option::or(Option::Some(x), _) = x;\n\
+                      Option::or(Option::None, x) = x;\n";
+        let program = infer(&format!(
+            "{option}main {{ Option::Some(\"a\").or(\"b\").length() + [1, 2].length() }}"
+        ))
+        .unwrap();
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::U64));
+        // The calls are resolved: `String::length(Option::or(..))`.
+        let ExpressionKind::BinaryOp(lhs, _, _) = &program.entry_point.return_expr.kind else {
+            panic!("expected a binary op");
+        };
+        let ExpressionKind::FunctionCall {
+            namespace,
+            arguments,
+            ..
+        } = &lhs.kind
+        else {
+            panic!("expected a call, got {lhs:?}");
+        };
+        assert_eq!(namespace.as_deref(), Some("String"));
+        assert!(matches!(
+            &arguments[0].kind,
+            ExpressionKind::FunctionCall { namespace: Some(ns), .. } if ns == "Option"
+        ));
+
+        let program = infer(&format!(
+            "{option}trait Size {{ size(Self) -> U64; }}\n\
+             impl Size for Bool {{ size(_) = 1; }}\n\
+             twice<A: Size>(A) -> U64;\ntwice(x) = x.size() * 2;\n\
+             main {{ True.size() + twice(False) + 1.checked_add(2).or(0) }}"
+        ));
+        assert!(program.is_ok(), "{program:?}");
+
+        let error = |source: &str| {
+            let errors = infer(&format!("{option}{source}")).unwrap_err();
+            (errors[0].code, errors[0].message.clone())
+        };
+        assert_eq!(
+            error("main { True.or(False) }"),
+            (
+                Some(codes::UNKNOWN_FUNCTION),
+                "no function `or` for `Bool`".to_owned()
+            )
+        );
+        assert_eq!(
+            error("f(x) = x.or(1);\nmain { 0 }"),
+            (
+                Some(codes::CANNOT_INFER),
+                "cannot infer the type `.or` is called on".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_record_patterns() {
+        let record = "type Person { name: String, age: U8 }\n";
+        let program = infer(&format!(
+            "{record}age(Person) -> U8;\nage(Person {{ age, .. }}) = age;\nmain {{ age(Person {{ name: \"Al\", age: 3 }}) }}"
+        ))
+        .unwrap();
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::U8));
+
+        let errors = infer(&format!(
+            "{record}f(Person) -> U8;\nf(Person {{ age, age: 1, height }}) = age;\nmain {{ 0 }}"
+        ))
+        .unwrap_err();
+        let codes: Vec<_> = errors.iter().map(|error| error.code).collect();
+        assert_eq!(
+            codes,
+            [
+                Some(codes::DUPLICATE_FIELD),
+                Some(codes::UNKNOWN_FIELD),
+                Some(codes::MISSING_FIELD)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declared_bodies_are_typed() {
+        let program = infer("sum(U8, U8) -> U8;\nsum(a, b) = a + b;\nmain { sum(1, 2) }").unwrap();
+        let FunctionBody::SingleLine(body) = program.function_implementations[0].body() else {
+            panic!("expected a single line body");
+        };
+        assert_eq!(body.get_type(), Some(&Type::U8));
+    }
+
+    #[test]
+    fn test_body_checked_against_return_type() {
+        let errors = infer("f(U8) -> Bool;\nf(x) = x;\nmain { f(1) }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `Bool`, found `U8`"
+        );
+    }
+
+    #[test]
+    fn test_blocks_ending_in_return_diverge() {
+        let source = "f(U8) -> U8;\nf(x) {\n    y = x + 1;\n    return y;\n}\nmain { f(1) }";
+        assert!(infer(source).is_ok());
+        let errors = infer("f(U8) -> U8;\nf(x) {\n    return True;\n}\nmain { f(1) }");
+        assert_eq!(errors.unwrap_err()[0].code, Some(codes::TYPE_MISMATCH));
+    }
+
+    #[test]
+    fn test_type_parameters_are_rigid() {
+        let errors = infer("id<A>(A) -> A;\nid(x) = True;\nmain { id(1) }").unwrap_err();
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `A`, found `Bool`"
+        );
+    }
+
+    #[test]
+    fn test_implementation_parameter_count() {
+        let errors = infer("f(U8, U8) -> U8;\nf(x) = x;\nmain { 0 }").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::WRONG_ARGUMENT_COUNT));
+    }
+
+    #[test]
+    fn test_literals_take_their_type_from_context() {
+        let program =
+            infer("f(U32) -> U32;\nf(x) = x;\nmain {\n    x = 300;\n    f(x)\n}").unwrap();
+        let StatementKind::Assignment(_, _, value) = &program.entry_point.statements[0].kind else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(value.get_type(), Some(&Type::U32));
+    }
+
+    #[test]
+    fn test_literals_take_their_type_from_annotations() {
+        let program = infer("main {\n    x: U32 = 300;\n    x\n}").unwrap();
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::U32));
+        let errors = infer("main {\n    x: U8 = 300;\n    x\n}").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::LITERAL_OUT_OF_RANGE));
+        let errors = infer("main {\n    x: U8 = True;\n    x\n}").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::TYPE_MISMATCH));
+    }
+
+    #[test]
+    fn test_literals_default() {
+        let program = infer("main {\n    x = 1.5;\n    2\n}").unwrap();
+        let StatementKind::Assignment(_, _, value) = &program.entry_point.statements[0].kind else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(value.get_type(), Some(&Type::F64));
+        assert_eq!(program.entry_point.return_expr.get_type(), Some(&Type::I32));
+    }
+
+    #[test]
+    fn test_literal_out_of_range() {
+        let errors = infer("f(U8) -> U8;\nf(x) = x;\nmain { f(256) }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(codes::LITERAL_OUT_OF_RANGE));
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "`256` doesn't fit in `U8`"
+        );
+    }
+
+    #[test]
+    fn test_negative_literal_range() {
+        assert!(infer("f(I8) -> I8;\nf(x) = x;\nmain { f(-128) }").is_ok());
+        assert!(infer("f(I8) -> I8;\nf(x) = x;\nmain { f(128) }").is_err());
+    }
+
+    #[test]
+    fn test_builtin_types_keep_their_variants() {
+        let source = "type Option<T> { Nothing, Just(T) }\nmain { Int::checked_add(1, 2) }";
+        let errors = infer(source).unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::INCOMPATIBLE_BUILTIN));
+        assert_eq!(
+            errors[0].primary().unwrap().message,
+            "expected `None` and `Some(_)`"
+        );
+        let source = "type Option<T> { Some(T), None }\nmain { Int::checked_add(1, 2) }";
+        assert!(infer(source).is_ok());
+    }
+
+    #[test]
+    fn test_non_exhaustive_match() {
+        let source = "type Option { None, Some(U8) }\nmain {\n    x = Option::Some(1);\n    match x {\n        Option::Some(0) => 0,\n        Option::None => 1\n    }\n}";
+        let errors = infer(source).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "non-exhaustive patterns: `Option::Some(1)` not covered"
+        );
+    }
+
+    #[test]
+    fn test_unreachable_clause() {
+        let mut program = parse_program(
+            "f(U8) -> U8;\nf(x) = x;\nf(0) = 1;\nmain { f(2) }",
+            FileId::default(),
+        )
+        .unwrap();
+        let warnings = TypeInference::default()
+            .infer_program(&mut program)
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, Some(codes::UNREACHABLE_PATTERN));
+    }
+
+    #[test]
+    fn test_non_exhaustive_clauses() {
+        let errors = infer("f(Bool) -> U8;\nf(True) = 1;\nmain { f(True) }").unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "non-exhaustive clauses: `f(False)` not covered"
+        );
+    }
+
+    #[test]
+    fn test_infinite_type() {
+        let errors = infer("f(x) = f;\nmain { 0 }").unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::INFINITE_TYPE));
     }
 }
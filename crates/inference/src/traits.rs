@@ -0,0 +1,263 @@
+use std::str::FromStr;
+
+use ast::{
+    diagnostics::{Diagnostic, codes},
+    functions::{FunctionDeclaration, FunctionImplementation, QualifiedName},
+    program::Program,
+    span::Span,
+    traits::{Bound, SELF, TraitImplementation, instance_method},
+    types::{FunctionSignature, Type},
+};
+
+use crate::{TypeInference, ty::Ty};
+
+// A method `show` of a trait `Show` is the function `Show::show`, generic
+// over `Self`, which must implement `Show`. The methods of an
+// implementation become functions of their own, `Show::show<U8>`, which
+// code generation calls when it knows the type, and otherwise finds in the
+// dictionary passed to a function bounded by the trait.
+
+/// A type that must implement a trait, because of the call at `span`.
+pub struct Predicate {
+    trait_name: String,
+    ty: Ty,
+    span: Span,
+    /// The type parameters of the declared function containing the call,
+    /// and the traits they're known to implement.
+    params: Vec<String>,
+    assumed: Vec<Bound>,
+}
+
+impl TypeInference {
+    /// Declares the methods of every trait, and turns those of every
+    /// implementation into functions of the program.
+    pub(crate) fn declare_traits(&mut self, program: &mut Program) {
+        self.traits.extend(program.traits.iter().cloned());
+        for td in &program.traits {
+            for method in td.methods() {
+                let mut type_params = vec![SELF.to_owned()];
+                type_params.extend(method.type_params().iter().cloned());
+                let mut bounds = vec![Bound::new(SELF, td.name())];
+                bounds.extend(method.bounds().iter().cloned());
+                let declaration = FunctionDeclaration::new(
+                    QualifiedName::new(Some(td.name().to_owned()), method.name()),
+                    type_params,
+                    method.signature().clone(),
+                )
+                .with_bounds(bounds);
+                let scheme = self.declared_scheme(&declaration);
+                self.functions
+                    .insert(declaration.qualified_name().qualified(), scheme);
+            }
+        }
+        for ti in std::mem::take(&mut program.trait_implementations) {
+            self.implement_trait(&ti, program);
+        }
+        for fd in &program.function_declarations {
+            self.check_bounds(fd);
+        }
+    }
+
+    fn implement_trait(&mut self, ti: &TraitImplementation, program: &mut Program) {
+        let trait_name = ti.trait_name();
+        let Some(td) = self
+            .traits
+            .iter()
+            .find(|td| td.name() == trait_name)
+            .cloned()
+        else {
+            self.diagnostics.push(unknown_trait(trait_name, ti.span()));
+            return;
+        };
+        let (self_type, generics) = match Type::from_str(ti.type_name()) {
+            Ok(ty) => (ty, vec![]),
+            Err(_) => match self.get_type(ti.type_name(), ti.span()) {
+                Ok(ct) => {
+                    let generics = ct.generics().to_vec();
+                    (
+                        Type::Custom(ct.name().to_owned(), generics.clone()),
+                        generics,
+                    )
+                }
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    return;
+                }
+            },
+        };
+        let instance = (trait_name.to_owned(), ti.type_name().to_owned());
+        if !self.instances.insert(instance) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "`{trait_name}` is implemented more than once for `{}`",
+                    ti.type_name()
+                ))
+                .with_code(codes::DUPLICATE_IMPLEMENTATION)
+                .with_primary(ti.span(), "second implementation"),
+            );
+            return;
+        }
+
+        for fi in ti.methods() {
+            if td.method(fi.name()).is_none() {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` is not a method of `{trait_name}`", fi.name()))
+                        .with_code(codes::UNKNOWN_FUNCTION)
+                        .with_primary(fi.span(), "not declared by the trait")
+                        .with_secondary(td.span(), "trait declared here"),
+                );
+            }
+        }
+        for method in td.methods() {
+            let clauses: Vec<&FunctionImplementation> = ti
+                .methods()
+                .iter()
+                .filter(|fi| fi.name() == method.name())
+                .collect();
+            if clauses.is_empty() {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "missing method `{}` in the implementation of `{trait_name}` for `{}`",
+                        method.name(),
+                        ti.type_name()
+                    ))
+                    .with_code(codes::MISSING_METHOD)
+                    .with_primary(ti.span(), format!("`{}` not implemented", method.name()))
+                    .with_secondary(method.span(), "declared here"),
+                );
+                continue;
+            }
+            let name = instance_method(trait_name, ti.type_name(), method.name());
+            let signature = method.signature();
+            let signature = FunctionSignature::new(
+                signature
+                    .arguments()
+                    .iter()
+                    .map(|arg| arg.replace(SELF, &self_type))
+                    .collect(),
+                signature.return_type().replace(SELF, &self_type),
+            );
+            let mut type_params = generics.clone();
+            type_params.extend(method.type_params().iter().cloned());
+            program.function_declarations.push(
+                FunctionDeclaration::new(name.clone(), type_params, signature)
+                    .with_bounds(method.bounds().to_vec())
+                    .with_span(method.span()),
+            );
+            program
+                .function_implementations
+                .extend(clauses.into_iter().map(|fi| {
+                    FunctionImplementation::new(
+                        name.clone(),
+                        fi.arguments().to_vec(),
+                        fi.body().clone(),
+                    )
+                    .with_span(fi.span())
+                }));
+        }
+    }
+
+    /// Reports the bounds of `fd` naming unknown traits or parameters.
+    fn check_bounds(&mut self, fd: &FunctionDeclaration) {
+        for bound in fd.bounds() {
+            if !self.traits.iter().any(|td| td.name() == bound.trait_name) {
+                self.diagnostics
+                    .push(unknown_trait(&bound.trait_name, fd.span()));
+            } else if !fd.type_params().contains(&bound.param) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("cannot find type parameter `{}`", bound.param))
+                        .with_code(codes::UNKNOWN_TYPE)
+                        .with_primary(fd.span(), "bounded but not declared")
+                        .with_note(format!(
+                            "declare it with the others: `<{}: {}>`",
+                            bound.param, bound.trait_name
+                        )),
+                );
+            }
+        }
+    }
+
+    /// The traits declaring a method `name` that `type_name` implements, or
+    /// is bounded by in the function being checked.
+    pub(crate) fn traits_declaring(&self, name: &str, type_name: &str) -> Vec<String> {
+        self.traits
+            .iter()
+            .filter(|td| td.method(name).is_some())
+            .map(|td| td.name().to_owned())
+            .filter(|trait_name| {
+                let assumed = self
+                    .assumed
+                    .1
+                    .iter()
+                    .any(|bound| bound.param == type_name && bound.trait_name == *trait_name);
+                assumed
+                    || self
+                        .instances
+                        .contains(&(trait_name.clone(), type_name.to_owned()))
+            })
+            .collect()
+    }
+
+    /// Requires `ty` to implement `trait_name`, for the call at `span`.
+    pub(crate) fn require(&mut self, trait_name: &str, ty: Ty, span: Span) {
+        let (params, assumed) = self.assumed.clone();
+        self.predicates.push(Predicate {
+            trait_name: trait_name.to_owned(),
+            ty,
+            span,
+            params,
+            assumed,
+        });
+    }
+
+    /// Reports the types required to implement a trait that don't.
+    pub(crate) fn check_predicates(&mut self) {
+        for predicate in std::mem::take(&mut self.predicates) {
+            self.default_literals(&predicate.ty);
+            let ty = self.substitution.apply(&predicate.ty);
+            let trait_name = &predicate.trait_name;
+            let name = match &ty {
+                Ty::Error => continue,
+                Ty::Con(name, _) => name,
+                _ => {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "cannot infer which implementation of `{trait_name}` to use"
+                        ))
+                        .with_code(codes::CANNOT_INFER)
+                        .with_primary(predicate.span, format!("type is `{ty}`"))
+                        .with_note(format!(
+                            "declare the function's signature, with a bound like `<A: {trait_name}>`"
+                        )),
+                    );
+                    continue;
+                }
+            };
+            let assumed = predicate
+                .assumed
+                .iter()
+                .any(|bound| bound.param == *name && bound.trait_name == *trait_name);
+            if assumed || self.instances.contains(&(trait_name.clone(), name.clone())) {
+                continue;
+            }
+            let diagnostic = Diagnostic::error(format!("`{ty}` doesn't implement `{trait_name}`"))
+                .with_code(codes::NOT_IMPLEMENTED)
+                .with_primary(predicate.span, format!("`{trait_name}` is required here"));
+            self.diagnostics
+                .push(match predicate.params.contains(name) {
+                    true => diagnostic.with_note(format!(
+                        "bound the type parameter: `<{name}: {trait_name}>`"
+                    )),
+                    false => diagnostic.with_note(format!(
+                        "implement it: `impl {trait_name} for {name} {{ ... }}`"
+                    )),
+                });
+        }
+    }
+}
+
+fn unknown_trait(name: &str, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("cannot find trait `{name}`"))
+        .with_code(codes::UNKNOWN_TRAIT)
+        .with_primary(span, "not defined")
+}
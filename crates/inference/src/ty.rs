@@ -0,0 +1,259 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    str::FromStr,
+};
+
+use ast::types::{CompoundType, FunctionSignature, Type};
+
+/// The name of the list type, as a [`Ty::Con`] of one argument. Type names
+/// can't be written like this, so it's never taken by a program's type.
+pub const LIST: &str = "[]";
+
+/// The name of tuple types, as a [`Ty::Con`] of their elements.
+pub const TUPLE: &str = "(,)";
+
+/// A type variable, standing for a type that isn't known yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypeVar(pub u32);
+
+/// The types inference works with: [`Type`], plus type variables.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Var(TypeVar),
+    /// A named type and its arguments: `U8`, `Option<A>`.
+    Con(String, Vec<Ty>),
+    Fun(Vec<Ty>, Box<Ty>),
+    /// The type of an integer literal whose type isn't known yet. It can
+    /// only become an integer type.
+    Integer(TypeVar),
+    /// The type of a float literal whose type isn't known yet. It can only
+    /// become a float type.
+    Float(TypeVar),
+    /// The type of an expression that already failed to type check. It
+    /// unifies with anything, so one mistake is reported only once.
+    Error,
+}
+
+/// A type, generalised over some of its variables: the type of `id(x) = x;`
+/// is `forall A. (A) -> A`, and every use of `id` gets fresh variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme {
+    pub vars: Vec<TypeVar>,
+    pub ty: Ty,
+    /// The traits some of the types must implement: `(Show, A)`.
+    pub bounds: Vec<(String, Ty)>,
+}
+
+impl Ty {
+    pub fn con(name: &str) -> Self {
+        Self::Con(name.to_owned(), vec![])
+    }
+
+    /// `[element]`
+    pub fn list(element: Self) -> Self {
+        Self::Con(LIST.to_owned(), vec![element])
+    }
+
+    /// `(elements)`
+    pub fn tuple(elements: Vec<Self>) -> Self {
+        Self::Con(TUPLE.to_owned(), elements)
+    }
+
+    /// Converts a type written in the source. Names in `params` are type
+    /// parameters (e.g. the `A` of `map<A>`) and stand for the given types.
+    pub fn from_type(ty: &Type, params: &BTreeMap<String, Self>) -> Self {
+        match ty {
+            Type::Custom(name, generics) if generics.is_empty() && params.contains_key(name) => {
+                params[name].clone()
+            }
+            Type::Custom(name, generics) => Self::Con(
+                name.to_owned(),
+                generics
+                    .iter()
+                    .map(|generic| match params.get(generic) {
+                        Some(param) => param.clone(),
+                        None => Type::from_str(generic)
+                            .map_or_else(|_| Self::con(generic), |ty| Self::from_type(&ty, params)),
+                    })
+                    .collect(),
+            ),
+            Type::Function(signature) => Self::Fun(
+                signature
+                    .arguments()
+                    .iter()
+                    .map(|arg| Self::from_type(arg, params))
+                    .collect(),
+                Box::new(Self::from_type(signature.return_type(), params)),
+            ),
+            Type::Compound(CompoundType::List(element)) => {
+                Self::list(Self::from_type(element, params))
+            }
+            Type::Compound(CompoundType::Tuple(elements)) => Self::tuple(
+                elements
+                    .iter()
+                    .map(|element| Self::from_type(element, params))
+                    .collect(),
+            ),
+            primitive => Self::con(&primitive.to_string()),
+        }
+    }
+
+    /// Converts back to a source type, if there are no variables left.
+    pub fn to_type(&self) -> Option<Type> {
+        match self {
+            Self::Var(_) | Self::Integer(_) | Self::Float(_) | Self::Error => None,
+            Self::Con(name, args) if name == LIST => Some(Type::list(args[0].to_type()?)),
+            Self::Con(name, args) if name == TUPLE => Some(Type::tuple(
+                args.iter().map(Self::to_type).collect::<Option<_>>()?,
+            )),
+            Self::Con(name, args) if args.is_empty() => {
+                Some(Type::from_str(name).unwrap_or_else(|_| Type::Custom(name.clone(), vec![])))
+            }
+            Self::Con(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.to_type().map(|ty| ty.to_string()))
+                    .collect::<Option<_>>()?;
+                Some(Type::Custom(name.clone(), args))
+            }
+            Self::Fun(args, ret) => {
+                let args = args.iter().map(Self::to_type).collect::<Option<_>>()?;
+                Some(Type::Function(FunctionSignature::new(args, ret.to_type()?)))
+            }
+        }
+    }
+
+    /// The variables a type can be generalised over. Those of literals are
+    /// left out: a literal has a single type, even if it's not known yet.
+    pub fn free_vars(&self) -> BTreeSet<TypeVar> {
+        let mut vars = BTreeSet::new();
+        self.collect_vars(&mut vars);
+        vars
+    }
+
+    fn collect_vars(&self, vars: &mut BTreeSet<TypeVar>) {
+        match self {
+            Self::Var(var) => {
+                vars.insert(*var);
+            }
+            Self::Con(_, args) => args.iter().for_each(|arg| arg.collect_vars(vars)),
+            Self::Fun(args, ret) => {
+                args.iter().for_each(|arg| arg.collect_vars(vars));
+                ret.collect_vars(vars);
+            }
+            Self::Integer(_) | Self::Float(_) | Self::Error => {}
+        }
+    }
+
+    pub fn occurs(&self, var: TypeVar) -> bool {
+        match self {
+            Self::Var(v) | Self::Integer(v) | Self::Float(v) => *v == var,
+            Self::Con(_, args) => args.iter().any(|arg| arg.occurs(var)),
+            Self::Fun(args, ret) => args.iter().any(|arg| arg.occurs(var)) || ret.occurs(var),
+            Self::Error => false,
+        }
+    }
+
+    pub fn contains_error(&self) -> bool {
+        match self {
+            Self::Var(_) | Self::Integer(_) | Self::Float(_) => false,
+            Self::Con(_, args) => args.iter().any(Self::contains_error),
+            Self::Fun(args, ret) => args.iter().any(Self::contains_error) || ret.contains_error(),
+            Self::Error => true,
+        }
+    }
+
+    /// Replaces variables according to `mapping`, leaving the others alone.
+    pub fn rename(&self, mapping: &BTreeMap<TypeVar, Self>) -> Self {
+        match self {
+            Self::Var(var) => mapping.get(var).cloned().unwrap_or_else(|| self.clone()),
+            Self::Con(name, args) => Self::Con(
+                name.clone(),
+                args.iter().map(|arg| arg.rename(mapping)).collect(),
+            ),
+            Self::Fun(args, ret) => Self::Fun(
+                args.iter().map(|arg| arg.rename(mapping)).collect(),
+                Box::new(ret.rename(mapping)),
+            ),
+            Self::Integer(_) | Self::Float(_) | Self::Error => self.clone(),
+        }
+    }
+
+    /// The literal types in `self` that aren't known yet.
+    pub fn literals(&self) -> Vec<Self> {
+        match self {
+            Self::Integer(_) | Self::Float(_) => vec![self.clone()],
+            Self::Con(_, args) => args.iter().flat_map(Self::literals).collect(),
+            Self::Fun(args, ret) => args
+                .iter()
+                .chain(std::iter::once(&**ret))
+                .flat_map(Self::literals)
+                .collect(),
+            Self::Var(_) | Self::Error => vec![],
+        }
+    }
+
+    /// Whether a literal of type `self`'s variable can have type `ty`.
+    pub fn accepts(&self, ty: &Self) -> bool {
+        let Self::Con(name, args) = ty else {
+            return false;
+        };
+        let Ok(ty) = Type::from_str(name) else {
+            return false;
+        };
+        args.is_empty()
+            && match self {
+                Self::Integer(_) => ty.is_integer(),
+                Self::Float(_) => ty.is_float(),
+                _ => false,
+            }
+    }
+}
+
+impl Scheme {
+    /// A scheme without quantified variables.
+    pub const fn mono(ty: Ty) -> Self {
+        Self {
+            vars: vec![],
+            ty,
+            bounds: vec![],
+        }
+    }
+
+    /// The free variables of the type that the scheme doesn't quantify over:
+    /// those it shares with the environment.
+    pub fn free_vars(&self) -> BTreeSet<TypeVar> {
+        let mut vars = self.ty.free_vars();
+        for var in &self.vars {
+            vars.remove(var);
+        }
+        vars
+    }
+}
+
+/// Unknown types print as `_`, like in `Option<_>`, and those of literals as
+/// `{integer}` or `{float}`.
+impl Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Var(_) | Self::Error => write!(f, "_"),
+            Self::Integer(_) => write!(f, "{{integer}}"),
+            Self::Float(_) => write!(f, "{{float}}"),
+            Self::Con(name, args) if name == LIST => write!(f, "[{}]", args[0]),
+            Self::Con(name, args) if name == TUPLE => {
+                let args: Vec<_> = args.iter().map(ToString::to_string).collect();
+                write!(f, "({})", args.join(", "))
+            }
+            Self::Con(name, args) if args.is_empty() => write!(f, "{name}"),
+            Self::Con(name, args) => {
+                let args: Vec<_> = args.iter().map(ToString::to_string).collect();
+                write!(f, "{name}<{}>", args.join(", "))
+            }
+            Self::Fun(args, ret) => {
+                let args: Vec<_> = args.iter().map(ToString::to_string).collect();
+                write!(f, "({}) -> {ret}", args.join(", "))
+            }
+        }
+    }
+}
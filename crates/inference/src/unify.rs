@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use crate::ty::{Ty, TypeVar};
+
+/// What the type variables solved so far stand for.
+#[derive(Debug, Default)]
+pub struct Substitution(BTreeMap<TypeVar, Ty>);
+
+#[derive(Debug, PartialEq)]
+pub enum UnifyError {
+    /// The types have different shapes.
+    Mismatch,
+    /// Solving would need an infinite type, like `A = Option<A>`.
+    Occurs(TypeVar, Ty),
+}
+
+impl Substitution {
+    pub fn bind(&mut self, var: TypeVar, ty: Ty) {
+        self.0.insert(var, ty);
+    }
+
+    /// `ty` with every solved variable replaced by its solution.
+    pub fn apply(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(var) | Ty::Integer(var) | Ty::Float(var) => match self.0.get(var) {
+                Some(solution) => self.apply(solution),
+                None => ty.clone(),
+            },
+            Ty::Con(name, args) => Ty::Con(
+                name.clone(),
+                args.iter().map(|arg| self.apply(arg)).collect(),
+            ),
+            Ty::Fun(args, ret) => Ty::Fun(
+                args.iter().map(|arg| self.apply(arg)).collect(),
+                Box::new(self.apply(ret)),
+            ),
+            Ty::Error => Ty::Error,
+        }
+    }
+
+    /// Solves `a = b`, extending the substitution.
+    ///
+    /// # Errors
+    /// When `a` and `b` can't be made equal. The substitution may then be
+    /// partially extended, which only matters for the quality of later errors.
+    pub fn unify(&mut self, a: &Ty, b: &Ty) -> Result<(), UnifyError> {
+        match (self.apply(a), self.apply(b)) {
+            (Ty::Error, _) | (_, Ty::Error) => Ok(()),
+            (Ty::Var(a), Ty::Var(b)) if a == b => Ok(()),
+            (Ty::Var(var), ty) | (ty, Ty::Var(var)) => {
+                if ty.occurs(var) {
+                    return Err(UnifyError::Occurs(var, ty));
+                }
+                self.bind(var, ty);
+                Ok(())
+            }
+            (Ty::Integer(a), Ty::Integer(b)) | (Ty::Float(a), Ty::Float(b)) if a == b => Ok(()),
+            (Ty::Integer(var), ty @ Ty::Integer(_)) | (Ty::Float(var), ty @ Ty::Float(_)) => {
+                self.bind(var, ty);
+                Ok(())
+            }
+            (literal @ (Ty::Integer(var) | Ty::Float(var)), ty)
+            | (ty, literal @ (Ty::Integer(var) | Ty::Float(var))) => {
+                if !literal.accepts(&ty) {
+                    return Err(UnifyError::Mismatch);
+                }
+                self.bind(var, ty);
+                Ok(())
+            }
+            (Ty::Con(a, a_args), Ty::Con(b, b_args)) => {
+                if a != b || a_args.len() != b_args.len() {
+                    return Err(UnifyError::Mismatch);
+                }
+                a_args
+                    .iter()
+                    .zip(&b_args)
+                    .try_for_each(|(a, b)| self.unify(a, b))
+            }
+            (Ty::Fun(a_args, a_ret), Ty::Fun(b_args, b_ret)) => {
+                if a_args.len() != b_args.len() {
+                    return Err(UnifyError::Mismatch);
+                }
+                a_args
+                    .iter()
+                    .zip(&b_args)
+                    .try_for_each(|(a, b)| self.unify(a, b))?;
+                self.unify(&a_ret, &b_ret)
+            }
+            _ => Err(UnifyError::Mismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(ty: Ty) -> Ty {
+        Ty::Con("Option".to_owned(), vec![ty])
+    }
+
+    #[test]
+    fn test_unify_binds_variables() {
+        let mut subst = Substitution::default();
+        let a = Ty::Var(TypeVar(0));
+        subst
+            .unify(&option(a.clone()), &option(Ty::con("U8")))
+            .unwrap();
+        assert_eq!(subst.apply(&a), Ty::con("U8"));
+    }
+
+    #[test]
+    fn test_unify_follows_chains() {
+        let mut subst = Substitution::default();
+        let (a, b) = (Ty::Var(TypeVar(0)), Ty::Var(TypeVar(1)));
+        subst.unify(&a, &b).unwrap();
+        subst.unify(&b, &Ty::con("Bool")).unwrap();
+        assert_eq!(subst.apply(&a), Ty::con("Bool"));
+    }
+
+    #[test]
+    fn test_unify_mismatch() {
+        let mut subst = Substitution::default();
+        let result = subst.unify(&option(Ty::con("U8")), &option(Ty::con("Bool")));
+        assert_eq!(result, Err(UnifyError::Mismatch));
+    }
+
+    #[test]
+    fn test_literals_only_become_numbers() {
+        let mut subst = Substitution::default();
+        let (int, float) = (Ty::Integer(TypeVar(0)), Ty::Float(TypeVar(1)));
+        assert_eq!(
+            subst.unify(&int, &Ty::con("Bool")),
+            Err(UnifyError::Mismatch)
+        );
+        assert_eq!(subst.unify(&int, &float), Err(UnifyError::Mismatch));
+        assert_eq!(
+            subst.unify(&float, &Ty::con("U8")),
+            Err(UnifyError::Mismatch)
+        );
+        subst.unify(&Ty::Var(TypeVar(2)), &int).unwrap();
+        subst.unify(&Ty::Var(TypeVar(2)), &Ty::con("U32")).unwrap();
+        assert_eq!(subst.apply(&int), Ty::con("U32"));
+    }
+
+    #[test]
+    fn test_occurs_check() {
+        let mut subst = Substitution::default();
+        let a = Ty::Var(TypeVar(0));
+        let result = subst.unify(&a, &option(a.clone()));
+        assert_eq!(result, Err(UnifyError::Occurs(TypeVar(0), option(a))));
+    }
+}
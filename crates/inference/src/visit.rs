@@ -0,0 +1,155 @@
+use ast::{
+    expressions::{Expression, ExpressionKind, MatchBody, StringPart},
+    functions::FunctionBody,
+    statements::{Block, StatementKind},
+};
+
+/// Calls `f` on every expression of `expr`, innermost first.
+pub fn walk_expr(expr: &Expression, f: &mut impl FnMut(&Expression)) {
+    match &expr.kind {
+        ExpressionKind::NewEnumInstance(_, _, args)
+        | ExpressionKind::List(args)
+        | ExpressionKind::Tuple(args)
+        | ExpressionKind::FunctionCall {
+            arguments: args, ..
+        } => args.iter().for_each(|arg| walk_expr(arg, f)),
+        ExpressionKind::MethodCall {
+            receiver,
+            arguments,
+            ..
+        } => {
+            walk_expr(receiver, f);
+            arguments.iter().for_each(|arg| walk_expr(arg, f));
+        }
+        ExpressionKind::NewRecordInstance(_, fields) => {
+            fields.iter().for_each(|(_, value)| walk_expr(value, f));
+        }
+        ExpressionKind::Match(scrutinee, arms) => {
+            walk_expr(scrutinee, f);
+            for arm in arms {
+                match &arm.body {
+                    MatchBody::Block(block) => walk_block(block, f),
+                    MatchBody::Expr(body) => walk_expr(body, f),
+                }
+            }
+        }
+        ExpressionKind::If(condition, then, otherwise) => {
+            walk_expr(condition, f);
+            walk_block(then, f);
+            walk_block(otherwise, f);
+        }
+        ExpressionKind::BinaryOp(lhs, _, rhs) => {
+            walk_expr(lhs, f);
+            walk_expr(rhs, f);
+        }
+        ExpressionKind::UnaryOp(_, operand) | ExpressionKind::Lambda(_, operand) => {
+            walk_expr(operand, f);
+        }
+        ExpressionKind::Interpolation(parts) => {
+            for part in parts {
+                if let StringPart::Hole(hole) = part {
+                    walk_expr(hole, f);
+                }
+            }
+        }
+        ExpressionKind::Identifier(_)
+        | ExpressionKind::Literal(_)
+        | ExpressionKind::RecordAccess(..)
+        | ExpressionKind::Unit => {}
+    }
+    f(expr);
+}
+
+pub fn walk_block(block: &Block, f: &mut impl FnMut(&Expression)) {
+    for statement in &block.statements {
+        match &statement.kind {
+            StatementKind::Assignment(_, _, value) | StatementKind::Return(value) => {
+                walk_expr(value, f);
+            }
+        }
+    }
+    walk_expr(&block.return_expr, f);
+}
+
+pub fn walk_body(body: &FunctionBody, f: &mut impl FnMut(&Expression)) {
+    match body {
+        FunctionBody::SingleLine(expr) => walk_expr(expr, f),
+        FunctionBody::MultiLine(block) => walk_block(block, f),
+    }
+}
+
+/// [`walk_expr`], for rewriting expressions in place.
+pub fn walk_expr_mut(expr: &mut Expression, f: &mut impl FnMut(&mut Expression)) {
+    match &mut expr.kind {
+        ExpressionKind::NewEnumInstance(_, _, args)
+        | ExpressionKind::List(args)
+        | ExpressionKind::Tuple(args)
+        | ExpressionKind::FunctionCall {
+            arguments: args, ..
+        } => args.iter_mut().for_each(|arg| walk_expr_mut(arg, f)),
+        ExpressionKind::MethodCall {
+            receiver,
+            arguments,
+            ..
+        } => {
+            walk_expr_mut(receiver, f);
+            arguments.iter_mut().for_each(|arg| walk_expr_mut(arg, f));
+        }
+        ExpressionKind::NewRecordInstance(_, fields) => {
+            fields
+                .iter_mut()
+                .for_each(|(_, value)| walk_expr_mut(value, f));
+        }
+        ExpressionKind::Match(scrutinee, arms) => {
+            walk_expr_mut(scrutinee, f);
+            for arm in arms {
+                match &mut arm.body {
+                    MatchBody::Block(block) => walk_block_mut(block, f),
+                    MatchBody::Expr(body) => walk_expr_mut(body, f),
+                }
+            }
+        }
+        ExpressionKind::If(condition, then, otherwise) => {
+            walk_expr_mut(condition, f);
+            walk_block_mut(then, f);
+            walk_block_mut(otherwise, f);
+        }
+        ExpressionKind::BinaryOp(lhs, _, rhs) => {
+            walk_expr_mut(lhs, f);
+            walk_expr_mut(rhs, f);
+        }
+        ExpressionKind::UnaryOp(_, operand) | ExpressionKind::Lambda(_, operand) => {
+            walk_expr_mut(operand, f);
+        }
+        ExpressionKind::Interpolation(parts) => {
+            for part in parts {
+                if let StringPart::Hole(hole) = part {
+                    walk_expr_mut(hole, f);
+                }
+            }
+        }
+        ExpressionKind::Identifier(_)
+        | ExpressionKind::Literal(_)
+        | ExpressionKind::RecordAccess(..)
+        | ExpressionKind::Unit => {}
+    }
+    f(expr);
+}
+
+pub fn walk_block_mut(block: &mut Block, f: &mut impl FnMut(&mut Expression)) {
+    for statement in &mut block.statements {
+        match &mut statement.kind {
+            StatementKind::Assignment(_, _, value) | StatementKind::Return(value) => {
+                walk_expr_mut(value, f);
+            }
+        }
+    }
+    walk_expr_mut(&mut block.return_expr, f);
+}
+
+pub fn walk_body_mut(body: &mut FunctionBody, f: &mut impl FnMut(&mut Expression)) {
+    match body {
+        FunctionBody::SingleLine(expr) => walk_expr_mut(expr, f),
+        FunctionBody::MultiLine(block) => walk_block_mut(block, f),
+    }
+}
@@ -3,11 +3,13 @@ use ast::types::{
     custom::{CustomTypeContent, Field, Variant},
 };
 use winnow::{
-    Parser, Result,
-    combinator::{alt, delimited, opt, separated, terminated},
+    ModalResult, Parser,
+    combinator::{alt, cut_err, delimited, opt, preceded, separated, terminated},
+    error::{StrContext, StrContextValue},
 };
 
 use crate::{
+    Input,
     identifiers::{parse_identifier_lower, parse_identifier_upper},
     keyword,
     types::parse_type,
@@ -16,16 +18,28 @@ use crate::{
 
 /// Custom types are defined as follows:
 /// `type CustomType { VariantA, VariantB }`
-pub fn parse_custom_type_definition(input: &mut &str) -> Result<CustomType> {
+pub fn parse_custom_type_definition(input: &mut Input<'_>) -> ModalResult<CustomType> {
     let _ = ws(keyword("type")).parse_next(input)?;
-    let name = ws(parse_identifier_upper).parse_next(input)?;
+    cut_err(parse_custom_type_rest).parse_next(input)
+}
+
+/// Everything after the `type` keyword, which commits to a type definition.
+fn parse_custom_type_rest(input: &mut Input<'_>) -> ModalResult<CustomType> {
+    let name = ws(parse_identifier_upper)
+        .context(StrContext::Expected(StrContextValue::Description(
+            "type name",
+        )))
+        .parse_next(input)?;
     let generics = opt(parse_custom_type_generics)
         .parse_next(input)?
         .unwrap_or_default();
-    let content = opt(delimited(
+    let content = opt(preceded(
         ws('{'),
-        terminated(parse_custom_type_contents, opt(ws(','))),
-        ws('}'),
+        cut_err(terminated(
+            terminated(parse_custom_type_contents, opt(ws(',')))
+                .context(StrContext::Label("contents")),
+            ws('}').context(StrContext::Expected(StrContextValue::CharLiteral('}'))),
+        )),
     ))
     .parse_next(input)?;
     Ok(CustomType::new(name, content, generics))
@@ -34,7 +48,7 @@ pub fn parse_custom_type_definition(input: &mut &str) -> Result<CustomType> {
 /// Generic parameters/arguments in angle brackets: `<A>`, `<A, B>`.
 /// Used for both type definitions (`type Option<A>`), type references
 /// (`Option<A>`), and function type-variable definitions (`map<A, B>(...)`).
-pub fn parse_custom_type_generics(input: &mut &str) -> Result<Vec<String>> {
+pub fn parse_custom_type_generics(input: &mut Input<'_>) -> ModalResult<Vec<String>> {
     delimited(
         '<',
         separated(1.., parse_identifier_upper.map(ToOwned::to_owned), ws(',')),
@@ -43,7 +57,7 @@ pub fn parse_custom_type_generics(input: &mut &str) -> Result<Vec<String>> {
     .parse_next(input)
 }
 
-fn parse_custom_type_contents(input: &mut &str) -> Result<CustomTypeContent> {
+fn parse_custom_type_contents(input: &mut Input<'_>) -> ModalResult<CustomTypeContent> {
     alt((
         parse_variants.map(|v: Vec<Variant>| {
             CustomTypeContent::Enum(
@@ -62,22 +76,22 @@ fn parse_custom_type_contents(input: &mut &str) -> Result<CustomTypeContent> {
     .parse_next(input)
 }
 
-fn parse_variants(input: &mut &str) -> Result<Vec<Variant>> {
+fn parse_variants(input: &mut Input<'_>) -> ModalResult<Vec<Variant>> {
     separated(1.., parse_variant, ws(',')).parse_next(input)
 }
 
-fn parse_fields(input: &mut &str) -> Result<Vec<(String, Type)>> {
+fn parse_fields(input: &mut Input<'_>) -> ModalResult<Vec<(String, Type)>> {
     separated(1.., parse_field, ws(',')).parse_next(input)
 }
 
-fn parse_variant(input: &mut &str) -> Result<Variant> {
+fn parse_variant(input: &mut Input<'_>) -> ModalResult<Variant> {
     let name = parse_identifier_upper(input)?;
     opt(delimited('(', separated(0.., parse_type, ws(',')), ')'))
         .map(|types| Variant::new(name, types.unwrap_or_default()))
         .parse_next(input)
 }
 
-fn parse_field(input: &mut &str) -> Result<(String, Type)> {
+fn parse_field(input: &mut Input<'_>) -> ModalResult<(String, Type)> {
     let name = parse_identifier_lower(input)?;
     let _ = ws(':').parse_next(input)?;
     let ty = parse_type(input)?;
@@ -86,6 +100,7 @@ fn parse_field(input: &mut &str) -> Result<(String, Type)> {
 
 #[cfg(test)]
 mod tests {
+    use crate::test_input;
     use ast::types::{
         Type,
         custom::{CustomTypeContent, Field, Variant},
@@ -93,7 +108,7 @@ mod tests {
 
     #[test]
     fn test_parse_empty_custom_type() {
-        let mut input = "type Phantom";
+        let mut input = test_input("type Phantom");
         let parsed = super::parse_custom_type_definition(&mut input).unwrap();
         assert_eq!(parsed.name(), "Phantom");
         assert_eq!(parsed.content(), None);
@@ -101,7 +116,7 @@ mod tests {
 
     #[test]
     fn test_parse_custom_type_variants() {
-        let mut input = "type CustomType { VariantA, VariantB, }";
+        let mut input = test_input("type CustomType { VariantA, VariantB, }");
         let parsed = super::parse_custom_type_definition(&mut input).unwrap();
         assert_eq!(parsed.name(), "CustomType");
         assert_eq!(
@@ -115,7 +130,7 @@ mod tests {
 
     #[test]
     fn test_parse_custom_type_generics() {
-        let mut input = "type Option<A> { Some(A), None }";
+        let mut input = test_input("type Option<A> { Some(A), None }");
         let parsed = super::parse_custom_type_definition(&mut input).unwrap();
         assert_eq!(parsed.name(), "Option");
         assert_eq!(
@@ -132,7 +147,7 @@ mod tests {
 
     #[test]
     fn test_parse_custom_type_record() {
-        let mut input = "type CustomType { admin: Bool, age: U8, }";
+        let mut input = test_input("type CustomType { admin: Bool, age: U8, }");
         let parsed = super::parse_custom_type_definition(&mut input).unwrap();
         assert_eq!(parsed.name(), "CustomType");
         assert_eq!(
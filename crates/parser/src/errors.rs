@@ -0,0 +1,243 @@
+use ast::{
+    diagnostics::{Diagnostic, codes},
+    span::Span,
+};
+use winnow::{
+    error::{ContextError, ErrMode, StrContext, StrContextValue},
+    stream::{Location, Stream},
+};
+
+use crate::Input;
+
+/// Labels of the top-level items, used to say what was being parsed.
+const ITEM_LABELS: [&str; 6] = [
+    "CustomType",
+    "Trait",
+    "Impl",
+    "EntryPoint",
+    "FunctionDef",
+    "FunctionImpl",
+];
+
+/// Turns the error of a failed parser into an "expected X, found Y"
+/// diagnostic, pointing at the input the parser stopped at.
+///
+/// X comes from the innermost `StrContext::Expected` values or, failing
+/// that, the innermost `StrContext::Label`. The outermost item label, if any,
+/// becomes a note.
+pub fn syntax_error(input: &Input<'_>, error: &ErrMode<ContextError>) -> Diagnostic {
+    // Report the next token, not the whitespace before it.
+    let rest = input.peek_finish();
+    let found = found_token(rest.trim_start());
+    let offset = input.current_token_start() + rest.len() - rest.trim_start().len();
+    let span = Span::new(input.state.file, offset, offset + found.len());
+
+    let context: Vec<&StrContext> = match error {
+        ErrMode::Backtrack(e) | ErrMode::Cut(e) => e.context().collect(),
+        ErrMode::Incomplete(_) => vec![],
+    };
+    let expected: Vec<String> = context
+        .iter()
+        .filter_map(|c| match c {
+            StrContext::Expected(value) => Some(describe_value(value)),
+            _ => None,
+        })
+        .collect();
+    let labels: Vec<&str> = context
+        .iter()
+        .filter_map(|c| match c {
+            StrContext::Label(label) => Some(*label),
+            _ => None,
+        })
+        .collect();
+
+    let expected = if expected.is_empty() {
+        labels.first().map(|l| describe_label(l).to_owned())
+    } else {
+        Some(expected.join(" or "))
+    };
+    let found = if found.is_empty() {
+        "end of file".to_owned()
+    } else {
+        format!("`{found}`")
+    };
+    let mut diagnostic = match expected {
+        Some(expected) => Diagnostic::error(format!("expected {expected}, found {found}"))
+            .with_primary(span, format!("expected {expected}")),
+        None => Diagnostic::error(format!("unexpected {found}")).with_primary(span, "unexpected"),
+    }
+    .with_code(codes::SYNTAX);
+
+    if let Some(item) = labels.iter().rev().find(|l| ITEM_LABELS.contains(l))
+        && labels.first() != Some(item)
+    {
+        diagnostic = diagnostic.with_note(format!("while parsing a {}", describe_label(item)));
+    }
+    diagnostic
+}
+
+/// How many bytes to skip, from the start of a malformed top-level item, to
+/// get to the next place where parsing can resume.
+///
+/// That is just past the `;` or the closing `}` (and a `;` right after it)
+/// ending the item, or the start of the next line that looks like a new item
+/// (`type`, `trait`, `impl`, `main` or a function header in the first column),
+/// whichever comes first. Braces inside string literals are ignored.
+pub fn skip_item(rest: &str) -> usize {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    let after = &rest[i + 1..];
+                    return match after.trim_start().strip_prefix(';') {
+                        Some(tail) => rest.len() - tail.len(),
+                        None => i + 1,
+                    };
+                }
+            }
+            ';' if depth == 0 => return i + 1,
+            '\n' if starts_item(&rest[i + 1..]) => return i + 1,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+/// How many bytes to skip, from the start of a malformed statement, to get to
+/// the next statement: just past its `;`, or up to the `}` closing the block
+/// it's in.
+pub fn skip_statement(rest: &str) -> usize {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '{' => depth += 1,
+            '}' if depth == 0 => return i,
+            '}' => depth -= 1,
+            ';' if depth == 0 => return i + 1,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+fn starts_item(line: &str) -> bool {
+    let word_len = line
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(line.len());
+    let (word, after) = line.split_at(word_len);
+    match word {
+        "" => false,
+        "type" | "trait" | "impl" | "main" => true,
+        _ => after.starts_with(['(', '<']) || after.starts_with("::"),
+    }
+}
+
+/// The token at the start of `rest`: a whole word, or a single character.
+fn found_token(rest: &str) -> &str {
+    let word_len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let len = match word_len {
+        0 => rest.chars().next().map_or(0, char::len_utf8),
+        n => n,
+    };
+    &rest[..len]
+}
+
+fn describe_value(value: &StrContextValue) -> String {
+    match value {
+        StrContextValue::CharLiteral(c) => format!("`{c}`"),
+        StrContextValue::StringLiteral(s) => format!("`{s}`"),
+        StrContextValue::Description(d) => (*d).to_owned(),
+        _ => value.to_string(),
+    }
+}
+
+fn describe_label(label: &str) -> &str {
+    match label {
+        "TopLevel" => "a type definition, function or `main` block",
+        "CustomType" => "type definition",
+        "Trait" => "trait",
+        "Impl" => "trait implementation",
+        "methods" => "methods",
+        "trait bounds" => "trait bounds",
+        "EntryPoint" => "`main` block",
+        "FunctionDef" => "function declaration",
+        "FunctionImpl" => "function implementation",
+        "signature" => "function signature",
+        "SignatureArgs" => "parameter types",
+        "sub-function" => "function type",
+        "ArgLabel" => "parameter label",
+        "arguments" => "parameters",
+        "body" => "function body",
+        "contents" => "variants or fields",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_item_stops_after_semicolon() {
+        let rest = "foo(U8 -> U8;\nbar(U8) -> U8;";
+        assert_eq!(&rest[skip_item(rest)..], "\nbar(U8) -> U8;");
+    }
+
+    #[test]
+    fn test_skip_item_skips_nested_braces() {
+        let rest = "main {\n  x = match y { A => 1 };\n  x = ;\n}\ntype T";
+        assert_eq!(&rest[skip_item(rest)..], "\ntype T");
+    }
+
+    #[test]
+    fn test_skip_item_eats_semicolon_after_block() {
+        let rest = "f(x) = match x {\n  1 => 2\n};\nmain {}";
+        assert_eq!(&rest[skip_item(rest)..], "\nmain {}");
+    }
+
+    #[test]
+    fn test_skip_statement_stops_at_end_of_block() {
+        let rest = "x = f({ 1 }) y; z = 2; }";
+        assert_eq!(&rest[skip_statement(rest)..], " z = 2; }");
+        let rest = "x = 1 + }";
+        assert_eq!(&rest[skip_statement(rest)..], "}");
+    }
+
+    #[test]
+    fn test_skip_item_stops_at_next_item_when_braces_are_unbalanced() {
+        let rest = "type T { A, B\nmain {}";
+        assert_eq!(&rest[skip_item(rest)..], "main {}");
+    }
+
+    #[test]
+    fn test_skip_item_stops_at_traits_and_impls() {
+        let rest = "f(U8) -> U8\ntrait S { s(Self) -> ; }";
+        assert_eq!(&rest[skip_item(rest)..], "trait S { s(Self) -> ; }");
+        let rest = "f(U8) -> U8\nimpl S for U8 { }";
+        assert_eq!(&rest[skip_item(rest)..], "impl S for U8 { }");
+    }
+
+    #[test]
+    fn test_skip_item_ignores_braces_in_strings() {
+        let rest = "main { x = \"}\"; }\nmain";
+        assert_eq!(&rest[skip_item(rest)..], "\nmain");
+    }
+
+    #[test]
+    fn test_found_token() {
+        assert_eq!(found_token("foo(x)"), "foo");
+        assert_eq!(found_token("; x"), ";");
+        assert_eq!(found_token(""), "");
+    }
+}
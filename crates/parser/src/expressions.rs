@@ -1,37 +1,45 @@
-use ast::expressions::{BinaryOp, Expression, Literal, MatchArm, MatchBody, UnaryOp};
+use ast::{
+    expressions::{
+        BinaryOp, Expression, ExpressionKind, Literal, MatchArm, MatchBody, StringPart, UnaryOp,
+    },
+    statements::Block,
+};
 use winnow::{
-    Parser, Result, dispatch,
+    ModalResult, Parser,
     ascii::{dec_uint, hex_uint, multispace0, multispace1},
     combinator::{
-        Infix, Prefix, alt, delimited, empty, expression, fail, not, opt, peek, preceded,
-        repeat, separated, separated_pair, terminated,
+        Infix, alt, cut_err, delimited, empty, expression, fail, not, opt, peek, preceded, repeat,
+        separated, separated_pair, terminated,
     },
-    error::ContextError,
+    dispatch,
+    error::{ContextError, ErrMode, StrContext, StrContextValue},
     token::{any, none_of, take_while},
 };
 
 use crate::{
+    Input,
     identifiers::{parse_identifier_lower, parse_identifier_upper},
-    keyword,
+    keyword, spanned,
     statements::parse_block,
     ws,
 };
 
-// Binding powers (higher binds tighter), following Rust's operator
-// precedence: `||` < `&&` < comparisons (non-associative) < `^`
+// Binding powers (higher binds tighter): `|>` binds loosest, then Rust's
+// operator precedence: `||` < `&&` < comparisons (non-associative) < `^`
 // < `+`/`-` < `*`/`/`/`%` < prefix `-`/`!`.
-const OR_POWER: i64 = 1;
-const AND_POWER: i64 = 3;
-const CMP_POWER: i64 = 5;
-const XOR_POWER: i64 = 7;
-const ADD_POWER: i64 = 9;
-const MUL_POWER: i64 = 11;
-const PREFIX_POWER: i64 = 13;
+const PIPE_POWER: i64 = 1;
+const OR_POWER: i64 = 3;
+const AND_POWER: i64 = 5;
+const CMP_POWER: i64 = 7;
+const XOR_POWER: i64 = 9;
+const ADD_POWER: i64 = 11;
+const MUL_POWER: i64 = 13;
 
 macro_rules! infix_fold {
     ($name:ident, $op:expr) => {
-        fn $name(_: &mut &str, l: Expression, r: Expression) -> Result<Expression> {
-            Ok(Expression::binary_op(l, $op, r))
+        fn $name(_: &mut Input<'_>, l: Expression, r: Expression) -> ModalResult<Expression> {
+            let span = l.span().to(r.span());
+            Ok(Expression::binary_op(l, $op, r).with_span(span))
         }
     };
 }
@@ -51,28 +59,42 @@ infix_fold!(fold_mul, BinaryOp::Mul);
 infix_fold!(fold_div, BinaryOp::Div);
 infix_fold!(fold_mod, BinaryOp::Mod);
 
-fn fold_negate(_: &mut &str, e: Expression) -> Result<Expression> {
-    Ok(Expression::unary_op(UnaryOp::Negate, e))
-}
-
-fn fold_not(_: &mut &str, e: Expression) -> Result<Expression> {
-    Ok(Expression::unary_op(UnaryOp::Not, e))
+/// `x |> f(y)` is sugar for `f(x, y)`, and `x |> f` for `f(x)`.
+fn fold_pipe(_: &mut Input<'_>, l: Expression, r: Expression) -> ModalResult<Expression> {
+    let span = l.span().to(r.span());
+    let call = match r.kind {
+        ExpressionKind::FunctionCall {
+            namespace,
+            name,
+            mut arguments,
+        } => {
+            arguments.insert(0, l);
+            Expression::namespaced_function_call(namespace, name, arguments)
+        }
+        ExpressionKind::Identifier(name) => Expression::function_call(name, vec![l]),
+        _ => {
+            let mut error = ContextError::new();
+            error.push(StrContext::Expected(StrContextValue::Description(
+                "a function call after `|>`",
+            )));
+            return Err(ErrMode::Cut(error));
+        }
+    };
+    Ok(call.with_span(span))
 }
 
 /// Expressions are parsed with a Pratt parser (`expression`).
 ///
 /// Operator precedence and associativity are handled declaratively. Operands
-/// are atoms; prefix and infix operators are dispatched on their first
-/// character.
-pub fn parse_expr(input: &mut &str) -> Result<Expression> {
-    expression(preceded(multispace0, parse_atom))
-        .prefix(dispatch! {any;
-            '-' => empty.value(Prefix(PREFIX_POWER, fold_negate)),
-            '!' => empty.value(Prefix(PREFIX_POWER, fold_not)),
-            _ => fail,
-        })
+/// are atoms, optionally preceded by prefix operators; infix operators are
+/// dispatched on their first character.
+pub fn parse_expr(input: &mut Input<'_>) -> ModalResult<Expression> {
+    expression(preceded(multispace0, parse_operand))
         .infix(dispatch! {ws(any);
-            '|' => '|'.value(Infix::Left(OR_POWER, fold_or)),
+            '|' => alt((
+                '|'.value(Infix::Left(OR_POWER, fold_or as fn(&mut Input<'_>, Expression, Expression) -> ModalResult<Expression>)),
+                '>'.value(Infix::Left(PIPE_POWER, fold_pipe)),
+            )),
             '&' => '&'.value(Infix::Left(AND_POWER, fold_and)),
             '^' => empty.value(Infix::Left(XOR_POWER, fold_xor)),
             '=' => '='.value(Infix::Neither(CMP_POWER, fold_eq)),
@@ -80,7 +102,7 @@ pub fn parse_expr(input: &mut &str) -> Result<Expression> {
             '>' => opt('=').map(|e| Infix::Neither(
                 CMP_POWER,
                 if e.is_some() {
-                    fold_greater_eq as fn(&mut &str, Expression, Expression) -> Result<Expression>
+                    fold_greater_eq as fn(&mut Input<'_>, Expression, Expression) -> ModalResult<Expression>
                 } else {
                     fold_greater
                 },
@@ -88,7 +110,7 @@ pub fn parse_expr(input: &mut &str) -> Result<Expression> {
             '<' => opt('=').map(|e| Infix::Neither(
                 CMP_POWER,
                 if e.is_some() {
-                    fold_less_eq as fn(&mut &str, Expression, Expression) -> Result<Expression>
+                    fold_less_eq as fn(&mut Input<'_>, Expression, Expression) -> ModalResult<Expression>
                 } else {
                     fold_less
                 },
@@ -101,15 +123,47 @@ pub fn parse_expr(input: &mut &str) -> Result<Expression> {
             '%' => empty.value(Infix::Left(MUL_POWER, fold_mod)),
             _ => fail,
         })
+        .context(StrContext::Label("expression"))
         .parse_next(input)
 }
 
-/// An operand of the expression grammar, dispatched on its first character.
-fn parse_atom(input: &mut &str) -> Result<Expression> {
-    dispatch! {peek(any);
-        '"' => parse_string.map(|s| Expression::literal(Literal::String(s))),
+/// Prefix `-`/`!` bind tighter than every infix operator, so they're parsed
+/// as part of the operand rather than through `expression`'s prefix table:
+/// this way the span of `-x` starts at the `-`.
+fn parse_operand(input: &mut Input<'_>) -> ModalResult<Expression> {
+    alt((
+        spanned((
+            alt(('-'.value(UnaryOp::Negate), '!'.value(UnaryOp::Not))),
+            preceded(multispace0, parse_operand),
+        ))
+        .map(|((op, e), span)| Expression::unary_op(op, e).with_span(span)),
+        parse_atom,
+    ))
+    .parse_next(input)
+}
+
+/// An operand of the expression grammar, dispatched on its first character,
+/// and the methods called on it: `opt.map(f)`.
+fn parse_atom(input: &mut Input<'_>) -> ModalResult<Expression> {
+    let mut expr = parse_primary(input)?;
+    while let Some(((name, args), span)) = opt(spanned(preceded(
+        (multispace0, '.'),
+        (parse_identifier_lower, parse_function_call_args),
+    )))
+    .parse_next(input)?
+    {
+        let span = expr.span().to(span);
+        expr = Expression::method_call(expr, name.to_owned(), args).with_span(span);
+    }
+    Ok(expr)
+}
+
+fn parse_primary(input: &mut Input<'_>) -> ModalResult<Expression> {
+    spanned(dispatch! {peek(any);
+        '"' => parse_string,
         c if c.is_ascii_digit() => parse_number.map(Expression::literal),
         '(' => parse_paren,
+        '[' => parse_list,
         c if c.is_ascii_uppercase() => alt((
             parse_bool.map(|b| Expression::literal(Literal::Bool(b))),
             parse_enum_instance,
@@ -118,27 +172,62 @@ fn parse_atom(input: &mut &str) -> Result<Expression> {
         )),
         _ => alt((
             parse_match,
+            parse_if,
             parse_function_call,
             parse_field_access,
             parse_identifier_expr,
         )),
-    }
+    })
+    .map(|(expr, span)| expr.with_span(span))
     .parse_next(input)
 }
 
-/// `()` (unit) or a parenthesized expression
-fn parse_paren(input: &mut &str) -> Result<Expression> {
-    preceded(
-        '(',
-        alt((
-            ws(')').map(|_| Expression::unit()),
-            terminated(parse_expr, ws(')')),
-        )),
-    )
+/// `()` (unit), a parenthesized expression, a tuple: `(1, True)`, or a
+/// lambda: `(x) -> x + 1`. Only the `->` tells a lambda apart, so it's tried
+/// first.
+fn parse_paren(input: &mut Input<'_>) -> ModalResult<Expression> {
+    alt((
+        parse_lambda,
+        preceded(
+            '(',
+            alt((
+                ws(')').map(|_| Expression::unit()),
+                delimited(
+                    multispace0,
+                    separated(1.., parse_expr, ws(',')),
+                    (multispace0, ')'),
+                )
+                .map(|mut elements: Vec<Expression>| match elements.len() {
+                    1 => elements.remove(0),
+                    _ => Expression::tuple(elements),
+                }),
+            )),
+        ),
+    ))
     .parse_next(input)
 }
 
-pub(crate) fn parse_number(input: &mut &str) -> Result<Literal> {
+/// Example: `(x, y) -> x * y`
+///
+/// Grammar reference: `Lambda = "(" LambdaParams? ")" "->" Expr`
+fn parse_lambda(input: &mut Input<'_>) -> ModalResult<Expression> {
+    let params: Vec<&str> = delimited(
+        ('(', multispace0),
+        terminated(
+            separated(0.., parse_identifier_lower, ws(',')),
+            opt(ws(',')),
+        ),
+        (multispace0, ')'),
+    )
+    .parse_next(input)?;
+    let body = preceded(ws("->"), cut_err(parse_expr)).parse_next(input)?;
+    Ok(Expression::lambda(
+        params.into_iter().map(str::to_owned).collect(),
+        body,
+    ))
+}
+
+pub(crate) fn parse_number(input: &mut Input<'_>) -> ModalResult<Literal> {
     alt((
         // Float before integer: "1.5" must not be consumed as "1" then fail on ".5"
         parse_float.map(Literal::float),
@@ -146,12 +235,12 @@ pub(crate) fn parse_number(input: &mut &str) -> Result<Literal> {
         parse_hex_int.map(Literal::int),
         parse_bin_int.map(Literal::int),
         parse_oct_int.map(Literal::int),
-        dec_uint::<&str, u128, ContextError>.map(Literal::int),
+        dec_uint::<_, u128, ErrMode<ContextError>>.map(Literal::int),
     ))
     .parse_next(input)
 }
 
-fn parse_float(input: &mut &str) -> Result<f64> {
+fn parse_float(input: &mut Input<'_>) -> ModalResult<f64> {
     // Require a decimal point so plain integers don't match as floats.
     // Non-negative: unary minus handles negation in expressions.
     (
@@ -164,11 +253,11 @@ fn parse_float(input: &mut &str) -> Result<f64> {
         .parse_next(input)
 }
 
-fn parse_hex_int(input: &mut &str) -> Result<u128> {
+fn parse_hex_int(input: &mut Input<'_>) -> ModalResult<u128> {
     preceded(alt(("0x", "0X")), hex_uint::<_, u128, _>).parse_next(input)
 }
 
-fn parse_bin_int(input: &mut &str) -> Result<u128> {
+fn parse_bin_int(input: &mut Input<'_>) -> ModalResult<u128> {
     preceded(
         alt(("0b", "0B")),
         take_while(1.., |c: char| c == '0' || c == '1')
@@ -177,7 +266,7 @@ fn parse_bin_int(input: &mut &str) -> Result<u128> {
     .parse_next(input)
 }
 
-fn parse_oct_int(input: &mut &str) -> Result<u128> {
+fn parse_oct_int(input: &mut Input<'_>) -> ModalResult<u128> {
     preceded(
         alt(("0o", "0O")),
         take_while(1.., |c: char| ('0'..='7').contains(&c))
@@ -186,17 +275,40 @@ fn parse_oct_int(input: &mut &str) -> Result<u128> {
     .parse_next(input)
 }
 
-fn parse_identifier_expr(input: &mut &str) -> Result<Expression> {
+fn parse_identifier_expr(input: &mut Input<'_>) -> ModalResult<Expression> {
     parse_identifier_lower
         .parse_next(input)
         .map(|id| Expression::identifier(id.to_owned()))
 }
 
-pub(crate) fn parse_string(input: &mut &str) -> Result<String> {
-    delimited('"', repeat(0.., parse_string_char), '"').parse_next(input)
+/// A string literal, or an interpolated string when it has holes:
+/// `"Hello, {person.name}"`. `\{` is a literal brace.
+pub(crate) fn parse_string(input: &mut Input<'_>) -> ModalResult<Expression> {
+    let pieces: Vec<StringPart> =
+        delimited('"', repeat(0.., parse_string_piece), '"').parse_next(input)?;
+    let mut parts: Vec<StringPart> = vec![];
+    for piece in pieces {
+        match (parts.last_mut(), piece) {
+            (Some(StringPart::Text(text)), StringPart::Text(more)) => text.push_str(&more),
+            (_, piece) => parts.push(piece),
+        }
+    }
+    Ok(match parts.as_slice() {
+        [] => Expression::literal(Literal::String(String::new())),
+        [StringPart::Text(text)] => Expression::literal(Literal::String(text.clone())),
+        _ => Expression::interpolation(parts),
+    })
 }
 
-fn parse_string_char(input: &mut &str) -> Result<char> {
+fn parse_string_piece(input: &mut Input<'_>) -> ModalResult<StringPart> {
+    alt((
+        parse_string_char.map(|c| StringPart::Text(c.to_string())),
+        delimited(('{', multispace0), parse_expr, (multispace0, '}')).map(StringPart::Hole),
+    ))
+    .parse_next(input)
+}
+
+fn parse_string_char(input: &mut Input<'_>) -> ModalResult<char> {
     alt((
         preceded('\\', any).map(|c: char| match c {
             '"' => '"',
@@ -206,12 +318,12 @@ fn parse_string_char(input: &mut &str) -> Result<char> {
             'r' => '\r',
             other => other,
         }),
-        none_of(['"', '\\']),
+        none_of(['"', '\\', '{']),
     ))
     .parse_next(input)
 }
 
-pub(crate) fn parse_bool(input: &mut &str) -> Result<bool> {
+pub(crate) fn parse_bool(input: &mut Input<'_>) -> ModalResult<bool> {
     alt((keyword("True"), keyword("False")))
         .parse_next(input)
         .map(|p| match p {
@@ -221,12 +333,24 @@ pub(crate) fn parse_bool(input: &mut &str) -> Result<bool> {
         })
 }
 
+/// Example: `[1, 2, 3]`, with an optional trailing comma
+fn parse_list(input: &mut Input<'_>) -> ModalResult<Expression> {
+    delimited(
+        ('[', multispace0),
+        terminated(separated(0.., parse_expr, ws(',')), opt(ws(','))),
+        (multispace0, ']'),
+    )
+    .map(Expression::list)
+    .parse_next(input)
+}
+
 /// Expression for creating a new instance of an enum
 /// Example: `MyType::Variant`
 /// Example: `MyType::Variant(1, 2)`
-fn parse_enum_instance(input: &mut &str) -> Result<Expression> {
+/// Example: `MyType.Variant(1, 2)`
+fn parse_enum_instance(input: &mut Input<'_>) -> ModalResult<Expression> {
     let ty = parse_identifier_upper(input)?;
-    let _ = "::".parse_next(input)?;
+    let _ = alt(("::", ".")).parse_next(input)?;
     let variant = parse_identifier_upper(input)?;
     let args = parse_variant_args(input)?;
 
@@ -237,20 +361,20 @@ fn parse_enum_instance(input: &mut &str) -> Result<Expression> {
     ))
 }
 
-fn parse_variant_args(input: &mut &str) -> Result<Vec<Expression>> {
+fn parse_variant_args(input: &mut Input<'_>) -> ModalResult<Vec<Expression>> {
     opt(delimited('(', separated(0.., parse_expr, ws(',')), ')'))
         .map(|r| r.unwrap_or_default())
         .parse_next(input)
 }
 
 /// Example: `MyType { field1: 1, field2: 2 }`
-fn parse_new_type_instance(input: &mut &str) -> Result<Expression> {
+fn parse_new_type_instance(input: &mut Input<'_>) -> ModalResult<Expression> {
     let ty = parse_identifier_upper(input)?;
     let fields = parse_fields(input)?;
     Ok(Expression::new_record_instance(ty.to_owned(), fields))
 }
 
-fn parse_fields(input: &mut &str) -> Result<Vec<(String, Expression)>> {
+fn parse_fields(input: &mut Input<'_>) -> ModalResult<Vec<(String, Expression)>> {
     delimited(
         ws('{'),
         separated(
@@ -268,20 +392,24 @@ fn parse_fields(input: &mut &str) -> Result<Vec<(String, Expression)>> {
 }
 
 /// example: `my_val.some_field`
-fn parse_field_access(input: &mut &str) -> Result<Expression> {
-    let (name, field) =
-        separated_pair(parse_identifier_lower, '.', parse_identifier_lower).parse_next(input)?;
+fn parse_field_access(input: &mut Input<'_>) -> ModalResult<Expression> {
+    // `p.name(x)` calls `name` on `p`.
+    let (name, field) = terminated(
+        separated_pair(parse_identifier_lower, '.', parse_identifier_lower),
+        not('('),
+    )
+    .parse_next(input)?;
     Ok(Expression::record_access(name.to_owned(), field.to_owned()))
 }
 
-fn parse_function_call(input: &mut &str) -> Result<Expression> {
+fn parse_function_call(input: &mut Input<'_>) -> ModalResult<Expression> {
     let function_name = parse_identifier_lower(input)?;
     let args = parse_function_call_args(input)?;
     Ok(Expression::function_call(function_name.to_owned(), args))
 }
 
 /// A call to a namespaced function: `Option::map(opt, f)`
-fn parse_namespaced_function_call(input: &mut &str) -> Result<Expression> {
+fn parse_namespaced_function_call(input: &mut Input<'_>) -> ModalResult<Expression> {
     let namespace = parse_identifier_upper(input)?;
     let _ = "::".parse_next(input)?;
     let function_name = parse_identifier_lower(input)?;
@@ -293,11 +421,11 @@ fn parse_namespaced_function_call(input: &mut &str) -> Result<Expression> {
     ))
 }
 
-fn parse_function_call_args(input: &mut &str) -> Result<Vec<Expression>> {
+fn parse_function_call_args(input: &mut Input<'_>) -> ModalResult<Vec<Expression>> {
     delimited('(', separated(0.., parse_expr, ws(',')), ws(')')).parse_next(input)
 }
 
-fn parse_match(input: &mut &str) -> Result<Expression> {
+fn parse_match(input: &mut Input<'_>) -> ModalResult<Expression> {
     let _ = terminated(keyword("match"), multispace1).parse_next(input)?;
     let pat = parse_expr(input)?;
     let cases =
@@ -305,7 +433,24 @@ fn parse_match(input: &mut &str) -> Result<Expression> {
     Ok(Expression::match_expr(pat, cases))
 }
 
-fn parse_match_arm(input: &mut &str) -> Result<MatchArm> {
+/// `if a > b { a } else { b }`, where the else block may be another `if`.
+fn parse_if(input: &mut Input<'_>) -> ModalResult<Expression> {
+    let _ = keyword("if").parse_next(input)?;
+    let condition = parse_expr(input)?;
+    let then = parse_block(input)?;
+    // An `if` is an expression, so it always has an `else`.
+    let _ = cut_err(ws(keyword("else")))
+        .context(StrContext::Expected(StrContextValue::StringLiteral("else")))
+        .parse_next(input)?;
+    let otherwise = alt((
+        spanned(parse_if).map(|(expr, span)| Block::new(vec![], expr.with_span(span))),
+        parse_block,
+    ))
+    .parse_next(input)?;
+    Ok(Expression::if_expr(condition, then, otherwise))
+}
+
+fn parse_match_arm(input: &mut Input<'_>) -> ModalResult<MatchArm> {
     let (pattern, body) =
         separated_pair(crate::patterns::parse_pattern, ws("=>"), parse_match_body)
             .parse_next(input)?;
@@ -313,7 +458,7 @@ fn parse_match_arm(input: &mut &str) -> Result<MatchArm> {
     Ok(MatchArm::new(pattern, body))
 }
 
-fn parse_match_body(input: &mut &str) -> Result<MatchBody> {
+fn parse_match_body(input: &mut Input<'_>) -> ModalResult<MatchBody> {
     alt((
         parse_expr.map(MatchBody::Expr),
         parse_block.map(MatchBody::Block),
@@ -323,55 +468,56 @@ fn parse_match_body(input: &mut &str) -> Result<MatchBody> {
 
 #[cfg(test)]
 mod tests {
+    use crate::test_input;
 
     use super::*;
-    use ast::patterns::Pattern;
+    use ast::{expressions::ExpressionKind, patterns::Pattern};
 
     #[test]
     fn test_parse_literal_bool() {
-        let mut input = "True";
+        let mut input = test_input("True");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(expr, Expression::literal(Literal::Bool(true)));
     }
 
     #[test]
     fn test_parse_literal_u8() {
-        let mut input = "37";
+        let mut input = test_input("37");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(expr, Expression::literal(Literal::int(37)));
     }
 
     #[test]
     fn test_parse_literal_binary() {
-        let mut input = "0b110";
+        let mut input = test_input("0b110");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(expr, Expression::literal(Literal::int(6)));
     }
 
     #[test]
     fn test_parse_literal_octal() {
-        let mut input = "0o20";
+        let mut input = test_input("0o20");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(expr, Expression::literal(Literal::int(16)));
     }
 
     #[test]
     fn test_parse_literal_hexadecimal() {
-        let mut input = "0x20";
+        let mut input = test_input("0x20");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(expr, Expression::literal(Literal::int(32)));
     }
 
     #[test]
     fn test_parse_literal_f32() {
-        let mut input = "0.12";
+        let mut input = test_input("0.12");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(expr, Expression::literal(Literal::float(0.12)));
     }
 
     #[test]
     fn test_parse_literal_negative() {
-        let mut input = "-37";
+        let mut input = test_input("-37");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(
             expr,
@@ -379,9 +525,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_interpolated_string() {
+        let mut input = test_input(r#""Hi {person.name}, \{not a hole} { age + 1 }""#);
+        let expr = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            expr,
+            Expression::interpolation(vec![
+                StringPart::Text("Hi ".to_owned()),
+                StringPart::Hole(Expression::record_access(
+                    "person".to_owned(),
+                    "name".to_owned()
+                )),
+                StringPart::Text(", {not a hole} ".to_owned()),
+                StringPart::Hole(Expression::binary_op(
+                    Expression::identifier("age".to_owned()),
+                    BinaryOp::Add,
+                    Expression::literal(Literal::int(1))
+                )),
+            ])
+        );
+    }
+
     #[test]
     fn test_parse_string() {
-        let mut input = r#""hello, \"world\"""#;
+        let mut input = test_input(r#""hello, \"world\"""#);
         let expr = parse_expr(&mut input);
         eprintln!("{expr:?}");
         assert_eq!(
@@ -392,7 +561,7 @@ mod tests {
 
     #[test]
     fn test_parse_identifier() {
-        let mut input = "my_var";
+        let mut input = test_input("my_var");
         let expr = parse_expr(&mut input).unwrap();
 
         assert_eq!(expr, Expression::identifier("my_var".to_owned()));
@@ -400,15 +569,72 @@ mod tests {
 
     #[test]
     fn test_parse_unit() {
-        let mut input = "()";
+        let mut input = test_input("()");
         let expr = parse_expr(&mut input).unwrap();
 
         assert_eq!(expr, Expression::unit());
     }
 
+    #[test]
+    fn test_parse_tuple() {
+        let mut input = test_input("( (1), (x, True) )");
+        let expr = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            expr,
+            Expression::tuple(vec![
+                Expression::literal(Literal::int(1)),
+                Expression::tuple(vec![
+                    Expression::identifier("x".to_owned()),
+                    Expression::literal(Literal::Bool(true)),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_lambda() {
+        let mut input = test_input("List::map(xs, (x, y) -> x - y)");
+        let expr = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        let lambda = Expression::lambda(
+            vec!["x".to_owned(), "y".to_owned()],
+            Expression::binary_op(
+                Expression::identifier("x".to_owned()),
+                BinaryOp::Sub,
+                Expression::identifier("y".to_owned()),
+            ),
+        );
+        let ExpressionKind::FunctionCall { arguments, .. } = expr.kind else {
+            panic!("expected a call, got {expr:?}");
+        };
+        assert_eq!(arguments[1], lambda);
+
+        let expr = parse_expr(&mut test_input("() -> 42")).unwrap();
+        assert_eq!(
+            expr,
+            Expression::lambda(vec![], Expression::literal(Literal::int(42)))
+        );
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let mut input = test_input("[1, x,\n [] ,]");
+        let expr = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            expr,
+            Expression::list(vec![
+                Expression::literal(Literal::int(1)),
+                Expression::identifier("x".to_owned()),
+                Expression::list(vec![]),
+            ])
+        );
+    }
+
     #[test]
     fn test_parse_new_enum_instance() {
-        let mut input = "Option::None";
+        let mut input = test_input("Option::None");
         let parsed = parse_expr(&mut input).unwrap();
         assert_eq!(
             parsed,
@@ -418,7 +644,7 @@ mod tests {
 
     #[test]
     fn test_parse_new_enum_instance_with_args() {
-        let mut input = "Option::Some(1)";
+        let mut input = test_input("Option::Some(1)");
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {}", input);
         assert_eq!(
@@ -431,9 +657,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_new_enum_instance_with_dot() {
+        let mut input = test_input("Option.Some(Flavor.Orange)");
+        let parsed = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {}", input);
+        assert_eq!(
+            parsed,
+            Expression::new_enum_instance(
+                "Option".to_owned(),
+                "Some".to_owned(),
+                vec![Expression::new_enum_instance(
+                    "Flavor".to_owned(),
+                    "Orange".to_owned(),
+                    vec![]
+                )]
+            ),
+        );
+    }
+
     #[test]
     fn test_parse_new_record_instance_with_fields() {
-        let mut input = "Person { name: \"Bob\", is_builder: True }";
+        let mut input = test_input("Person { name: \"Bob\", is_builder: True }");
         let parsed = parse_expr(&mut input);
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -456,7 +701,7 @@ mod tests {
 
     #[test]
     fn test_parse_function_call() {
-        let mut input = "my_function(arg1, arg2)";
+        let mut input = test_input("my_function(arg1, arg2)");
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty());
         assert_eq!(
@@ -473,7 +718,7 @@ mod tests {
 
     #[test]
     fn test_parse_function_call_complex() {
-        let mut input = "my_function(other_fn(42), Person { name: \"Bob\" })";
+        let mut input = test_input("my_function(other_fn(42), Person { name: \"Bob\" })");
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -499,10 +744,12 @@ mod tests {
 
     #[test]
     fn test_parse_match() {
-        let mut input = "match my_bool {
+        let mut input = test_input(
+            "match my_bool {
             True => 1,
             False => 0
-        }";
+        }",
+        );
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty());
         assert_eq!(
@@ -511,11 +758,11 @@ mod tests {
                 Expression::identifier("my_bool".to_owned()),
                 vec![
                     MatchArm {
-                        pattern: Pattern::Literal(Literal::Bool(true)),
+                        pattern: Pattern::literal(Literal::Bool(true)),
                         body: MatchBody::Expr(Expression::literal(Literal::int(1))),
                     },
                     MatchArm {
-                        pattern: Pattern::Literal(Literal::Bool(false)),
+                        pattern: Pattern::literal(Literal::Bool(false)),
                         body: MatchBody::Expr(Expression::literal(Literal::int(0)))
                     }
                 ]
@@ -525,10 +772,12 @@ mod tests {
 
     #[test]
     fn test_parse_match_patterns_1() {
-        let mut input = "match Option::Some(x) {
+        let mut input = test_input(
+            "match Option::Some(x) {
             1 => True,
             _ => False
-        }";
+        }",
+        );
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty());
         assert_eq!(
@@ -541,11 +790,11 @@ mod tests {
                 ),
                 vec![
                     MatchArm {
-                        pattern: Pattern::Literal(Literal::int(1)),
+                        pattern: Pattern::literal(Literal::int(1)),
                         body: MatchBody::Expr(Expression::literal(Literal::Bool(true))),
                     },
                     MatchArm {
-                        pattern: Pattern::Wildcard,
+                        pattern: Pattern::wildcard(),
                         body: MatchBody::Expr(Expression::literal(Literal::Bool(false)))
                     }
                 ]
@@ -624,7 +873,7 @@ mod tests {
     #[test]
     fn test_parse_octal_with_seven() {
         // Regression: the octal range used to exclude the digit '7'.
-        let mut input = "0o17";
+        let mut input = test_input("0o17");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(expr, Expression::literal(Literal::int(15)));
@@ -633,13 +882,15 @@ mod tests {
     #[test]
     fn test_parse_bin_overflow_errors_instead_of_panicking() {
         // Regression: binary/octal literals used to `unwrap` a u64 parse.
-        let mut input = "0b11111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111";
+        let mut input = test_input(
+            "0b11111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111",
+        );
         assert!(parse_expr(&mut input).is_err() || !input.is_empty());
     }
 
     #[test]
     fn test_parse_hex_u128() {
-        let mut input = "0xffffffffffffffffffffffffffffffff"; // 32 f's: only fits u128
+        let mut input = test_input("0xffffffffffffffffffffffffffffffff"); // 32 f's: only fits u128
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(expr, Expression::literal(Literal::int(u128::MAX)));
@@ -648,21 +899,21 @@ mod tests {
     #[test]
     fn test_parse_bool_keyword_boundary() {
         // Regression: `Truex` used to parse as `True` leaving `x`.
-        let mut input = "Truex";
+        let mut input = test_input("Truex");
         assert!(parse_expr(&mut input).is_err());
     }
 
     #[test]
     fn test_parse_unary_not_vs_negate() {
         // Regression: `!` used to map to `Negate`, same as `-`.
-        let mut input = "!True";
+        let mut input = test_input("!True");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(
             expr,
             Expression::unary_op(UnaryOp::Not, Expression::literal(Literal::Bool(true)))
         );
 
-        let mut input = "-True";
+        let mut input = test_input("-True");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(
             expr,
@@ -673,7 +924,7 @@ mod tests {
     #[test]
     fn test_parse_enum_instance_empty_parens() {
         // Regression: `Option::None()` used to fail, leaving `()` unconsumed.
-        let mut input = "Option::None()";
+        let mut input = test_input("Option::None()");
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -685,7 +936,7 @@ mod tests {
     #[test]
     fn test_parse_empty_record_instance() {
         // Regression: `MyType {}` used to fail (fields required 1+).
-        let mut input = "MyType {}";
+        let mut input = test_input("MyType {}");
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -697,7 +948,7 @@ mod tests {
     #[test]
     fn test_parse_xor_operator() {
         // `Xor` existed in the AST but no token produced it.
-        let mut input = "1 ^ 2";
+        let mut input = test_input("1 ^ 2");
         let parsed = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -712,7 +963,7 @@ mod tests {
 
     #[test]
     fn test_precedence_mul_before_add() {
-        let mut input = "1 * 2 + 3";
+        let mut input = test_input("1 * 2 + 3");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -731,7 +982,7 @@ mod tests {
 
     #[test]
     fn test_precedence_add_after_mul() {
-        let mut input = "2 + 3 * 4";
+        let mut input = test_input("2 + 3 * 4");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(
             expr,
@@ -749,7 +1000,7 @@ mod tests {
 
     #[test]
     fn test_sub_is_left_associative() {
-        let mut input = "10 - 4 - 3";
+        let mut input = test_input("10 - 4 - 3");
         let expr = parse_expr(&mut input).unwrap();
         assert_eq!(
             expr,
@@ -767,7 +1018,7 @@ mod tests {
 
     #[test]
     fn test_parenthesized_expr() {
-        let mut input = "(1 + 2) * 3";
+        let mut input = test_input("(1 + 2) * 3");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -788,9 +1039,9 @@ mod tests {
     fn test_comparison_is_non_associative() {
         // `1 < 2 < 3` parses `1 < 2` and stops, leaving `< 3` unconsumed
         // (the enclosing context will error on the leftover).
-        let mut input = "1 < 2 < 3";
+        let mut input = test_input("1 < 2 < 3");
         let expr = parse_expr(&mut input).unwrap();
-        assert_eq!(input, " < 3");
+        assert_eq!(input.to_string(), " < 3");
         assert_eq!(
             expr,
             Expression::binary_op(
@@ -804,7 +1055,7 @@ mod tests {
     #[test]
     fn test_xor_binds_tighter_than_comparison() {
         // Rust-style: `x ^ y == z` is `(x ^ y) == z`, not C's `x ^ (y == z)`.
-        let mut input = "x ^ y == z";
+        let mut input = test_input("x ^ y == z");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -823,7 +1074,7 @@ mod tests {
 
     #[test]
     fn test_unary_binds_tighter_than_mul() {
-        let mut input = "-x * 2";
+        let mut input = test_input("-x * 2");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -838,7 +1089,7 @@ mod tests {
 
     #[test]
     fn test_unary_binds_tighter_than_comparison() {
-        let mut input = "!True == False";
+        let mut input = test_input("!True == False");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -854,7 +1105,7 @@ mod tests {
     #[test]
     fn test_function_call_with_expr_args() {
         // Was impossible before the Pratt rewrite (args were a restricted alt).
-        let mut input = "f(1 + 2, 3)";
+        let mut input = test_input("f(1 + 2, 3)");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -875,7 +1126,7 @@ mod tests {
 
     #[test]
     fn test_field_access_in_binary_op() {
-        let mut input = "a.b + c.d";
+        let mut input = test_input("a.b + c.d");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -888,9 +1139,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_if_else_if() {
+        let mut input = test_input("if a > b { a } else if a < b { b } else { 0 }");
+        let expr = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        let var = |name: &str| Expression::identifier(name.to_owned());
+        let block = |expr| Block::new(vec![], expr);
+        assert_eq!(
+            expr,
+            Expression::if_expr(
+                Expression::binary_op(var("a"), BinaryOp::Greater, var("b")),
+                block(var("a")),
+                block(Expression::if_expr(
+                    Expression::binary_op(var("a"), BinaryOp::Less, var("b")),
+                    block(var("b")),
+                    block(Expression::literal(Literal::int(0))),
+                )),
+            )
+        );
+        assert!(parse_expr(&mut test_input("if a { b }")).is_err());
+    }
+
+    #[test]
+    fn test_pipe_desugars_to_calls() {
+        let mut input = test_input("xs |> List::map(double) |> sum");
+        let expr = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            expr,
+            Expression::function_call(
+                "sum".to_owned(),
+                vec![Expression::namespaced_function_call(
+                    Some("List".to_owned()),
+                    "map".to_owned(),
+                    vec![
+                        Expression::identifier("xs".to_owned()),
+                        Expression::identifier("double".to_owned()),
+                    ],
+                )],
+            )
+        );
+        let or = parse_expr(&mut test_input("a || b |> not")).unwrap();
+        assert!(matches!(or.kind, ExpressionKind::FunctionCall { .. }));
+        assert!(parse_expr(&mut test_input("x |> 1")).is_err());
+    }
+
     #[test]
     fn test_parse_namespaced_function_call() {
-        let mut input = "Option::map(opt, f)";
+        let mut input = test_input("Option::map(opt, f)");
         let expr = parse_expr(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
@@ -905,5 +1202,49 @@ mod tests {
             )
         );
     }
-}
 
+    #[test]
+    fn test_parse_method_call() {
+        let mut input = test_input("opt.map(f)\n    .or(p.age)");
+        let expr = parse_expr(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            expr,
+            Expression::method_call(
+                Expression::method_call(
+                    Expression::identifier("opt".to_owned()),
+                    "map".to_owned(),
+                    vec![Expression::identifier("f".to_owned())],
+                ),
+                "or".to_owned(),
+                vec![Expression::record_access("p".to_owned(), "age".to_owned())],
+            )
+        );
+        assert_eq!(expr.span().range(), 0..25);
+
+        let expr = parse_expr(&mut test_input("-[1].length()")).unwrap();
+        let ExpressionKind::UnaryOp(_, operand) = &expr.kind else {
+            panic!("Expected a unary op, got {expr:?}");
+        };
+        assert!(matches!(operand.kind, ExpressionKind::MethodCall { .. }));
+    }
+
+    #[test]
+    fn test_spans_cover_source() {
+        let mut input = test_input("-x + f(1, 2)");
+        let expr = parse_expr(&mut input).unwrap();
+        assert_eq!(expr.span().range(), 0..12);
+        let ExpressionKind::BinaryOp(lhs, _, rhs) = &expr.kind else {
+            panic!("Expected a binary op, got {expr:?}");
+        };
+        assert_eq!(lhs.span().range(), 0..2);
+        assert_eq!(rhs.span().range(), 5..12);
+    }
+
+    #[test]
+    fn test_span_of_spaced_prefix_op_starts_at_operator() {
+        let mut input = test_input("!  (a)");
+        let expr = parse_expr(&mut input).unwrap();
+        assert_eq!(expr.span().range(), 0..6);
+    }
+}
@@ -1,27 +1,30 @@
 use ast::{
     functions::{FunctionBody, FunctionDeclaration, FunctionImplementation, QualifiedName},
+    traits::Bound,
     types::{FunctionSignature, Type},
 };
 use winnow::{
-    Parser, Result,
-    combinator::{alt, delimited, opt, separated, separated_pair, terminated},
+    ModalResult, Parser,
+    combinator::{alt, cut_err, delimited, opt, preceded, separated, terminated},
     error::{StrContext, StrContextValue},
 };
 
 use crate::{
-    custom_types::parse_custom_type_generics,
+    Input,
     expressions::parse_expr,
     identifiers::{parse_identifier_lower, parse_identifier_upper},
     patterns::parse_pattern,
     statements::parse_block,
-    types::{parse_custom_type, parse_primitive_type, parse_type},
+    types::{
+        parse_custom_type, parse_list_type, parse_primitive_type, parse_tuple_type, parse_type,
+    },
     ws,
 };
 
 /// A qualified function name: `map` or `Option::map`.
 ///
 /// Grammar reference: `QualifiedName = (TypeIdentifier "::")? Identifier`
-fn parse_qualified_name(input: &mut &str) -> Result<QualifiedName> {
+pub fn parse_qualified_name(input: &mut Input<'_>) -> ModalResult<QualifiedName> {
     let namespace = opt(terminated(parse_identifier_upper, "::")).parse_next(input)?;
     let name = parse_identifier_lower(input)?;
     Ok(QualifiedName::new(namespace.map(str::to_owned), name))
@@ -31,8 +34,8 @@ fn parse_qualified_name(input: &mut &str) -> Result<QualifiedName> {
 /// If parenthesis are encountered, its a function signature, parse recursively.
 /// Example: `(U8, U8) -> U8`
 /// Example: `(((U8) -> Bool), U8) -> Bool`
-pub fn parse_function_signature(input: &mut &str) -> Result<FunctionSignature> {
-    let (args, out) = separated_pair(
+pub fn parse_function_signature(input: &mut Input<'_>) -> ModalResult<FunctionSignature> {
+    let (args, out) = (
         delimited(
             ws('('),
             separated(
@@ -43,6 +46,7 @@ pub fn parse_function_signature(input: &mut &str) -> Result<FunctionSignature> {
                     alt((
                         parse_primitive_type,
                         parse_custom_type,
+                        parse_list_type,
                         // Bare function type: `(A) -> B`
                         parse_function_signature
                             .map(Type::Function)
@@ -53,6 +57,7 @@ pub fn parse_function_signature(input: &mut &str) -> Result<FunctionSignature> {
                         delimited(ws('('), parse_function_signature, ws(')'))
                             .map(Type::Function)
                             .context(StrContext::Label("sub-function")),
+                        parse_tuple_type,
                     )),
                 )
                     .map(|(_label, t)| t),
@@ -61,38 +66,71 @@ pub fn parse_function_signature(input: &mut &str) -> Result<FunctionSignature> {
             ws(')'),
         )
         .context(StrContext::Label("SignatureArgs")),
-        ws("->"),
-        parse_type,
+        // Only a signature has an arrow after its parameters
+        preceded(
+            ws("->"),
+            cut_err(parse_type.context(StrContext::Label("type"))),
+        ),
     )
-    .parse_next(input)?;
+        .parse_next(input)?;
 
     Ok(FunctionSignature::new(args, out))
 }
 
-pub fn parse_function_definition(input: &mut &str) -> Result<FunctionDeclaration> {
+/// Type parameters, and the traits they must implement: `<A: Show + Eq, B>`.
+fn parse_type_params(input: &mut Input<'_>) -> ModalResult<(Vec<String>, Vec<Bound>)> {
+    let params: Vec<(&str, Option<Vec<&str>>)> = delimited(
+        '<',
+        separated(
+            1..,
+            (
+                ws(parse_identifier_upper),
+                opt(preceded(
+                    ':',
+                    cut_err(separated(1.., ws(parse_identifier_upper), '+'))
+                        .context(StrContext::Label("trait bounds")),
+                )),
+            ),
+            ',',
+        ),
+        '>',
+    )
+    .parse_next(input)?;
+    let bounds = params
+        .iter()
+        .flat_map(|(param, traits)| {
+            traits
+                .iter()
+                .flatten()
+                .map(|trait_name| Bound::new(param, trait_name))
+        })
+        .collect();
+    let params = params
+        .iter()
+        .map(|(param, _)| (*param).to_owned())
+        .collect();
+    Ok((params, bounds))
+}
+
+pub fn parse_function_definition(input: &mut Input<'_>) -> ModalResult<FunctionDeclaration> {
     let name = parse_qualified_name(input)?;
-    let type_params = opt(parse_custom_type_generics)
+    let (type_params, bounds) = opt(parse_type_params)
         .parse_next(input)?
         .unwrap_or_default();
     let signature = parse_function_signature
         .context(StrContext::Label("signature"))
         .parse_next(input)?;
-    let _ = ';'
-        .context(StrContext::Expected(StrContextValue::CharLiteral(';')))
+    let _ = cut_err(';'.context(StrContext::Expected(StrContextValue::CharLiteral(';'))))
         .parse_next(input)?;
 
-    Ok(FunctionDeclaration::new(name, type_params, signature))
+    Ok(FunctionDeclaration::new(name, type_params, signature).with_bounds(bounds))
 }
 
-pub fn parse_function_impl(input: &mut &str) -> Result<FunctionImplementation> {
+pub fn parse_function_impl(input: &mut Input<'_>) -> ModalResult<FunctionImplementation> {
     let name = parse_qualified_name(input)?;
-    let args = delimited(
-        ws('('),
-        separated(0.., ws(parse_pattern), ws(',')),
-        ws(')'),
-    )
-    .context(winnow::error::StrContext::Label("arguments"))
-    .parse_next(input)?;
+    let args = delimited(ws('('), separated(0.., ws(parse_pattern), ws(',')), ws(')'))
+        .context(winnow::error::StrContext::Label("arguments"))
+        .parse_next(input)?;
     let body = alt((
         parse_function_body_single_line,
         parse_block.map(FunctionBody::MultiLine),
@@ -103,14 +141,19 @@ pub fn parse_function_impl(input: &mut &str) -> Result<FunctionImplementation> {
     Ok(FunctionImplementation::new(name, args, body))
 }
 
-fn parse_function_body_single_line(input: &mut &str) -> Result<FunctionBody> {
+fn parse_function_body_single_line(input: &mut Input<'_>) -> ModalResult<FunctionBody> {
     let _ = ws('=').parse_next(input)?;
-    let body = terminated(ws(parse_expr), ';').parse_next(input)?;
+    let body = cut_err(terminated(
+        ws(parse_expr),
+        ';'.context(StrContext::Expected(StrContextValue::CharLiteral(';'))),
+    ))
+    .parse_next(input)?;
     Ok(FunctionBody::SingleLine(body))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::test_input;
     use ast::{
         expressions::{Expression, Literal},
         patterns::Pattern,
@@ -122,7 +165,7 @@ mod tests {
 
     #[test]
     fn test_parse_function_definition() {
-        let mut input = "my_function(U8, U8) -> U8;";
+        let mut input = test_input("my_function(U8, U8) -> U8;");
         let function = parse_function_definition(&mut input).unwrap();
         assert!(input.is_empty());
         assert_eq!(function.name(), "my_function");
@@ -134,11 +177,14 @@ mod tests {
 
     #[test]
     fn test_parse_basic_function_impl() {
-        let mut input = "my_function(_x) = ();";
+        let mut input = test_input("my_function(_x) = ();");
         let function_impl = parse_function_impl(&mut input).unwrap();
 
         assert_eq!(function_impl.name(), "my_function");
-        assert_eq!(function_impl.arguments(), &[Pattern::Identifier("_x".to_owned())]);
+        assert_eq!(
+            function_impl.arguments(),
+            &[Pattern::identifier("_x".to_owned())]
+        );
         assert_eq!(
             function_impl.body(),
             &FunctionBody::SingleLine(Expression::unit())
@@ -147,15 +193,15 @@ mod tests {
 
     #[test]
     fn test_parse_function_impl() {
-        let mut input = "my_function(_x, _y) = 1;";
+        let mut input = test_input("my_function(_x, _y) = 1;");
         let function_impl = parse_function_impl(&mut input).unwrap();
         assert!(input.is_empty());
         assert_eq!(function_impl.name(), "my_function");
         assert_eq!(
             function_impl.arguments(),
             &[
-                Pattern::Identifier("_x".to_owned()),
-                Pattern::Identifier("_y".to_owned())
+                Pattern::identifier("_x".to_owned()),
+                Pattern::identifier("_y".to_owned())
             ]
         );
         assert_eq!(
@@ -166,12 +212,14 @@ mod tests {
 
     #[test]
     fn test_parse_multiline_function_impl() {
-        let mut input = "my_function(_x, _y) {
+        let mut input = test_input(
+            "my_function(_x, _y) {
               _z = 1;
             _z
-        }";
+        }",
+        );
 
-        let expected_statements = vec![Statement::Assignment(
+        let expected_statements = vec![Statement::assignment(
             "_z".to_owned(),
             Expression::literal(Literal::int(1)),
         )];
@@ -182,8 +230,8 @@ mod tests {
         assert_eq!(
             function_impl.arguments(),
             &[
-                Pattern::Identifier("_x".to_owned()),
-                Pattern::Identifier("_y".to_owned())
+                Pattern::identifier("_x".to_owned()),
+                Pattern::identifier("_y".to_owned())
             ]
         );
         assert_eq!(
@@ -197,7 +245,7 @@ mod tests {
 
     #[test]
     fn test_parse_function_signature() {
-        let mut input = "(A, U8) -> Bool";
+        let mut input = test_input("(A, U8) -> Bool");
         let expected = FunctionSignature::new(
             vec![Type::Custom("A".to_owned(), vec![]), Type::U8],
             Type::Bool,
@@ -209,7 +257,7 @@ mod tests {
 
     #[test]
     fn test_parse_higher_order_function_signature() {
-        let mut input = "(f: ((U8) -> U8), n: U8) -> U8";
+        let mut input = test_input("(f: ((U8) -> U8), n: U8) -> U8");
         let expected = FunctionSignature::new(
             vec![
                 Type::Function(FunctionSignature::new(vec![Type::U8], Type::U8)),
@@ -226,12 +274,13 @@ mod tests {
 #[cfg(test)]
 mod regression_tests {
     use super::*;
+    use crate::test_input;
     use ast::patterns::Pattern;
 
     #[test]
     fn test_parse_nullary_function_signature() {
         // Regression: `() -> U8` used to fail (args required 1+).
-        let mut input = "() -> U8";
+        let mut input = test_input("() -> U8");
         let parsed = parse_function_signature(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(parsed, FunctionSignature::new(vec![], Type::U8));
@@ -239,7 +288,7 @@ mod regression_tests {
 
     #[test]
     fn test_parse_namespaced_function_definition() {
-        let mut input = "Option::is_some(Self<A>) -> Bool;";
+        let mut input = test_input("Option::is_some(Self<A>) -> Bool;");
         let def = parse_function_definition(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(def.name(), "is_some");
@@ -249,7 +298,7 @@ mod regression_tests {
 
     #[test]
     fn test_parse_function_definition_with_type_params() {
-        let mut input = "Option::map<A, B>(Self<A>, (A) -> B) -> Self<B>;";
+        let mut input = test_input("Option::map<A, B>(Self<A>, (A) -> B) -> Self<B>;");
         let def = parse_function_definition(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(def.type_params(), &["A".to_owned(), "B".to_owned()]);
@@ -270,33 +319,32 @@ mod regression_tests {
 
     #[test]
     fn test_parse_pattern_matched_impl() {
-        let mut input = "Option::is_some(Option::None) = False;";
+        let mut input = test_input("Option::is_some(Option::None) = False;");
         let imp = parse_function_impl(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(imp.qualified_name().qualified(), "Option::is_some");
         assert_eq!(
             imp.arguments(),
-            &[Pattern::EnumInstance {
-                enum_name: "Option".to_owned(),
-                variant_name: "None".to_owned(),
-                args: vec![],
-            }]
+            &[Pattern::enum_instance(
+                "Option".to_owned(),
+                "None".to_owned(),
+                vec![]
+            )]
         );
     }
 
     #[test]
     fn test_parse_pattern_matched_impl_with_binding() {
-        let mut input = "Option::unwrap(Option::Some(x)) = x;";
+        let mut input = test_input("Option::unwrap(Option::Some(x)) = x;");
         let imp = parse_function_impl(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
             imp.arguments(),
-            &[Pattern::EnumInstance {
-                enum_name: "Option".to_owned(),
-                variant_name: "Some".to_owned(),
-                args: vec![Pattern::Identifier("x".to_owned())],
-            }]
+            &[Pattern::enum_instance(
+                "Option".to_owned(),
+                "Some".to_owned(),
+                vec![Pattern::identifier("x".to_owned())]
+            )]
         );
     }
 }
-
@@ -1,12 +1,14 @@
 use winnow::{
-    Parser, Result,
+    ModalResult, Parser,
     combinator::opt,
     stream::AsChar,
     token::{one_of, take_while},
 };
 
+use crate::Input;
+
 // can begin with a lowercase letter or underscore, then it can be followed by any number of letters, numbers, or underscores
-pub fn parse_identifier_lower<'s>(input: &mut &'s str) -> Result<&'s str> {
+pub fn parse_identifier_lower<'s>(input: &mut Input<'s>) -> ModalResult<&'s str> {
     (
         one_of(|c: char| c.is_alpha() && c.is_lowercase() || c == '_'),
         take_while(0.., |c: char| c.is_alphanum() || c == '_'),
@@ -16,7 +18,7 @@ pub fn parse_identifier_lower<'s>(input: &mut &'s str) -> Result<&'s str> {
 }
 
 // can begin with an uppercase letter or underscore followed by an uppercase letter, then it can be followed by any number of letters or numbers
-pub fn parse_identifier_upper<'s>(input: &mut &'s str) -> Result<&'s str> {
+pub fn parse_identifier_upper<'s>(input: &mut Input<'s>) -> ModalResult<&'s str> {
     (
         opt('_'),
         one_of(|c: char| c.is_alpha() && c.is_uppercase()),
@@ -29,74 +31,75 @@ pub fn parse_identifier_upper<'s>(input: &mut &'s str) -> Result<&'s str> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_input;
 
     #[test]
     fn test_parse_identifier_lower() {
-        let mut input = "my_value12";
+        let mut input = test_input("my_value12");
         let parsed = parse_identifier_lower(&mut input).unwrap();
         assert_eq!(parsed, "my_value12");
     }
 
     #[test]
     fn test_parse_identifier_lower_underscore() {
-        let mut input = "_my_value";
+        let mut input = test_input("_my_value");
         let parsed = parse_identifier_lower(&mut input).unwrap();
         assert_eq!(parsed, "_my_value");
     }
 
     #[test]
     fn test_parse_identifier_lower_with_type() {
-        let mut input = "my_value: U8";
+        let mut input = test_input("my_value: U8");
         let parsed = parse_identifier_lower(&mut input).unwrap();
-        assert_eq!(input, ": U8");
+        assert_eq!(input.to_string(), ": U8");
         assert_eq!(parsed, "my_value");
     }
 
     #[test]
     fn test_parse_invalid_identifier_lower_is_upper() {
-        let mut input = "MyType";
+        let mut input = test_input("MyType");
         let result = parse_identifier_lower(&mut input);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_invalid_identifier_lower_starts_number() {
-        let mut input = "12ident";
+        let mut input = test_input("12ident");
         let result = parse_identifier_lower(&mut input);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_identifier_upper() {
-        let mut input = "MyType";
+        let mut input = test_input("MyType");
         let parsed = parse_identifier_upper(&mut input).unwrap();
         assert_eq!(parsed, "MyType");
     }
 
     #[test]
     fn test_parse_identifier_upper_underscore() {
-        let mut input = "_MyType";
+        let mut input = test_input("_MyType");
         let parsed = parse_identifier_upper(&mut input).unwrap();
         assert_eq!(parsed, "_MyType");
     }
 
     #[test]
     fn test_parse_invalid_identifier_upper_is_lower() {
-        let mut input = "my_value";
+        let mut input = test_input("my_value");
         let result = parse_identifier_upper(&mut input);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_invalid_identifier_upper_starts_lower() {
-        let mut input = "_myValue";
+        let mut input = test_input("_myValue");
         let result = parse_identifier_upper(&mut input);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_parse_invalid_identifier_upper_has_middle_underscore() {
-        let mut input = "My_Value";
+        let mut input = test_input("My_Value");
         let result = parse_identifier_upper(&mut input);
         assert!(result.is_err());
     }
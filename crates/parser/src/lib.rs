@@ -1,11 +1,20 @@
+use std::{cell::RefCell, rc::Rc};
+
+use ast::{
+    diagnostics::Diagnostic,
+    span::{FileId, Span},
+};
 use winnow::{
-    Parser as Pw, ascii::multispace0,
+    ModalResult, Parser as Pw,
+    ascii::multispace0,
     combinator::{delimited, not, peek, terminated},
-    error::ParserError,
+    error::{ContextError, ErrMode, ParserError},
+    stream::{LocatingSlice, Stateful},
     token::one_of,
 };
 
 mod custom_types;
+mod errors;
 pub mod expressions;
 mod functions;
 mod identifiers;
@@ -13,22 +22,74 @@ mod patterns;
 pub mod program;
 mod statements;
 mod top_level;
+mod traits;
 mod types;
 
-pub fn ws<'a, F, O, E: ParserError<&'a str>>(inner: F) -> impl Pw<&'a str, O, E>
+/// Parser input: the source text, tracking byte offsets so nodes can record
+/// their [`Span`], plus the [`State`] of the parse.
+pub type Input<'a> = Stateful<LocatingSlice<&'a str>, State>;
+
+/// What the parser carries along besides the text.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    /// The file being parsed.
+    pub file: FileId,
+    /// Syntax errors the parser recovered from. Shared, so that backtracking
+    /// (which works on copies of the input) can't lose them.
+    errors: Rc<RefCell<Vec<Diagnostic>>>,
+}
+
+impl State {
+    pub fn report(&self, error: Diagnostic) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    pub fn take_errors(&self) -> Vec<Diagnostic> {
+        self.errors.take()
+    }
+}
+
+pub fn new_input(source: &str, file: FileId) -> Input<'_> {
+    Stateful {
+        input: LocatingSlice::new(source),
+        state: State {
+            file,
+            errors: Rc::default(),
+        },
+    }
+}
+
+/// Runs `inner` and pairs its output with the span of the input it consumed.
+/// Trailing whitespace eaten by `ws` isn't considered part of the node.
+pub fn spanned<'a, F, O>(mut inner: F) -> impl Pw<Input<'a>, (O, Span), ErrMode<ContextError>>
+where
+    F: Pw<Input<'a>, O, ErrMode<ContextError>>,
+{
+    move |input: &mut Input<'a>| -> ModalResult<(O, Span)> {
+        let file = input.state.file;
+        let ((output, taken), range) = inner.by_ref().with_taken().with_span().parse_next(input)?;
+        let end = range.start + taken.trim_end().len();
+        Ok((output, Span::new(file, range.start, end)))
+    }
+}
+
+pub fn ws<'a, F, O, E: ParserError<Input<'a>>>(inner: F) -> impl Pw<Input<'a>, O, E>
 where
-    F: Pw<&'a str, O, E>,
+    F: Pw<Input<'a>, O, E>,
 {
     delimited(multispace0, inner, multispace0)
 }
 
 /// A literal keyword that must not be immediately followed by an identifier
 /// character, so e.g. `returnfoo` doesn't parse as `return foo`.
-pub fn keyword<'a, E: ParserError<&'a str>>(
-    lit: &'static str,
-) -> impl Pw<&'a str, &'a str, E> {
+pub fn keyword<'a, E: ParserError<Input<'a>>>(lit: &'static str) -> impl Pw<Input<'a>, &'a str, E> {
     terminated(
         lit,
         peek(not(one_of(|c: char| c.is_alphanumeric() || c == '_'))),
     )
 }
+
+#[cfg(test)]
+pub(crate) fn test_input(source: &str) -> Input<'_> {
+    new_input(source, FileId::default())
+}
@@ -1,54 +1,133 @@
 use ast::{
-    expressions::Literal,
-    patterns::Pattern,
+    expressions::{ExpressionKind, Literal},
+    patterns::{Pattern, PatternKind},
+    span::Span,
 };
 use winnow::{
-    Parser, Result, dispatch,
-    combinator::{alt, delimited, not, opt, peek, separated, terminated},
+    ModalResult, Parser,
+    ascii::multispace0,
+    combinator::{alt, delimited, not, opt, peek, preceded, separated, terminated},
+    dispatch,
     token::{any, one_of},
 };
 
 use crate::{
+    Input,
     expressions::{parse_bool, parse_number, parse_string},
     identifiers::{parse_identifier_lower, parse_identifier_upper},
-    ws,
+    spanned, ws,
 };
 
 /// Patterns are a restricted, destructuring-only subset of syntax — NOT
 /// expressions. Used in match arms and function implementation parameters.
 ///
 /// Grammar reference (`grammar_optimized.pest`): `Pattern`
-pub fn parse_pattern(input: &mut &str) -> Result<Pattern> {
-    dispatch! {peek(any);
+pub fn parse_pattern(input: &mut Input<'_>) -> ModalResult<Pattern> {
+    spanned(dispatch! {peek(any);
         '_' => parse_wildcard_or_binding,
-        '"' => parse_string.map(|s| Pattern::Literal(Literal::String(s))),
-        c if c.is_ascii_digit() => parse_number.map(Pattern::Literal),
+        '[' => parse_list_pattern,
+        '(' => parse_tuple_pattern,
+        '"' => parse_string.verify_map(|s| match s.kind {
+            ExpressionKind::Literal(literal) => Some(Pattern::literal(literal)),
+            _ => None,
+        }),
+        c if c.is_ascii_digit() => parse_number.map(Pattern::literal),
         c if c.is_ascii_uppercase() => alt((
-            parse_bool.map(|b| Pattern::Literal(Literal::Bool(b))),
+            parse_bool.map(|b| Pattern::literal(Literal::Bool(b))),
+            parse_record_pattern,
             parse_enum_pattern,
         )),
-        _ => parse_identifier_lower.map(|s| Pattern::Identifier(s.to_owned())),
-    }
+        _ => parse_identifier_lower.map(|s| Pattern::identifier(s.to_owned())),
+    })
+    .map(|(pattern, span)| pattern.with_span(span))
     .parse_next(input)
 }
 
 /// Bare `_` is a wildcard; `_x` is an ordinary binding.
-fn parse_wildcard_or_binding(input: &mut &str) -> Result<Pattern> {
+fn parse_wildcard_or_binding(input: &mut Input<'_>) -> ModalResult<Pattern> {
     alt((
         terminated(
             '_',
             peek(not(one_of(|c: char| c.is_alphanumeric() || c == '_'))),
         )
-        .map(|_| Pattern::Wildcard),
-        parse_identifier_lower.map(|s| Pattern::Identifier(s.to_owned())),
+        .map(|_| Pattern::wildcard()),
+        parse_identifier_lower.map(|s| Pattern::identifier(s.to_owned())),
     ))
     .parse_next(input)
 }
 
-/// Example: `Option::Some(x)`, `Status::Ready`
-fn parse_enum_pattern(input: &mut &str) -> Result<Pattern> {
+/// Example: `(0, x)`
+fn parse_tuple_pattern(input: &mut Input<'_>) -> ModalResult<Pattern> {
+    delimited(
+        ('(', multispace0),
+        separated(2.., parse_pattern, ws(',')),
+        (multispace0, ')'),
+    )
+    .map(Pattern::tuple)
+    .parse_next(input)
+}
+
+/// Example: `Person { name, age: 30, .. }`
+fn parse_record_pattern(input: &mut Input<'_>) -> ModalResult<Pattern> {
+    let type_name = terminated(parse_identifier_upper, ws('{')).parse_next(input)?;
+    let (fields, rest) = terminated(
+        (
+            separated(0.., parse_field_pattern, ws(',')),
+            // A `..` ignores the fields left out.
+            opt((opt(ws(',')), "..")).map(|rest| rest.is_some()),
+        ),
+        (opt(ws(',')), multispace0, '}'),
+    )
+    .parse_next(input)?;
+    Ok(Pattern::record(type_name.to_owned(), fields, rest))
+}
+
+/// `age: 30`, or `name`, binding the field to its name.
+fn parse_field_pattern(input: &mut Input<'_>) -> ModalResult<(String, Pattern)> {
+    let (name, span) = spanned(parse_identifier_lower).parse_next(input)?;
+    let pattern = opt(preceded(ws(':'), parse_pattern))
+        .parse_next(input)?
+        .unwrap_or_else(|| Pattern::identifier(name.to_owned()).with_span(span));
+    Ok((name.to_owned(), pattern))
+}
+
+/// Example: `[]`, `[x, y]`, `[head, ..tail]`, `[first, ..]`
+fn parse_list_pattern(input: &mut Input<'_>) -> ModalResult<Pattern> {
+    let ((elements, rest), span) = spanned(delimited(
+        ('[', multispace0),
+        (
+            separated(0.., parse_pattern, ws(',')),
+            // A bare `..` ignores the rest of the list.
+            opt(preceded(
+                (ws(','), ".."),
+                opt(parse_pattern).map(|rest| rest.unwrap_or_else(Pattern::wildcard)),
+            )),
+        ),
+        (opt(ws(',')), multispace0, ']'),
+    ))
+    .parse_next(input)?;
+    let mut pattern = Pattern::list(elements, rest);
+    set_list_span(&mut pattern, span);
+    Ok(pattern)
+}
+
+/// The lists of the remaining elements have no source of their own: they're
+/// given the span of the whole list.
+fn set_list_span(list: &mut Pattern, span: Span) {
+    match &mut list.kind {
+        PatternKind::Cons { tail, .. } => {
+            list.span = span;
+            set_list_span(tail, span);
+        }
+        PatternKind::EmptyList => list.span = span,
+        _ => {}
+    }
+}
+
+/// Example: `Option::Some(x)`, `Status::Ready`, `Status.Ready`
+fn parse_enum_pattern(input: &mut Input<'_>) -> ModalResult<Pattern> {
     let enum_name = parse_identifier_upper(input)?;
-    let _ = "::".parse_next(input)?;
+    let _ = alt(("::", ".")).parse_next(input)?;
     let variant_name = parse_identifier_upper(input)?;
     let args = opt(delimited(
         '(',
@@ -58,92 +137,178 @@ fn parse_enum_pattern(input: &mut &str) -> Result<Pattern> {
     .parse_next(input)?
     .unwrap_or_default();
 
-    Ok(Pattern::EnumInstance {
-        enum_name: enum_name.to_owned(),
-        variant_name: variant_name.to_owned(),
+    Ok(Pattern::enum_instance(
+        enum_name.to_owned(),
+        variant_name.to_owned(),
         args,
-    })
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_input;
 
     #[test]
     fn test_parse_wildcard() {
-        let mut input = "_";
-        assert_eq!(parse_pattern(&mut input).unwrap(), Pattern::Wildcard);
+        let mut input = test_input("_");
+        assert_eq!(parse_pattern(&mut input).unwrap(), Pattern::wildcard());
     }
 
     #[test]
     fn test_parse_underscore_binding_is_not_wildcard() {
-        let mut input = "_x";
+        let mut input = test_input("_x");
         let parsed = parse_pattern(&mut input).unwrap();
         assert!(input.is_empty());
-        assert_eq!(parsed, Pattern::Identifier("_x".to_owned()));
+        assert_eq!(parsed, Pattern::identifier("_x".to_owned()));
     }
 
     #[test]
     fn test_parse_literal_pattern() {
-        let mut input = "42";
+        let mut input = test_input("42");
         assert_eq!(
             parse_pattern(&mut input).unwrap(),
-            Pattern::Literal(Literal::int(42))
+            Pattern::literal(Literal::int(42))
         );
 
-        let mut input = "True";
+        let mut input = test_input("True");
         assert_eq!(
             parse_pattern(&mut input).unwrap(),
-            Pattern::Literal(Literal::Bool(true))
+            Pattern::literal(Literal::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_parse_tuple_pattern() {
+        let mut input = test_input("(0, (x, _))");
+        let parsed = parse_pattern(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            parsed,
+            Pattern::tuple(vec![
+                Pattern::literal(Literal::int(0)),
+                Pattern::tuple(vec![
+                    Pattern::identifier("x".to_owned()),
+                    Pattern::wildcard()
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_record_pattern() {
+        let mut input = test_input("Person { name, age: 30, .. }");
+        let parsed = parse_pattern(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            parsed,
+            Pattern::record(
+                "Person".to_owned(),
+                vec![
+                    ("name".to_owned(), Pattern::identifier("name".to_owned())),
+                    ("age".to_owned(), Pattern::literal(Literal::int(30))),
+                ],
+                true
+            )
+        );
+
+        let mut input = test_input("Point {\n    x: _,\n    y,\n}");
+        let parsed = parse_pattern(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            parsed,
+            Pattern::record(
+                "Point".to_owned(),
+                vec![
+                    ("x".to_owned(), Pattern::wildcard()),
+                    ("y".to_owned(), Pattern::identifier("y".to_owned())),
+                ],
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_list_patterns() {
+        let x = || Pattern::identifier("x".to_owned());
+        let mut input = test_input("[x, ..rest]");
+        assert_eq!(
+            parse_pattern(&mut input).unwrap(),
+            Pattern::list(vec![x()], Some(Pattern::identifier("rest".to_owned())))
+        );
+
+        let mut input = test_input("[ x , 1, ]");
+        let parsed = parse_pattern(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            parsed,
+            Pattern::list(vec![x(), Pattern::literal(Literal::int(1))], None)
+        );
+
+        let mut input = test_input("[_, ..]");
+        assert_eq!(
+            parse_pattern(&mut input).unwrap(),
+            Pattern::list(vec![Pattern::wildcard()], Some(Pattern::wildcard()))
+        );
+        assert_eq!(
+            parse_pattern(&mut test_input("[]")).unwrap(),
+            Pattern::list(vec![], None)
         );
     }
 
     #[test]
     fn test_parse_enum_pattern() {
-        let mut input = "Option::Some(x)";
+        let mut input = test_input("Option::Some(x)");
         let parsed = parse_pattern(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
             parsed,
-            Pattern::EnumInstance {
-                enum_name: "Option".to_owned(),
-                variant_name: "Some".to_owned(),
-                args: vec![Pattern::Identifier("x".to_owned())],
-            }
+            Pattern::enum_instance(
+                "Option".to_owned(),
+                "Some".to_owned(),
+                vec![Pattern::identifier("x".to_owned())]
+            )
         );
     }
 
     #[test]
     fn test_parse_enum_pattern_no_args() {
-        let mut input = "Option::None";
+        let mut input = test_input("Option::None");
+        let parsed = parse_pattern(&mut input).unwrap();
+        assert!(input.is_empty(), "Remaining input: {input}");
+        assert_eq!(
+            parsed,
+            Pattern::enum_instance("Option".to_owned(), "None".to_owned(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_parse_enum_pattern_with_dot() {
+        let mut input = test_input("Status.Ready");
         let parsed = parse_pattern(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
             parsed,
-            Pattern::EnumInstance {
-                enum_name: "Option".to_owned(),
-                variant_name: "None".to_owned(),
-                args: vec![],
-            }
+            Pattern::enum_instance("Status".to_owned(), "Ready".to_owned(), vec![])
         );
     }
 
     #[test]
     fn test_parse_nested_enum_pattern() {
-        let mut input = "Option::Some(Option::Some(1))";
+        let mut input = test_input("Option::Some(Option::Some(1))");
         let parsed = parse_pattern(&mut input).unwrap();
         assert!(input.is_empty(), "Remaining input: {input}");
         assert_eq!(
             parsed,
-            Pattern::EnumInstance {
-                enum_name: "Option".to_owned(),
-                variant_name: "Some".to_owned(),
-                args: vec![Pattern::EnumInstance {
-                    enum_name: "Option".to_owned(),
-                    variant_name: "Some".to_owned(),
-                    args: vec![Pattern::Literal(Literal::int(1))],
-                }],
-            }
+            Pattern::enum_instance(
+                "Option".to_owned(),
+                "Some".to_owned(),
+                vec![Pattern::enum_instance(
+                    "Option".to_owned(),
+                    "Some".to_owned(),
+                    vec![Pattern::literal(Literal::int(1))]
+                )]
+            )
         );
     }
 }
@@ -1,23 +1,30 @@
-use ast::program::Program;
-use winnow::{Parser, Result};
+use ast::{diagnostics::Diagnostic, program::Program, span::FileId};
 
-use crate::top_level::parse_top_levels;
+use crate::{new_input, top_level::parse_top_levels};
 
+/// Parses a whole source file.
+///
 /// # Errors
-/// todo
-pub fn parse_program(input: &mut &str) -> Result<Program> {
-    parse_top_levels
-        .parse_next(input)
-        .map(Program::from_top_levels)
+/// A diagnostic for every syntax error in the file or, if there are none, for
+/// a program without exactly one entry point.
+pub fn parse_program(source: &str, file: FileId) -> Result<Program, Vec<Diagnostic>> {
+    let (top_levels, errors) = parse_top_levels(&mut new_input(source, file));
+    // A malformed `main` would also be reported as a missing entry point.
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Program::from_top_levels(top_levels)
 }
 
 #[cfg(test)]
 mod tests {
+    use ast::{diagnostics::codes, span::FileId};
+
     use crate::program::parse_program;
 
     #[test]
     fn parse_simple_program() {
-        let mut input = "sum(U8, U8) -> U8;
+        let input = "sum(U8, U8) -> U8;
             sum(a, b) = a + b;
 
             main {
@@ -27,6 +34,67 @@ mod tests {
                 z
             }
             ";
-        let _parsed = parse_program(&mut input).unwrap();
+        let _parsed = parse_program(input, FileId::default()).unwrap();
+    }
+
+    #[test]
+    fn syntax_error_points_at_offending_input() {
+        let input = "main {\n    x = 1 +;\n}";
+        let errors = parse_program(input, FileId::default()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(codes::SYNTAX));
+        assert_eq!(errors[0].message, "expected expression, found `;`");
+        assert_eq!(errors[0].primary().unwrap().span.range(), 18..19);
+    }
+
+    #[test]
+    fn recovery_stops_at_traits() {
+        let input = "f(U8) -> U8\ntrait S { s(Self) -> ; }\nmain { 1 }";
+        let errors = parse_program(input, FileId::default()).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn every_syntax_error_is_reported() {
+        let input = "type Person { name: String age: U8 }
+sum(U8, U8) -> ;
+sum(a, b) = a + b;
+
+main {
+    x = ;
+    y = sum(x, 1)
+    y
+}";
+        let errors = parse_program(input, FileId::default()).unwrap_err();
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "expected `}`, found `age`",
+                "expected type, found `;`",
+                "expected expression, found `;`",
+                "expected `;`, found `y`",
+            ]
+        );
+        assert_eq!(
+            errors[1].notes,
+            ["while parsing a function declaration".to_owned()]
+        );
+    }
+
+    #[test]
+    fn multiple_entry_points_are_reported() {
+        let input = "main { 1 } main { 2 }";
+        let errors = parse_program(input, FileId::default()).unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::MULTIPLE_ENTRY_POINTS));
+        assert_eq!(errors[0].primary().unwrap().span.range(), 11..21);
+        assert_eq!(errors[0].secondary().next().unwrap().span.range(), 0..10);
+    }
+
+    #[test]
+    fn missing_entry_point_is_reported() {
+        let input = "sum(U8, U8) -> U8;";
+        let errors = parse_program(input, FileId::default()).unwrap_err();
+        assert_eq!(errors[0].code, Some(codes::MISSING_ENTRY_POINT));
     }
 }
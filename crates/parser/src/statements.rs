@@ -1,16 +1,36 @@
 use ast::statements::{Block, Statement};
 use winnow::{
-    Parser, Result,
-    combinator::{alt, delimited, opt, repeat, terminated},
+    ModalResult, Parser,
+    ascii::multispace0,
+    combinator::{alt, cut_err, not, opt, peek, preceded, terminated},
+    error::{ErrMode, StrContext, StrContextValue},
+    stream::Stream,
 };
 
-use crate::{expressions::parse_expr, identifiers::parse_identifier_lower, keyword, ws};
+use crate::{
+    Input,
+    errors::{skip_statement, syntax_error},
+    expressions::parse_expr,
+    identifiers::parse_identifier_lower,
+    keyword, spanned,
+    types::parse_type,
+    ws,
+};
 
-pub fn parse_block(input: &mut &str) -> Result<Block> {
-    delimited(ws('{'), parse_block_content, ws('}')).parse_next(input)
+/// A `{ ... }` block. Nothing but a block starts with `{`, so once it's seen
+/// the parser commits and reports errors inside the block where they occur.
+pub fn parse_block(input: &mut Input<'_>) -> ModalResult<Block> {
+    preceded(
+        ws('{'),
+        cut_err(terminated(
+            parse_block_content,
+            ws('}').context(StrContext::Expected(StrContextValue::CharLiteral('}'))),
+        )),
+    )
+    .parse_next(input)
 }
 
-fn parse_block_content(input: &mut &str) -> Result<Block> {
+fn parse_block_content(input: &mut Input<'_>) -> ModalResult<Block> {
     let statements = parse_statements(input)?;
     // NOTE: `opt` (not `if let Ok(...)`) — it resets the input when the
     // expression parser fails, so the closing `}` is left unconsumed.
@@ -20,29 +40,73 @@ fn parse_block_content(input: &mut &str) -> Result<Block> {
     }
 }
 
-fn parse_statements(input: &mut &str) -> Result<Vec<Statement>> {
-    repeat(0.., parse_statement).parse_next(input)
+/// Statements up to the end of the block. A malformed statement is reported
+/// and skipped, and parsing carries on with the next one.
+fn parse_statements(input: &mut Input<'_>) -> ModalResult<Vec<Statement>> {
+    let mut statements = vec![];
+    loop {
+        let start = input.checkpoint();
+        match parse_statement(input) {
+            Ok(statement) => statements.push(statement),
+            Err(ErrMode::Cut(error)) => {
+                input
+                    .state
+                    .report(syntax_error(input, &ErrMode::Cut(error)));
+                input.reset(&start);
+                let skipped = skip_statement(input.peek_finish());
+                if skipped == 0 {
+                    break;
+                }
+                let _ = input.next_slice(skipped);
+                let _ = multispace0.parse_next(input)?;
+            }
+            Err(ErrMode::Backtrack(_)) => {
+                input.reset(&start);
+                break;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(statements)
 }
 
-fn parse_statement(input: &mut &str) -> Result<Statement> {
+fn parse_statement(input: &mut Input<'_>) -> ModalResult<Statement> {
     terminated(
-        alt((parse_assign_statement, parse_return_statement)),
-        ws(';'),
+        spanned(alt((parse_assign_statement, parse_return_statement)))
+            .map(|(statement, span)| statement.with_span(span)),
+        cut_err(ws(';').context(StrContext::Expected(StrContextValue::CharLiteral(';')))),
     )
     .parse_next(input)
 }
 
-fn parse_assign_statement(input: &mut &str) -> Result<Statement> {
-    let identifier = parse_identifier_lower(input)?;
-    let _ = ws('=').parse_next(input)?;
-    let expr = parse_expr(input)?;
-    Ok(Statement::Assignment(identifier.to_owned(), expr))
+/// `x = 1;`, or `x: U32 = 300;`, whose value must have the annotated type.
+fn parse_assign_statement(input: &mut Input<'_>) -> ModalResult<Statement> {
+    let identifier = parse_identifier_lower(input)?.to_owned();
+    let annotation = opt(preceded(
+        ws(':'),
+        cut_err(ws(parse_type)).context(StrContext::Expected(StrContextValue::Description("type"))),
+    ))
+    .parse_next(input)?;
+    // `not('=')`: `x == y` is a comparison, not an assignment
+    let mut equals = ws(terminated('=', peek(not('='))));
+    let _ = match annotation {
+        // After a type annotation, this can only be an assignment.
+        Some(_) => cut_err(equals)
+            .context(StrContext::Expected(StrContextValue::CharLiteral('=')))
+            .parse_next(input)?,
+        None => equals.parse_next(input)?,
+    };
+    let expr = cut_err(parse_expr).parse_next(input)?;
+    Ok(match annotation {
+        Some(ty) => Statement::typed_assignment(identifier, ty, expr),
+        None => Statement::assignment(identifier, expr),
+    })
 }
 
-fn parse_return_statement(input: &mut &str) -> Result<Statement> {
+fn parse_return_statement(input: &mut Input<'_>) -> ModalResult<Statement> {
     let _ = ws(keyword("return")).parse_next(input)?;
-    let expr = parse_expr(input)?;
-    Ok(Statement::Return(expr))
+    let expr = cut_err(parse_expr).parse_next(input)?;
+    Ok(Statement::return_(expr))
 }
 
 // #[cfg(test)]
@@ -130,11 +194,12 @@ fn parse_return_statement(input: &mut &str) -> Result<Statement> {
 #[cfg(test)]
 mod regression_tests {
     use super::*;
+    use crate::test_input;
 
     #[test]
     fn test_return_keyword_boundary() {
         // Regression: `returnfoo;` used to parse as `return foo`.
-        let mut input = "returnfoo;";
+        let mut input = test_input("returnfoo;");
         assert!(parse_statement(&mut input).is_err());
     }
 }
@@ -1,95 +1,151 @@
-use ast::top_level::TopLevel;
+use ast::{diagnostics::Diagnostic, top_level::TopLevel};
 use winnow::{
-    Parser, Result,
-    combinator::{alt, preceded, repeat},
-    error::StrContext,
+    ModalResult, Parser,
+    ascii::multispace0,
+    combinator::{alt, peek, preceded},
+    error::{ContextError, ErrMode, StrContext},
+    stream::Stream,
 };
 
 use crate::{
+    Input,
     custom_types::parse_custom_type_definition,
-    functions::{parse_function_definition, parse_function_impl},
-    keyword,
+    errors::{skip_item, syntax_error},
+    functions::{parse_function_definition, parse_function_impl, parse_qualified_name},
+    keyword, spanned,
     statements::parse_block,
+    traits::{parse_trait, parse_trait_impl},
     ws,
 };
 
-pub fn parse_top_levels(input: &mut &str) -> Result<Vec<TopLevel>> {
-    repeat(
-        1..,
-        ws(parse_top_level).context(StrContext::Label("TopLevel")),
-    )
-    .context(StrContext::Label("TopLevels"))
-    .parse_next(input)
+/// Parses every top-level item of the input.
+///
+/// A malformed item doesn't stop the parser: its error is recorded, the rest
+/// of the item is skipped and parsing resumes with the next one, so a single
+/// run reports every syntax error in the file.
+pub fn parse_top_levels(input: &mut Input<'_>) -> (Vec<TopLevel>, Vec<Diagnostic>) {
+    let mut top_levels = vec![];
+    let mut errors = vec![];
+    loop {
+        let _ = multispace0::<_, ErrMode<ContextError>>.parse_next(input);
+        if input.is_empty() {
+            break;
+        }
+        let start = input.checkpoint();
+        match ws(parse_top_level).parse_next(input) {
+            Ok(top_level) => top_levels.push(top_level),
+            Err(error) => {
+                errors.push(syntax_error(input, &error));
+                input.reset(&start);
+                let skipped = skip_item(input.peek_finish());
+                let _ = input.next_slice(skipped);
+            }
+        }
+    }
+    // Errors recovered from inside items come first in the source, but
+    // they're reported in order of position regardless.
+    errors.extend(input.state.take_errors());
+    errors.sort_by_key(|e| e.primary().map(|l| l.span.start));
+    (top_levels, errors)
 }
 
-fn parse_top_level(input: &mut &str) -> Result<TopLevel> {
+fn parse_top_level(input: &mut Input<'_>) -> ModalResult<TopLevel> {
     alt((
-        parse_custom_type_definition
+        spanned(parse_custom_type_definition)
             .context(StrContext::Label("CustomType"))
-            .map(TopLevel::CustomType),
-        preceded(ws(keyword("main")), parse_block)
+            .map(|(ct, span)| TopLevel::CustomType(ct.with_span(span))),
+        spanned(parse_trait)
+            .context(StrContext::Label("Trait"))
+            .map(|(td, span)| TopLevel::Trait(td.with_span(span))),
+        spanned(parse_trait_impl)
+            .context(StrContext::Label("Impl"))
+            .map(|(ti, span)| TopLevel::TraitImplementation(ti.with_span(span))),
+        spanned(preceded(ws(keyword("main")), parse_block))
             .context(StrContext::Label("EntryPoint"))
-            .map(TopLevel::EntryPoint),
-        parse_function_definition
-            .context(StrContext::Label("FunctionDef"))
-            .map(TopLevel::FunctionDefinition),
-        parse_function_impl
-            .context(StrContext::Label("FunctionImpl"))
-            .map(TopLevel::FunctionImplementation),
+            .map(|(block, span)| TopLevel::EntryPoint(block, span)),
+        // Anything else must start with a function name; checking that up front
+        // keeps unrecognisable input from being reported as a bad function.
+        preceded(
+            peek(parse_qualified_name),
+            alt((
+                spanned(parse_function_definition)
+                    .context(StrContext::Label("FunctionDef"))
+                    .map(|(fd, span)| TopLevel::FunctionDefinition(fd.with_span(span))),
+                spanned(parse_function_impl)
+                    .context(StrContext::Label("FunctionImpl"))
+                    .map(|(fi, span)| TopLevel::FunctionImplementation(fi.with_span(span))),
+            )),
+        ),
     ))
+    .context(StrContext::Label("TopLevel"))
     .parse_next(input)
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::test_input;
     use ast::top_level::TopLevel;
 
     use crate::top_level::{parse_top_level, parse_top_levels};
 
     #[test]
     fn test_parse_entrypoint() {
-        let mut input = "main { }";
+        let mut input = test_input("main { }");
         let parsed = parse_top_level(&mut input).unwrap();
-        assert!(matches!(parsed, TopLevel::EntryPoint(_)));
+        assert!(matches!(parsed, TopLevel::EntryPoint(..)));
     }
 
     #[test]
     fn test_parse_function_definition() {
-        let mut input = "func(MyType) -> U8;";
+        let mut input = test_input("func(MyType) -> U8;");
         let parsed = parse_top_level(&mut input).unwrap();
         assert!(matches!(parsed, TopLevel::FunctionDefinition(_)));
     }
 
     #[test]
     fn test_parse_function_impl() {
-        let mut input = "func(x) = 2;";
+        let mut input = test_input("func(x) = 2;");
         let parsed = parse_top_level(&mut input).unwrap();
         assert!(matches!(parsed, TopLevel::FunctionImplementation(_)));
     }
 
     #[test]
     fn test_parse_custom_type() {
-        let mut input = "type MyType { Var1, Var2 }";
+        let mut input = test_input("type MyType { Var1, Var2 }");
         let parsed = parse_top_level(&mut input).unwrap();
         assert!(matches!(parsed, TopLevel::CustomType(_)));
     }
 
     #[test]
     fn test_parse_top_levels() {
-        let mut input = "
+        let mut input = test_input(
+            "
         type MyType { Var1, Var2 }
 
         func(MyType) -> U8;
 
         func(x) = 2;
 
-        main {}";
-        let parsed = parse_top_levels(&mut input).unwrap();
+        main {}",
+        );
+        let (parsed, errors) = parse_top_levels(&mut input);
+        assert!(errors.is_empty(), "Unexpected errors: {errors:?}");
         assert!(input.is_empty(), "Did not parse all input: {input:?}");
         assert_eq!(parsed.len(), 4);
         assert!(matches!(parsed[0], TopLevel::CustomType(_)));
         assert!(matches!(parsed[1], TopLevel::FunctionDefinition(_)));
         assert!(matches!(parsed[2], TopLevel::FunctionImplementation(_)));
-        assert!(matches!(parsed[3], TopLevel::EntryPoint(_)));
+        assert!(matches!(parsed[3], TopLevel::EntryPoint(..)));
+    }
+
+    #[test]
+    fn test_top_level_spans_exclude_trailing_whitespace() {
+        let source = "func(MyType) -> U8;\nfunc(x) {\n  2\n}\n\n";
+        let mut input = test_input(source);
+        let (parsed, _) = parse_top_levels(&mut input);
+        let TopLevel::FunctionImplementation(fi) = &parsed[1] else {
+            panic!("Expected a function implementation");
+        };
+        assert_eq!(&source[fi.span().range()], "func(x) {\n  2\n}");
     }
 }
@@ -0,0 +1,165 @@
+use ast::{
+    diagnostics::{Diagnostic, codes},
+    functions::{FunctionDeclaration, FunctionImplementation},
+    span::Span,
+    traits::{TraitDeclaration, TraitImplementation},
+    types::Type,
+};
+use winnow::{
+    ModalResult, Parser,
+    combinator::{cut_err, delimited, repeat},
+    error::{StrContext, StrContextValue},
+};
+
+use crate::{
+    Input,
+    functions::{parse_function_definition, parse_function_impl},
+    identifiers::parse_identifier_upper,
+    keyword, spanned,
+    types::parse_type,
+    ws,
+};
+
+/// `trait Show { show(Self) -> String; }`
+pub fn parse_trait(input: &mut Input<'_>) -> ModalResult<TraitDeclaration> {
+    let _ = ws(keyword("trait")).parse_next(input)?;
+    cut_err(parse_trait_rest).parse_next(input)
+}
+
+/// Everything after the `trait` keyword, which commits to a trait.
+fn parse_trait_rest(input: &mut Input<'_>) -> ModalResult<TraitDeclaration> {
+    let name = ws(parse_identifier_upper)
+        .context(StrContext::Expected(StrContextValue::Description(
+            "trait name",
+        )))
+        .parse_next(input)?;
+    let methods: Vec<FunctionDeclaration> = delimited(
+        ws('{'),
+        repeat(
+            0..,
+            ws(spanned(parse_function_definition).map(|(fd, span)| fd.with_span(span))),
+        ),
+        ws('}').context(StrContext::Expected(StrContextValue::CharLiteral('}'))),
+    )
+    .context(StrContext::Label("methods"))
+    .parse_next(input)?;
+    Ok(TraitDeclaration::new(name, methods))
+}
+
+/// `impl Show for Bool { show(True) = "True"; show(False) = "False"; }`
+pub fn parse_trait_impl(input: &mut Input<'_>) -> ModalResult<TraitImplementation> {
+    let _ = ws(keyword("impl")).parse_next(input)?;
+    cut_err(parse_trait_impl_rest).parse_next(input)
+}
+
+/// Everything after the `impl` keyword, which commits to an implementation.
+fn parse_trait_impl_rest(input: &mut Input<'_>) -> ModalResult<TraitImplementation> {
+    let trait_name = ws(parse_identifier_upper)
+        .context(StrContext::Expected(StrContextValue::Description(
+            "trait name",
+        )))
+        .parse_next(input)?;
+    let _ = ws(keyword("for"))
+        .context(StrContext::Expected(StrContextValue::StringLiteral("for")))
+        .parse_next(input)?;
+    let (target, span) = ws(spanned(parse_type))
+        .context(StrContext::Expected(StrContextValue::Description(
+            "type name",
+        )))
+        .parse_next(input)?;
+    let type_name = match target {
+        Type::Custom(name, arguments) => {
+            if !arguments.is_empty() {
+                input.state.report(
+                    bare_type_expected(&Type::Custom(name.clone(), arguments), span).with_note(
+                        format!(
+                            "`impl {trait_name} for {name}` covers `{name}` with any type arguments"
+                        ),
+                    ),
+                );
+            }
+            name
+        }
+        Type::Function(_) | Type::Compound(_) => {
+            input.state.report(bare_type_expected(&target, span));
+            target.to_string()
+        }
+        primitive => primitive.to_string(),
+    };
+    let methods: Vec<FunctionImplementation> = delimited(
+        ws('{'),
+        repeat(
+            0..,
+            ws(spanned(parse_function_impl).map(|(fi, span)| fi.with_span(span))),
+        ),
+        ws('}').context(StrContext::Expected(StrContextValue::CharLiteral('}'))),
+    )
+    .context(StrContext::Label("methods"))
+    .parse_next(input)?;
+    Ok(TraitImplementation::new(trait_name, &type_name, methods))
+}
+
+/// Traits are implemented for a type by its name, so only a bare name can
+/// follow `for`.
+fn bare_type_expected(target: &Type, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("cannot implement a trait for `{target}`"))
+        .with_code(codes::SYNTAX)
+        .with_primary(span, "expected a type name, without type arguments")
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::traits::Bound;
+
+    use super::*;
+    use crate::{program::parse_program, test_input};
+
+    #[test]
+    fn test_parse_trait() {
+        let mut input =
+            test_input("trait Show {\n    show(Self) -> String;\n    width(Self) -> U8;\n}");
+        let parsed = parse_trait(&mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(parsed.name(), "Show");
+        let methods: Vec<_> = parsed.methods().iter().map(|fd| fd.name()).collect();
+        assert_eq!(methods, ["show", "width"]);
+    }
+
+    #[test]
+    fn test_parse_trait_impl() {
+        let mut input = test_input(
+            "impl Show for Bool {\n    show(True) = \"yes\";\n    show(False) = \"no\";\n}",
+        );
+        let parsed = parse_trait_impl(&mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!((parsed.trait_name(), parsed.type_name()), ("Show", "Bool"));
+        assert_eq!(parsed.methods().len(), 2);
+    }
+
+    #[test]
+    fn test_impl_needs_a_bare_type_name() {
+        let source = "impl Show for Option<U8> { show(_) = \"\"; }\n\
+                      impl Show for [U8] { show(_) = \"\"; }\nmain { 1 }";
+        let errors = parse_program(source, Default::default()).unwrap_err();
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "cannot implement a trait for `Option<U8>`",
+                "cannot implement a trait for `[U8]`"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        let source = "describe<A: Show + Eq, B>(A, B) -> String;\nmain { 1 }";
+        let program = parse_program(source, Default::default()).unwrap();
+        let fd = &program.function_declarations[0];
+        assert_eq!(fd.type_params(), ["A", "B"]);
+        assert_eq!(
+            fd.bounds(),
+            [Bound::new("A", "Show"), Bound::new("A", "Eq")]
+        );
+    }
+}
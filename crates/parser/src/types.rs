@@ -1,20 +1,24 @@
+use crate::Input;
 use crate::custom_types::parse_custom_type_generics;
 use crate::functions::parse_function_signature;
 use crate::identifiers::parse_identifier_upper;
+use crate::ws;
 use ast::types::Type;
-use winnow::combinator::{alt, opt};
-use winnow::{Parser, Result, ascii::alphanumeric1};
+use winnow::combinator::{alt, delimited, opt, separated};
+use winnow::{ModalResult, Parser, ascii::alphanumeric1};
 
-pub fn parse_type(input: &mut &str) -> Result<Type> {
+pub fn parse_type(input: &mut Input<'_>) -> ModalResult<Type> {
     alt((
         parse_function_signature.map(Type::Function),
+        parse_tuple_type,
+        parse_list_type,
         parse_primitive_type,
         parse_custom_type,
     ))
     .parse_next(input)
 }
 
-pub fn parse_primitive_type(input: &mut &str) -> Result<Type> {
+pub fn parse_primitive_type(input: &mut Input<'_>) -> ModalResult<Type> {
     alphanumeric1
         .verify_map(|s: &str| match s {
             "I8" => Some(Type::I8),
@@ -35,7 +39,21 @@ pub fn parse_primitive_type(input: &mut &str) -> Result<Type> {
         .parse_next(input)
 }
 
-pub fn parse_custom_type(input: &mut &str) -> Result<Type> {
+/// Example: `[U8]`
+pub fn parse_list_type(input: &mut Input<'_>) -> ModalResult<Type> {
+    delimited('[', ws(parse_type), ']')
+        .map(Type::list)
+        .parse_next(input)
+}
+
+/// Example: `(U8, Bool)`
+pub fn parse_tuple_type(input: &mut Input<'_>) -> ModalResult<Type> {
+    delimited('(', separated(2.., ws(parse_type), ','), ')')
+        .map(Type::tuple)
+        .parse_next(input)
+}
+
+pub fn parse_custom_type(input: &mut Input<'_>) -> ModalResult<Type> {
     let name = parse_identifier_upper.parse_next(input)?;
     let generics = opt(parse_custom_type_generics)
         .parse_next(input)?
@@ -46,24 +64,46 @@ pub fn parse_custom_type(input: &mut &str) -> Result<Type> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_input;
 
     #[test]
     fn test_parse_primitive_type() {
-        let mut input = "U8";
+        let mut input = test_input("U8");
 
-        let (_, parsed) = parse_primitive_type.parse_peek(input).unwrap();
+        let (_, parsed) = parse_primitive_type.parse_peek(input.clone()).unwrap();
         assert_eq!(parsed, Type::U8);
 
         let parsed = parse_type(&mut input).unwrap();
         assert_eq!(parsed, Type::U8);
     }
 
+    #[test]
+    fn test_parse_tuple_type() {
+        let mut input = test_input("(U8, (Bool, [I8]))");
+        let expected = Type::tuple(vec![
+            Type::U8,
+            Type::tuple(vec![Type::Bool, Type::list(Type::I8)]),
+        ]);
+        assert_eq!(parse_type(&mut input).unwrap(), expected);
+        assert_eq!(expected.to_string().parse(), Ok(expected));
+    }
+
+    #[test]
+    fn test_parse_list_type() {
+        let mut input = test_input("[[Option<A>]]");
+        let expected = Type::list(Type::list(Type::Custom(
+            "Option".to_owned(),
+            vec!["A".to_owned()],
+        )));
+        assert_eq!(parse_type(&mut input).unwrap(), expected);
+    }
+
     #[test]
     fn test_parse_type_custom() {
-        let mut input = "CustomType";
+        let mut input = test_input("CustomType");
         let expected = Type::Custom("CustomType".to_owned(), vec![]);
 
-        let (_, parsed) = parse_custom_type.parse_peek(input).unwrap();
+        let (_, parsed) = parse_custom_type.parse_peek(input.clone()).unwrap();
         assert_eq!(parsed, expected);
 
         let parsed = parse_type(&mut input).unwrap();
@@ -72,10 +112,10 @@ mod tests {
 
     #[test]
     fn test_parse_type_custom_with_generics() {
-        let mut input = "Option<A>";
+        let mut input = test_input("Option<A>");
         let expected = Type::Custom("Option".to_owned(), vec!["A".to_owned()]);
 
-        let (_, parsed) = parse_custom_type.parse_peek(input).unwrap();
+        let (_, parsed) = parse_custom_type.parse_peek(input.clone()).unwrap();
         assert_eq!(parsed, expected);
 
         let parsed = parse_type(&mut input).unwrap();